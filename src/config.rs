@@ -1,10 +1,14 @@
+#[cfg(feature = "online")]
 use bdk::blockchain::esplora::{EsploraBlockchain, EsploraBlockchainConfig, EsploraError};
+#[cfg(feature = "online")]
 use bdk::blockchain::ConfigurableBlockchain;
 
 use bitcoin::network::constants::Network;
 
+#[cfg(feature = "online")]
 use crate::e::{ErrorKind, S5Error};
 
+#[cfg(feature = "online")]
 pub struct WalletConfig {
     pub deposit_desc: String,
     pub change_desc: String,
@@ -12,16 +16,35 @@ pub struct WalletConfig {
     pub client: EsploraBlockchain,
 }
 
+#[cfg(feature = "online")]
 pub enum BlockchainBackend {
     Electrum,
     Esplora,
     Rpc,
 }
 
+/// Change descriptor for a deposit descriptor, using the /1/* branch.
+pub fn change_descriptor(deposit_desc: &str) -> String {
+    deposit_desc.replace("/0/*", "/1/*")
+}
+
+/// Mainnet keys are xpub/xprv, everything else is treated as testnet.
+pub fn descriptor_network(deposit_desc: &str) -> Network {
+    if deposit_desc.contains("xpub") || deposit_desc.contains("xprv") {
+        Network::Bitcoin
+    } else {
+        Network::Testnet
+    }
+}
+
+#[cfg(feature = "online")]
 pub const DEFAULT: &str = "default";
+#[cfg(feature = "online")]
 pub const DEFAULT_TESTNET_NODE: &str = "ssl://electrum.blockstream.info:60002";
+#[cfg(feature = "online")]
 pub const DEFAULT_MAINNET_NODE: &str = "ssl://electrum.blockstream.info:50002";
 
+#[cfg(feature = "online")]
 impl WalletConfig {
     pub fn new(
         deposit_desc: &str,
@@ -29,14 +52,8 @@ impl WalletConfig {
         node_address: &str,
         socks5: Option<String>,
     ) -> Result<Self, S5Error> {
-        let change_desc: &str = &deposit_desc.replace("/0/*", "/1/*");
-        let network = if <&str>::clone(&deposit_desc).contains("xpub")
-            || <&str>::clone(&deposit_desc).contains("xprv")
-        {
-            Network::Bitcoin
-        } else {
-            Network::Testnet
-        };
+        let change_desc: &str = &change_descriptor(deposit_desc);
+        let network = descriptor_network(deposit_desc);
 
         let node_address = if node_address.contains(DEFAULT) {
             match network {
@@ -135,7 +152,7 @@ impl WalletConfig {
 //   }
 // }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "online"))]
 mod tests {
     use super::*;
     use crate::config::WalletConfig;
@@ -40,6 +40,7 @@ pub mod e;
 use e::{ErrorKind, S5Error};
 
 mod config;
+#[cfg(feature = "online")]
 use crate::config::{
     BlockchainBackend, WalletConfig, DEFAULT, DEFAULT_MAINNET_NODE, DEFAULT_TESTNET_NODE,
 };
@@ -50,14 +51,44 @@ use crate::key::master;
 
 pub mod wallet;
 use crate::wallet::address;
+use crate::wallet::bip21;
+use crate::wallet::bsms;
+use crate::wallet::coldcard;
+use crate::wallet::descriptor;
+use crate::wallet::export;
+#[cfg(feature = "online")]
 use crate::wallet::history;
+use crate::wallet::labels;
 use crate::wallet::policy;
+#[cfg(feature = "online")]
+use crate::wallet::precheck;
+use crate::wallet::progress;
 use crate::wallet::psbt;
+#[cfg(feature = "online")]
+use crate::wallet::report;
+#[cfg(feature = "online")]
+use crate::wallet::store::WalletStore;
+use crate::wallet::utxo;
 
 pub mod network;
+#[cfg(feature = "online")]
+use crate::network::exchange;
 use crate::network::fees;
 use crate::network::height;
 
+pub mod qr;
+use crate::qr::bbqr;
+use crate::qr::ur;
+
+/// Reads a descriptor input, checking and removing its checksum if it has one.
+/// Every function taking a descriptor goes through this, so a mismatched checksum is always rejected.
+unsafe fn checked_descriptor<'a>(descriptor: *const c_char, label: &str) -> Result<&'a str, S5Error> {
+    match CStr::from_ptr(descriptor).to_str() {
+        Ok(string) => descriptor::strip_checksum(string),
+        Err(_) => Err(S5Error::new(ErrorKind::Input, label)),
+    }
+}
+
 /// Generates a mnemonic phrase of a given length. Defaults to 24 words.
 /// A master xprv is created from the mnemonic and passphrase.
 /// - *OUTPUT*
@@ -255,25 +286,422 @@ pub unsafe extern "C" fn compile(policy: *const c_char, script_type: *const c_ch
     }
 }
 
+/// Computes the checksum of a descriptor. Any checksum already on the descriptor is replaced.
+/// Every function taking a descriptor accepts it with or without a checksum, and rejects a checksum that does not match.
+/// - *OUTPUT*
+/// ```
+/// DescriptorChecksum {
+///   descriptor: String,
+///   checksum: String,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn descriptor_checksum(descriptor: *const c_char) -> *mut c_char {
+    let descriptor_cstr = CStr::from_ptr(descriptor);
+    let descriptor: &str = match descriptor_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Descriptor").c_stringify(),
+    };
+
+    match descriptor::checksum(descriptor) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Parses a descriptor, checking its checksum if it has one.
+/// network is None for descriptors with only single keys.
+/// - *OUTPUT*
+/// ```
+/// DescriptorInfo {
+///   descriptor: String,
+///   checksum: String,
+///   network: Option<String>,
+///   script_type: String,
+///   key_origins: Vec<KeyOrigin {
+///     fingerprint: Option<String>,
+///     path: Option<String>,
+///     is_private: bool,
+///   }>,
+///   has_private_keys: bool,
+///   is_ranged: bool,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn validate_descriptor(descriptor: *const c_char) -> *mut c_char {
+    let descriptor_cstr = CStr::from_ptr(descriptor);
+    let descriptor: &str = match descriptor_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Descriptor").c_stringify(),
+    };
+
+    match descriptor::validate(descriptor) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Imports the generic JSON export of a ColdCard (Advanced > Export Wallet > Generic JSON).
+/// Returns watch-only deposit descriptors, each checked to derive the first address in the export.
+/// - *OUTPUT*
+/// ```
+/// ColdCardDescriptors {
+///   network: String,
+///   fingerprint: String,
+///   bip44: String,
+///   bip49: String,
+///   bip84: String,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn import_coldcard(json: *const c_char) -> *mut c_char {
+    let json_cstr = CStr::from_ptr(json);
+    let json: &str = match json_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "ColdCard-JSON").c_stringify(),
+    };
+
+    match coldcard::import(json) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Creates the ColdCard multisig setup file for a sortedmulti deposit descriptor, to register the wallet on each ColdCard.
+/// Every key needs an origin and a /0/* path. name is at most 20 ASCII characters.
+/// - *OUTPUT*
+/// ```
+/// ColdCardMultisig {
+///   file: String,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn export_coldcard_multisig(
+    name: *const c_char,
+    deposit_desc: *const c_char,
+) -> *mut c_char {
+    let name_cstr = CStr::from_ptr(name);
+    let name: &str = match name_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Wallet-Name").c_stringify(),
+    };
+
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    match coldcard::multisig_setup(name, deposit_desc) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Exports a watch-only wallet for another app. format is "core" (importdescriptors JSON), "specter", "sparrow" or "electrum".
+/// timestamp (unix seconds) sets where Core rescans from and blockheight where Specter rescans from. Empty string rescans everything.
+/// name labels the wallet in Specter and Sparrow. Electrum only supports single signature and sortedmulti wallets.
+/// - *OUTPUT*
+/// ```
+/// WalletExport {
+///   format: String,
+///   data: String,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn export_wallet(
+    deposit_desc: *const c_char,
+    format: *const c_char,
+    name: *const c_char,
+    timestamp: *const c_char,
+    blockheight: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let format_cstr = CStr::from_ptr(format);
+    let format: export::WalletFormat = match format_cstr.to_str() {
+        Ok(string) => match string.parse() {
+            Ok(result) => result,
+            Err(e) => return e.c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Wallet-Format").c_stringify(),
+    };
+
+    let name_cstr = CStr::from_ptr(name);
+    let name: &str = match name_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Wallet-Name").c_stringify(),
+    };
+
+    let timestamp_cstr = CStr::from_ptr(timestamp);
+    let timestamp: u64 = match timestamp_cstr.to_str() {
+        Ok("") => 0,
+        Ok(string) => match string.parse::<u64>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Timestamp").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Timestamp").c_stringify(),
+    };
+
+    let blockheight_cstr = CStr::from_ptr(blockheight);
+    let blockheight: u32 = match blockheight_cstr.to_str() {
+        Ok("") => 0,
+        Ok(string) => match string.parse::<u32>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Blockheight").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Blockheight").c_stringify(),
+    };
+
+    match export::export(deposit_desc, format, name, timestamp, blockheight) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Imports a wallet file from another app: Core importdescriptors or listdescriptors JSON,
+/// Specter or Sparrow wallet JSON, or an unencrypted Electrum wallet file. The format is detected.
+/// Core wallets can hold several wallets; descriptors this library cannot use (eg. tr) are skipped.
+/// - *OUTPUT*
+/// ```
+/// ImportedWallets {
+///   wallets: Vec<ImportedWallet>,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn import_wallet(data: *const c_char) -> *mut c_char {
+    let data_cstr = CStr::from_ptr(data);
+    let data: &str = match data_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Wallet-File").c_stringify(),
+    };
+
+    match export::import(data) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Creates a BSMS (BIP129) TOKEN for one signer. mode is "none" (token 00, no encryption), "standard" or "extended".
+/// The coordinator gives each signer its own TOKEN.
+/// - *OUTPUT*
+/// ```
+/// BsmsToken {
+///   token: String,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn bsms_token(mode: *const c_char) -> *mut c_char {
+    let mode_cstr = CStr::from_ptr(mode);
+    let mode: &str = match mode_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "BSMS-Mode").c_stringify(),
+    };
+
+    match bsms::token(mode) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Creates a signed BSMS key record for the BIP48 key at m/48'/coin'/account'/script_type' (2' for wsh, 1' for sh-wsh).
+/// The record is encrypted with token unless it is 00. description is one line of at most 80 characters.
+/// Keep xpub to check the coordinator's descriptor record with bsms_verify.
+/// - *OUTPUT*
+/// ```
+/// BsmsRecord {
+///   record: String,
+///   xpub: String,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn bsms_key_record(
+    master_xprv: *const c_char,
+    script_type: *const c_char,
+    account: *const c_char,
+    token: *const c_char,
+    description: *const c_char,
+) -> *mut c_char {
+    let master_xprv_cstr = CStr::from_ptr(master_xprv);
+    let master_xprv: &str = match master_xprv_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Master-Key").c_stringify(),
+    };
+
+    let script_type_cstr = CStr::from_ptr(script_type);
+    let script_type: &str = match script_type_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Script-Type").c_stringify(),
+    };
+
+    let account_cstr = CStr::from_ptr(account);
+    let account: &str = match account_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Account").c_stringify(),
+    };
+
+    let token_cstr = CStr::from_ptr(token);
+    let token: &str = match token_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "BSMS-Token").c_stringify(),
+    };
+
+    let description_cstr = CStr::from_ptr(description);
+    let description: &str = match description_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Description").c_stringify(),
+    };
+
+    match bsms::key_record(master_xprv, script_type, account, token, description) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Coordinates a BSMS multisig from the signers' key records, checking each record's signature.
+/// submissions is a JSON array of {"token": String, "record": String}. script_type is wsh, sh-wsh or sh.
+/// records holds the descriptor record for each submission, in the same order, to send back to its signer.
+/// - *OUTPUT*
+/// ```
+/// BsmsSetup {
+///   deposit_desc: String,
+///   template: String,
+///   first_address: String,
+///   records: Vec<String>,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn bsms_coordinate(
+    threshold: *const c_char,
+    script_type: *const c_char,
+    submissions: *const c_char,
+) -> *mut c_char {
+    let threshold_cstr = CStr::from_ptr(threshold);
+    let threshold: usize = match threshold_cstr.to_str() {
+        Ok(string) => match string.parse::<usize>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Threshold").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Threshold").c_stringify(),
+    };
+
+    let script_type_cstr = CStr::from_ptr(script_type);
+    let script_type: &str = match script_type_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Script-Type").c_stringify(),
+    };
+
+    let submissions_cstr = CStr::from_ptr(submissions);
+    let submissions: Vec<bsms::BsmsSubmission> = match submissions_cstr.to_str() {
+        Ok(string) => match serde_json::from_str(string) {
+            Ok(result) => result,
+            Err(_) => return S5Error::new(ErrorKind::Input, "BSMS-Submissions").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "BSMS-Submissions").c_stringify(),
+    };
+
+    match bsms::coordinate(threshold, script_type, &submissions) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Verifies the BSMS descriptor record a signer received: it must contain the xpub bsms_key_record returned
+/// and derive the first address it lists. Show first_address to the user to compare with the other signers.
+/// - *OUTPUT*
+/// ```
+/// BsmsWallet {
+///   deposit_desc: String,
+///   change_desc: String,
+///   first_address: String,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn bsms_verify(
+    record: *const c_char,
+    token: *const c_char,
+    xpub: *const c_char,
+) -> *mut c_char {
+    let record_cstr = CStr::from_ptr(record);
+    let record: &str = match record_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "BSMS-Record").c_stringify(),
+    };
+
+    let token_cstr = CStr::from_ptr(token);
+    let token: &str = match token_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "BSMS-Token").c_stringify(),
+    };
+
+    let xpub_cstr = CStr::from_ptr(xpub);
+    let xpub: &str = match xpub_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Xpub").c_stringify(),
+    };
+
+    match bsms::verify_descriptor_record(record, token, xpub) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
 /// Syncs to a remote node and fetches balance of a descriptor wallet.
+/// Utxos frozen in the wallet store at db_path are reported as a separate frozen balance.
+/// Use an empty db_path to skip the wallet store.
 /// - *OUTPUT*
 /// ```
 /// WalletBalance {
-///   balance: u64
+///   balance: u64,
+///   spendable: u64,
+///   frozen: u64,
 /// }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
 #[no_mangle]
 pub async unsafe extern "C" fn sync_balance(
     deposit_desc: *const c_char,
     node_address: *const c_char,
+    db_path: *const c_char,
 ) -> *mut c_char {
-    let deposit_desc_cstr = CStr::from_ptr(deposit_desc);
-    let deposit_desc: &str = match deposit_desc_cstr.to_str() {
-        Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
     };
 
     let node_address_cstr = CStr::from_ptr(node_address);
@@ -297,39 +725,73 @@ pub async unsafe extern "C" fn sync_balance(
         Ok(conf) => conf,
         Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
     };
-    match history::sync_balance(config).await {
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    let store = match WalletStore::load(db_path, deposit_desc) {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
+    };
+
+    match history::sync_balance(config, &store).await {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
 }
 
 /// Syncs to a remote node and fetches history of a descriptor wallet.
+/// Transaction labels are read from the wallet store at db_path.
 /// - *OUTPUT*
 /// ```
 ///  WalletHistory{
 ///    history: Vec<Transaction {
 ///      timestamp: u64,
 ///      height: u32,
+///      status: "pending" | "confirmed",
+///      confirmations: u32,
 ///      verified: bool,
 ///      txid: String,
-///     received: u64,
+///      received: u64,
 ///      sent: u64,
 ///      fee: u64,
+///      vsize: usize,
+///      fee_rate: f32,
+///      rbf: bool,
+///      direction: "incoming" | "outgoing" | "self_transfer" | "consolidation",
+///      inputs: Vec<TxInput {
+///        previous_outpoint: String,
+///        address: Option<String>,
+///        value: Option<u64>,
+///        sequence: u32,
+///        is_mine: bool,
+///      }>,
+///      outputs: Vec<TxOutput {
+///        vout: u32,
+///        address: String,
+///        value: u64,
+///        is_mine: bool,
+///        keychain: Option<"external" | "internal">,
+///      }>,
+///      label: Option<String>,
 ///    }>;
 ///  }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
 #[no_mangle]
 pub async unsafe extern "C" fn sync_history(
     deposit_desc: *const c_char,
     node_address: *const c_char,
+    db_path: *const c_char,
 ) -> *mut c_char {
-    let deposit_desc_cstr = CStr::from_ptr(deposit_desc);
-    let deposit_desc: &str = match deposit_desc_cstr.to_str() {
-        Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
     };
 
     let node_address_cstr = CStr::from_ptr(node_address);
@@ -353,33 +815,51 @@ pub async unsafe extern "C" fn sync_history(
         Ok(conf) => conf,
         Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
     };
-    match history::sync_history(config).await {
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    let store = match WalletStore::load(db_path, deposit_desc) {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
+    };
+
+    match history::sync_history(config, &store).await {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
 }
 
-/// Gets a new address for a descriptor wallet at a given index.
-/// Client must keep track of address indexes and ENSURE prevention of address reuse.
+/// Syncs to a remote node and lists the unspent outputs of a descriptor wallet.
+/// Frozen status and labels are read from the wallet store at db_path.
 /// - *OUTPUT*
 /// ```
-/// WalletAddress {
-///   address: String,
+/// WalletUtxos {
+///   utxos: Vec<WalletUtxo {
+///     outpoint: String,
+///     value: u64,
+///     address: String,
+///     keychain: "external" | "internal",
+///     frozen: bool,
+///     label: Option<String>,
+///   }>
 /// }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
 #[no_mangle]
-pub async unsafe extern "C" fn get_address(
+pub async unsafe extern "C" fn list_unspent(
     deposit_desc: *const c_char,
     node_address: *const c_char,
-    index: *const c_char,
+    db_path: *const c_char,
 ) -> *mut c_char {
-    let deposit_desc_cstr = CStr::from_ptr(deposit_desc);
-    let deposit_desc: &str = match deposit_desc_cstr.to_str() {
-        Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
     };
 
     let node_address_cstr = CStr::from_ptr(node_address);
@@ -394,7 +874,18 @@ pub async unsafe extern "C" fn get_address(
         Err(_) => DEFAULT,
     };
 
-    let config = match WalletConfig::new(
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    let store = match WalletStore::load(db_path, deposit_desc) {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let config = match WalletConfig::new(
         deposit_desc,
         BlockchainBackend::Electrum,
         node_address,
@@ -403,207 +894,179 @@ pub async unsafe extern "C" fn get_address(
         Ok(conf) => conf,
         Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
     };
-
-    let index_cstr = CStr::from_ptr(index);
-    let address_index: u32 = match index_cstr.to_str() {
-        Ok(string) => match string.parse::<u32>() {
-            Ok(i) => i,
-            Err(_) => {
-                return CString::new("Error: Address Index Input.")
-                    .unwrap()
-                    .into_raw()
-            }
-        },
-        Err(_) => return S5Error::new(ErrorKind::Input, "Address-Index").c_stringify(),
-    };
-
-    match address::generate(config, address_index).await {
+    match utxo::list_unspent(config, &store).await {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
 }
 
-/// Gets the current network fee (in sats/vbyte) for a given confirmation target.
+/// Freezes a utxo (txid:vout) so that it is never used by build_tx, including sweeps.
+/// The frozen status is saved in the wallet store at db_path.
 /// - *OUTPUT*
-/// ```  
-///  NetworkFee {
-///    rate: f32,
-///    absolute: Option<u64>,
-///  }
+/// ```
+/// UtxoStatus {
+///   outpoint: String,
+///   frozen: bool,
+///   label: Option<String>,
+/// }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
 #[no_mangle]
-pub async unsafe extern "C" fn estimate_network_fee(
-    network: *const c_char,
-    node_address: *const c_char,
-    conf_target: *const c_char,
+pub unsafe extern "C" fn freeze_utxo(
+    deposit_desc: *const c_char,
+    db_path: *const c_char,
+    outpoint: *const c_char,
 ) -> *mut c_char {
-    let conf_target_cstr = CStr::from_ptr(conf_target);
-    let conf_target_int: usize = match conf_target_cstr.to_str() {
-        Ok(string) => string.parse::<usize>().unwrap_or(6),
-        Err(_) => 6,
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
     };
 
-    let network_cstr = CStr::from_ptr(network);
-    let network: &str = match network_cstr.to_str() {
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => "test",
-    };
-    let network_enum = match network {
-        "main" => Network::Bitcoin,
-        _ => Network::Testnet,
-    };
-    let node_address_cstr = CStr::from_ptr(node_address);
-    let node_address: &str = match node_address_cstr.to_str() {
-        Ok(string) => {
-            if string == DEFAULT {
-                match network_enum {
-                    Network::Bitcoin => DEFAULT_MAINNET_NODE,
-                    _ => DEFAULT_TESTNET_NODE,
-                }
-            } else {
-                string
-            }
-        }
-        Err(_) => match network_enum {
-            Network::Bitcoin => DEFAULT_MAINNET_NODE,
-            _ => DEFAULT_TESTNET_NODE,
-        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
     };
 
-    let config = match WalletConfig::new("/0/*", BlockchainBackend::Electrum, node_address, None) {
-        Ok(conf) => conf,
-        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
+    let outpoint_cstr = CStr::from_ptr(outpoint);
+    let outpoint: &str = match outpoint_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Outpoint").c_stringify(),
     };
-    match fees::estimate_rate(config, conf_target_int).await {
+
+    match utxo::freeze(db_path, deposit_desc, outpoint, true) {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
 }
 
-/// Converts a given fee_rate (in sats/vbyte) to absolute fee (in sats); given some transaction weight.
+/// Unfreezes a utxo (txid:vout) making it available for coin selection again.
 /// - *OUTPUT*
-/// ```  
-///  NetworkFee {
-///    rate: f32,
-///    absolute: Option<u64>,
-///  }
+/// ```
+/// UtxoStatus {
+///   outpoint: String,
+///   frozen: bool,
+///   label: Option<String>,
+/// }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
 #[no_mangle]
-pub unsafe extern "C" fn fee_rate_to_absolute(
-    fee_rate: *const c_char,
-    weight: *const c_char,
+pub unsafe extern "C" fn unfreeze_utxo(
+    deposit_desc: *const c_char,
+    db_path: *const c_char,
+    outpoint: *const c_char,
 ) -> *mut c_char {
-    let weight_cstr = CStr::from_ptr(weight);
-    let weight_usize: usize = match weight_cstr.to_str() {
-        Ok(string) => string.parse::<usize>().unwrap_or(250),
-        Err(_) => 250,
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
     };
 
-    let fee_rate_cstr = CStr::from_ptr(fee_rate);
-    let fee_rate_f32: f32 = match fee_rate_cstr.to_str() {
-        Ok(string) => string.parse::<f32>().unwrap_or(1.0),
-        Err(_) => 1.0,
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
     };
 
-    fees::get_absolute(fee_rate_f32, weight_usize).c_stringify()
+    let outpoint_cstr = CStr::from_ptr(outpoint);
+    let outpoint: &str = match outpoint_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Outpoint").c_stringify(),
+    };
+
+    match utxo::freeze(db_path, deposit_desc, outpoint, false) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
 }
 
-/// Converts a given absolute_fee (in sats) to fee rate (in sats/vbyte); given some transaction weight.
+/// Attaches a label to a utxo (txid:vout). Use an empty label to remove it.
 /// - *OUTPUT*
-/// ```  
-///  NetworkFee {
-///    rate: f32,
-///    absolute: Option<u64>,
-///  }
+/// ```
+/// UtxoStatus {
+///   outpoint: String,
+///   frozen: bool,
+///   label: Option<String>,
+/// }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
 #[no_mangle]
-pub unsafe extern "C" fn fee_absolute_to_rate(
-    fee_absolute: *const c_char,
-    weight: *const c_char,
+pub unsafe extern "C" fn label_utxo(
+    deposit_desc: *const c_char,
+    db_path: *const c_char,
+    outpoint: *const c_char,
+    label: *const c_char,
 ) -> *mut c_char {
-    let weight_cstr = CStr::from_ptr(weight);
-    let weight_usize: usize = match weight_cstr.to_str() {
-        Ok(string) => string.parse::<usize>().unwrap_or(250),
-        Err(_) => 250,
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
     };
 
-    let fee_absolute_cstr = CStr::from_ptr(fee_absolute);
-    let fee_absolute_u64: u64 = match fee_absolute_cstr.to_str() {
-        Ok(string) => string.parse::<u64>().unwrap_or(1000),
-        Err(_) => 1000,
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
     };
 
-    fees::get_rate(fee_absolute_u64, weight_usize).c_stringify()
-}
-
-/// Gets the weight of a transaction built with a given deposit-descriptor.
-/// - *OUTPUT*
-/// ```  
-///  TransactionWeight {
-///     weight: usize,
-///  }
-/// ```
-/// # Safety
-/// - This function is unsafe because it dereferences and a returns raw pointer.
-/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
-#[no_mangle]
-pub unsafe extern "C" fn get_weight(
-    deposit_desc: *const c_char,
-    psbt: *const c_char,
-) -> *mut c_char {
-    let deposit_desc_cstr = CStr::from_ptr(deposit_desc);
-    let deposit_desc: &str = match deposit_desc_cstr.to_str() {
+    let outpoint_cstr = CStr::from_ptr(outpoint);
+    let outpoint: &str = match outpoint_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
+        Err(_) => return S5Error::new(ErrorKind::Input, "Outpoint").c_stringify(),
     };
 
-    let psbt_cstr = CStr::from_ptr(psbt);
-    let psbt: &str = match psbt_cstr.to_str() {
+    let label_cstr = CStr::from_ptr(label);
+    let label: &str = match label_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+        Err(_) => return S5Error::new(ErrorKind::Input, "Label").c_stringify(),
     };
 
-    match psbt::get_weight(deposit_desc, psbt) {
+    match utxo::label(db_path, deposit_desc, outpoint, label) {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
 }
 
-/// Builds a transaction for a given descriptor wallet.
-/// If sweep is set to true, amount value is ignored and will default to None.
-/// Set amount to 0 for sweep.
+/// Syncs to a remote node and exports the history of a descriptor wallet for accounting.
+/// format is one of "csv", "koinly" or "cointracker". Defaults to "csv".
+/// prices is an optional JSON price series used to add the fiat value at the time of each transaction.
+/// Use an empty string to skip fiat values.
+/// ```
+/// PriceSeries {
+///   currency: String,
+///   prices: Vec<PricePoint {
+///     timestamp: u64,
+///     price: f64,
+///   }>
+/// }
+/// ```
 /// - *OUTPUT*
 /// ```
-///  WalletPSBT {
-///    pub psbt: String,
-///    pub is_finalized: bool,
-///  }
+/// HistoryReport {
+///   format: "csv" | "koinly" | "cointracker",
+///   rows: usize,
+///   data: String,
+/// }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
 #[no_mangle]
-pub async unsafe extern "C" fn build_tx(
+pub async unsafe extern "C" fn export_history(
     deposit_desc: *const c_char,
     node_address: *const c_char,
-    to_address: *const c_char,
-    amount: *const c_char,
-    fee_absolute: *const c_char,
-    sweep: *const c_char,
+    db_path: *const c_char,
+    format: *const c_char,
+    prices: *const c_char,
 ) -> *mut c_char {
-    let deposit_desc_cstr = CStr::from_ptr(deposit_desc);
-    let deposit_desc: &str = match deposit_desc_cstr.to_str() {
-        Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
     };
 
     let node_address_cstr = CStr::from_ptr(node_address);
@@ -618,65 +1081,31 @@ pub async unsafe extern "C" fn build_tx(
         Err(_) => DEFAULT,
     };
 
-    let config = match WalletConfig::new(
-        deposit_desc,
-        BlockchainBackend::Electrum,
-        node_address,
-        None,
-    ) {
-        Ok(conf) => conf,
-        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
-    };
-
-    let to_address_cstr = CStr::from_ptr(to_address);
-    let to_address: &str = match to_address_cstr.to_str() {
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "To-Address").c_stringify(),
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
     };
 
-    let sweep_cstr = CStr::from_ptr(sweep);
-    let sweep: bool = match sweep_cstr.to_str() {
-        Ok(string) => string == "true",
-        Err(_) => false,
+    let store = match WalletStore::load(db_path, deposit_desc) {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
     };
 
-    let amount_cstr = CStr::from_ptr(amount);
-    let amount: Option<u64> = match amount_cstr.to_str() {
-        Ok(string) => match string.parse::<u64>() {
-            Ok(i) => {
-                if sweep {
-                    None
-                } else {
-                    Some(i)
-                }
-            }
-            Err(_) => return S5Error::new(ErrorKind::Input, "Invalid Amount.").c_stringify(),
-        },
-        Err(_) => return S5Error::new(ErrorKind::Input, "Amount").c_stringify(),
+    let format_cstr = CStr::from_ptr(format);
+    let format: report::ReportFormat = match format_cstr.to_str() {
+        Ok(string) => string.parse().unwrap_or(report::ReportFormat::Csv),
+        Err(_) => report::ReportFormat::Csv,
     };
 
-    let fee_absolute_cstr = CStr::from_ptr(fee_absolute);
-    let fee_absolute: u64 = match fee_absolute_cstr.to_str() {
-        Ok(string) => match string.parse::<u64>() {
-            Ok(i) => i,
-            Err(_) => return S5Error::new(ErrorKind::Input, "Fee Rate").c_stringify(),
+    let prices_cstr = CStr::from_ptr(prices);
+    let prices: Option<report::PriceSeries> = match prices_cstr.to_str() {
+        Ok("") => None,
+        Ok(string) => match serde_json::from_str(string) {
+            Ok(result) => Some(result),
+            Err(_) => return S5Error::new(ErrorKind::Input, "Price-Series").c_stringify(),
         },
-        Err(_) => return S5Error::new(ErrorKind::Input, "Fee Rate").c_stringify(),
-    };
-
-    // FIX ME. THIS ASSUMES RAFT IS THE ONLY WSH USED.
-
-    // WAYY TOO HACKY.
-    let policy_path: Option<policy::SpendingPolicyPaths> = if deposit_desc.contains("wsh") {
-        if deposit_desc.split("and_v").collect::<Vec<&str>>()[0].contains("tprv")
-            || deposit_desc.split("and_v").collect::<Vec<&str>>()[0].contains("xprv")
-        {
-            Some(policy::raft_policy_paths(config).await.unwrap().primary)
-        } else {
-            Some(policy::raft_policy_paths(config).await.unwrap().secondary)
-        }
-    } else {
-        None
+        Err(_) => return S5Error::new(ErrorKind::Input, "Price-Series").c_stringify(),
     };
 
     let config = match WalletConfig::new(
@@ -688,74 +1117,1415 @@ pub async unsafe extern "C" fn build_tx(
         Ok(conf) => conf,
         Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
     };
-
-    match psbt::build(config, to_address, amount, fee_absolute, sweep, policy_path).await {
+    match report::sync_report(config, &store, format, prices).await {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
 }
 
-/// Decodes a PSBT and returns all outputs of the transaction and total size.
-/// "miner" is used in the 'to' field of an output to indicate fee.
+/// Sets a BIP329 label in the wallet store at db_path. Use an empty label to remove it.
+/// label_type is one of "tx", "addr", "pubkey", "input", "output" or "xpub".
 /// - *OUTPUT*
 /// ```
-///   DecodedTx{
-///     outputs: Vec<DecodedTxIO {
-///       value: u64,
-///       to: String,
-///     }>
-///   }
+/// WalletLabels {
+///   count: usize,
+///   bip329: String,
+/// }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
 #[no_mangle]
-pub unsafe extern "C" fn decode_psbt(network: *const c_char, psbt: *const c_char) -> *mut c_char {
-    let network_cstr = CStr::from_ptr(network);
-    let network_str: &str = match network_cstr.to_str() {
+pub unsafe extern "C" fn set_label(
+    deposit_desc: *const c_char,
+    db_path: *const c_char,
+    label_type: *const c_char,
+    reference: *const c_char,
+    label: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => "test",
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
     };
-    let network = match network_str {
-        "main" => Network::Bitcoin,
-        "test" => Network::Testnet,
-        _ => Network::Testnet,
+
+    let label_type_cstr = CStr::from_ptr(label_type);
+    let label_type: labels::LabelType = match label_type_cstr.to_str() {
+        Ok(string) => match string.parse() {
+            Ok(result) => result,
+            Err(e) => return e.c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Label-Type").c_stringify(),
     };
 
-    let psbt_cstr = CStr::from_ptr(psbt);
-    let psbt: &str = match psbt_cstr.to_str() {
+    let reference_cstr = CStr::from_ptr(reference);
+    let reference: &str = match reference_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+        Err(_) => return S5Error::new(ErrorKind::Input, "Label-Reference").c_stringify(),
+    };
+
+    let label_cstr = CStr::from_ptr(label);
+    let label: &str = match label_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Label").c_stringify(),
     };
 
-    match psbt::decode(network, psbt) {
+    match labels::set(db_path, deposit_desc, label_type, reference, label) {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
 }
 
-/// Signs a PSBT with a descriptor.
-/// Can only be used with descriptors containing private key(s).
+/// Imports labels in the BIP329 JSON Lines format into the wallet store at db_path.
+/// Output records with "spendable":false freeze the utxo.
 /// - *OUTPUT*
 /// ```
-///  WalletPSBT {
-///    pub psbt: String,
-///    pub is_finalized: bool,
-///  }
+/// WalletLabels {
+///   count: usize,
+///   bip329: String,
+/// }
 /// ```
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
 #[no_mangle]
-pub unsafe extern "C" fn sign_tx(
+pub unsafe extern "C" fn import_labels(
     deposit_desc: *const c_char,
-    node_address: *const c_char,
-    unsigned_psbt: *const c_char,
+    db_path: *const c_char,
+    bip329: *const c_char,
 ) -> *mut c_char {
-    let deposit_desc_cstr = CStr::from_ptr(deposit_desc);
-    let deposit_desc: &str = match deposit_desc_cstr.to_str() {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    let bip329_cstr = CStr::from_ptr(bip329);
+    let bip329: &str = match bip329_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "BIP329").c_stringify(),
+    };
+
+    match labels::import(db_path, deposit_desc, bip329) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Exports all labels in the wallet store at db_path in the BIP329 JSON Lines format.
+/// - *OUTPUT*
+/// ```
+/// WalletLabels {
+///   count: usize,
+///   bip329: String,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn export_labels(
+    deposit_desc: *const c_char,
+    db_path: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    match labels::export(db_path, deposit_desc) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Gets the address for a descriptor wallet at a given index, eg. to show a past address again.
+/// Use get_new_address or get_unused_address to receive, which track indexes in the wallet store and prevent address reuse.
+/// - *OUTPUT*
+/// ```
+/// WalletAddress {
+///   address: String,
+///   label: Option<String>,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn get_address(
+    deposit_desc: *const c_char,
+    node_address: *const c_char,
+    index: *const c_char,
+    db_path: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let node_address_cstr = CStr::from_ptr(node_address);
+    let node_address: &str = match node_address_cstr.to_str() {
+        Ok(string) => {
+            if string.contains("electrum") || string.contains("http") {
+                string
+            } else {
+                DEFAULT
+            }
+        }
+        Err(_) => DEFAULT,
+    };
+
+    let config = match WalletConfig::new(
+        deposit_desc,
+        BlockchainBackend::Electrum,
+        node_address,
+        None,
+    ) {
+        Ok(conf) => conf,
+        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
+    };
+
+    let index_cstr = CStr::from_ptr(index);
+    let address_index: u32 = match index_cstr.to_str() {
+        Ok(string) => match string.parse::<u32>() {
+            Ok(i) => i,
+            Err(_) => {
+                return CString::new("Error: Address Index Input.")
+                    .unwrap()
+                    .into_raw()
+            }
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Address-Index").c_stringify(),
+    };
+
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    let store = match WalletStore::load(db_path, deposit_desc) {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
+    };
+
+    match address::generate(config, address_index, &store).await {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Gets the next unused receive address, after both the last revealed and the last used address.
+/// The wallet is synced to find used addresses, and revealed indexes are saved in the wallet store at db_path.
+/// warning is set once more than 20 unused addresses follow the last used one, past the gap limit other wallets restore with.
+/// - *OUTPUT*
+/// ```
+///  NewAddress {
+///    pub index: u32,
+///    pub address: String,
+///    pub label: Option<String>,
+///    pub last_used: Option<u32>,
+///    pub gap: u32,
+///    pub warning: Option<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn get_new_address(
+    deposit_desc: *const c_char,
+    node_address: *const c_char,
+    db_path: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let node_address_cstr = CStr::from_ptr(node_address);
+    let node_address: &str = match node_address_cstr.to_str() {
+        Ok(string) => {
+            if string.contains("electrum") || string.contains("http") {
+                string
+            } else {
+                DEFAULT
+            }
+        }
+        Err(_) => DEFAULT,
+    };
+
+    let config = match WalletConfig::new(
+        deposit_desc,
+        BlockchainBackend::Electrum,
+        node_address,
+        None,
+    ) {
+        Ok(conf) => conf,
+        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
+    };
+
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    match address::next_address(config, db_path, false).await {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Gets the last revealed receive address again while nothing has been received on it, otherwise a new one.
+/// The wallet is synced to find used addresses, and revealed indexes are saved in the wallet store at db_path.
+/// warning is set once more than 20 unused addresses follow the last used one, past the gap limit other wallets restore with.
+/// - *OUTPUT*
+/// ```
+///  NewAddress {
+///    pub index: u32,
+///    pub address: String,
+///    pub label: Option<String>,
+///    pub last_used: Option<u32>,
+///    pub gap: u32,
+///    pub warning: Option<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn get_unused_address(
+    deposit_desc: *const c_char,
+    node_address: *const c_char,
+    db_path: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let node_address_cstr = CStr::from_ptr(node_address);
+    let node_address: &str = match node_address_cstr.to_str() {
+        Ok(string) => {
+            if string.contains("electrum") || string.contains("http") {
+                string
+            } else {
+                DEFAULT
+            }
+        }
+        Err(_) => DEFAULT,
+    };
+
+    let config = match WalletConfig::new(
+        deposit_desc,
+        BlockchainBackend::Electrum,
+        node_address,
+        None,
+    ) {
+        Ok(conf) => conf,
+        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
+    };
+
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    match address::next_address(config, db_path, true).await {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Derives the address at index offline, without a node connection. Works for watch-only descriptors.
+/// keychain is "receive" or "change".
+/// - *OUTPUT*
+/// ```
+///  DerivedAddress {
+///    pub index: u32,
+///    pub is_change: bool,
+///    pub address: String,
+///    pub script_pubkey: String,
+///    pub key_paths: Vec<KeyPath {
+///      pub fingerprint: String,
+///      pub path: String,
+///    }>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn derive_address(
+    deposit_desc: *const c_char,
+    keychain: *const c_char,
+    index: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let keychain_cstr = CStr::from_ptr(keychain);
+    let is_change: bool = match keychain_cstr.to_str() {
+        Ok(string) => match address::parse_keychain(string) {
+            Ok(result) => result,
+            Err(e) => return e.c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Keychain").c_stringify(),
+    };
+
+    let index_cstr = CStr::from_ptr(index);
+    let index: u32 = match index_cstr.to_str() {
+        Ok(string) => match string.parse::<u32>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Address-Index").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Address-Index").c_stringify(),
+    };
+
+    match address::derive(deposit_desc, is_change, index) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Derives count addresses starting at start, offline. count is at most 1000.
+/// keychain is "receive" or "change".
+/// - *OUTPUT*
+/// ```
+///  DerivedAddresses {
+///    pub addresses: Vec<DerivedAddress>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn derive_addresses(
+    deposit_desc: *const c_char,
+    keychain: *const c_char,
+    start: *const c_char,
+    count: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let keychain_cstr = CStr::from_ptr(keychain);
+    let is_change: bool = match keychain_cstr.to_str() {
+        Ok(string) => match address::parse_keychain(string) {
+            Ok(result) => result,
+            Err(e) => return e.c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Keychain").c_stringify(),
+    };
+
+    let start_cstr = CStr::from_ptr(start);
+    let start: u32 = match start_cstr.to_str() {
+        Ok(string) => match string.parse::<u32>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Address-Index").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Address-Index").c_stringify(),
+    };
+
+    let count_cstr = CStr::from_ptr(count);
+    let count: u32 = match count_cstr.to_str() {
+        Ok(string) => match string.parse::<u32>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Address-Count").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Address-Count").c_stringify(),
+    };
+
+    match address::derive_range(deposit_desc, is_change, start, start.saturating_add(count)) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Checks whether an address belongs to a descriptor, searching indexes 0 to max_index of the receive and change keychains.
+/// key_paths holds each cosigner's derived public key, to verify against a hardware wallet display. max_index is at most 100000.
+/// - *OUTPUT*
+/// ```
+///  FoundAddress {
+///    pub address: String,
+///    pub found: bool,
+///    pub keychain: Option<String>,
+///    pub index: Option<u32>,
+///    pub key_paths: Vec<KeyPath>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn find_address(
+    descriptor: *const c_char,
+    address: *const c_char,
+    max_index: *const c_char,
+) -> *mut c_char {
+    let descriptor: &str = match checked_descriptor(descriptor, "Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let address_cstr = CStr::from_ptr(address);
+    let address: &str = match address_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Address").c_stringify(),
+    };
+
+    let max_index_cstr = CStr::from_ptr(max_index);
+    let max_index: u32 = match max_index_cstr.to_str() {
+        Ok(string) => match string.parse::<u32>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Max-Index").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Max-Index").c_stringify(),
+    };
+
+    match address::find(descriptor, address, max_index) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Describes an address: its network, script type and witness version.
+/// With a deposit_desc, also checks whether the address belongs to the wallet (first 1000 deposit and change addresses).
+/// - *OUTPUT*
+/// ```
+///  AddressInfo {
+///    pub address: String,
+///    pub network: String,
+///    pub script_type: String,
+///    pub witness_version: Option<u8>,
+///    pub script_pubkey: String,
+///    pub is_mine: bool,
+///    pub is_change: bool,
+///    pub index: Option<u32>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn validate_address(
+    address: *const c_char,
+    deposit_desc: *const c_char,
+) -> *mut c_char {
+    let address_cstr = CStr::from_ptr(address);
+    let address: &str = match address_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Address").c_stringify(),
+    };
+
+    let deposit_desc: Option<&str> = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok("") => None,
+        Ok(desc) => Some(desc),
+        Err(e) => return e.c_stringify(),
+    };
+
+    match address::validate(address, deposit_desc) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Parses a scanned BIP21 payment URI eg. bitcoin:tb1q...?amount=0.001&label=Rent
+/// The address must be for network ("main" or "test"). amount is returned in sats.
+/// Unknown req- parameters are rejected. Other unknown parameters are returned in params.
+/// - *OUTPUT*
+/// ```
+///  PaymentRequest {
+///    pub address: String,
+///    pub amount: Option<u64>,
+///    pub label: Option<String>,
+///    pub message: Option<String>,
+///    pub lightning: Option<String>,
+///    pub params: BTreeMap<String, String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn parse_payment_uri(uri: *const c_char, network: *const c_char) -> *mut c_char {
+    let uri_cstr = CStr::from_ptr(uri);
+    let uri: &str = match uri_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Payment-URI").c_stringify(),
+    };
+
+    let network_cstr = CStr::from_ptr(network);
+    let network: &str = match network_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => "test",
+    };
+    let network_enum = match network {
+        "main" => Network::Bitcoin,
+        _ => Network::Testnet,
+    };
+
+    match bip21::parse(uri, network_enum) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Creates a BIP21 payment URI for an address from get_address, percent-encoding label and message.
+/// amount is in sats. amount, label and message can be empty strings to leave them out.
+/// - *OUTPUT*
+/// ```
+///  PaymentUri {
+///    pub uri: String,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn create_payment_uri(
+    address: *const c_char,
+    network: *const c_char,
+    amount: *const c_char,
+    label: *const c_char,
+    message: *const c_char,
+) -> *mut c_char {
+    let address_cstr = CStr::from_ptr(address);
+    let address: &str = match address_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Address").c_stringify(),
+    };
+
+    let network_cstr = CStr::from_ptr(network);
+    let network: &str = match network_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => "test",
+    };
+    let network_enum = match network {
+        "main" => Network::Bitcoin,
+        _ => Network::Testnet,
+    };
+
+    let amount_cstr = CStr::from_ptr(amount);
+    let amount: Option<u64> = match amount_cstr.to_str() {
+        Ok("") => None,
+        Ok(string) => match string.parse::<u64>() {
+            Ok(result) => Some(result),
+            Err(_) => return S5Error::new(ErrorKind::Input, "Amount").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Amount").c_stringify(),
+    };
+
+    let label_cstr = CStr::from_ptr(label);
+    let label: Option<&str> = match label_cstr.to_str() {
+        Ok("") => None,
+        Ok(string) => Some(string),
+        Err(_) => return S5Error::new(ErrorKind::Input, "Label").c_stringify(),
+    };
+
+    let message_cstr = CStr::from_ptr(message);
+    let message: Option<&str> = match message_cstr.to_str() {
+        Ok("") => None,
+        Ok(string) => Some(string),
+        Err(_) => return S5Error::new(ErrorKind::Input, "Message").c_stringify(),
+    };
+
+    match bip21::create(address, network_enum, amount, label, message) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Gets the current network fee (in sats/vbyte) for a given confirmation target.
+/// - *OUTPUT*
+/// ```  
+///  NetworkFee {
+///    rate: f32,
+///    absolute: Option<u64>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn estimate_network_fee(
+    network: *const c_char,
+    node_address: *const c_char,
+    conf_target: *const c_char,
+) -> *mut c_char {
+    let conf_target_cstr = CStr::from_ptr(conf_target);
+    let conf_target_int: usize = match conf_target_cstr.to_str() {
+        Ok(string) => string.parse::<usize>().unwrap_or(6),
+        Err(_) => 6,
+    };
+
+    let network_cstr = CStr::from_ptr(network);
+    let network: &str = match network_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => "test",
+    };
+    let network_enum = match network {
+        "main" => Network::Bitcoin,
+        _ => Network::Testnet,
+    };
+    let node_address_cstr = CStr::from_ptr(node_address);
+    let node_address: &str = match node_address_cstr.to_str() {
+        Ok(string) => {
+            if string == DEFAULT {
+                match network_enum {
+                    Network::Bitcoin => DEFAULT_MAINNET_NODE,
+                    _ => DEFAULT_TESTNET_NODE,
+                }
+            } else {
+                string
+            }
+        }
+        Err(_) => match network_enum {
+            Network::Bitcoin => DEFAULT_MAINNET_NODE,
+            _ => DEFAULT_TESTNET_NODE,
+        },
+    };
+
+    let config = match WalletConfig::new("/0/*", BlockchainBackend::Electrum, node_address, None) {
+        Ok(conf) => conf,
+        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
+    };
+    match fees::estimate_rate(config, conf_target_int).await {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Converts a given fee_rate (in sats/vbyte) to absolute fee (in sats); given some transaction weight.
+/// - *OUTPUT*
+/// ```  
+///  NetworkFee {
+///    rate: f32,
+///    absolute: Option<u64>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn fee_rate_to_absolute(
+    fee_rate: *const c_char,
+    weight: *const c_char,
+) -> *mut c_char {
+    let weight_cstr = CStr::from_ptr(weight);
+    let weight_usize: usize = match weight_cstr.to_str() {
+        Ok(string) => string.parse::<usize>().unwrap_or(250),
+        Err(_) => 250,
+    };
+
+    let fee_rate_cstr = CStr::from_ptr(fee_rate);
+    let fee_rate_f32: f32 = match fee_rate_cstr.to_str() {
+        Ok(string) => string.parse::<f32>().unwrap_or(1.0),
+        Err(_) => 1.0,
+    };
+
+    fees::get_absolute(fee_rate_f32, weight_usize).c_stringify()
+}
+
+/// Converts a given absolute_fee (in sats) to fee rate (in sats/vbyte); given some transaction weight.
+/// - *OUTPUT*
+/// ```  
+///  NetworkFee {
+///    rate: f32,
+///    absolute: Option<u64>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn fee_absolute_to_rate(
+    fee_absolute: *const c_char,
+    weight: *const c_char,
+) -> *mut c_char {
+    let weight_cstr = CStr::from_ptr(weight);
+    let weight_usize: usize = match weight_cstr.to_str() {
+        Ok(string) => string.parse::<usize>().unwrap_or(250),
+        Err(_) => 250,
+    };
+
+    let fee_absolute_cstr = CStr::from_ptr(fee_absolute);
+    let fee_absolute_u64: u64 = match fee_absolute_cstr.to_str() {
+        Ok(string) => string.parse::<u64>().unwrap_or(1000),
+        Err(_) => 1000,
+    };
+
+    fees::get_rate(fee_absolute_u64, weight_usize).c_stringify()
+}
+
+/// Gets the weight of a transaction built with a given deposit-descriptor.
+/// - *OUTPUT*
+/// ```  
+///  TransactionWeight {
+///     weight: usize,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn get_weight(
+    deposit_desc: *const c_char,
+    psbt: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let psbt_cstr = CStr::from_ptr(psbt);
+    let psbt: &str = match psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    match psbt::get_weight(deposit_desc, psbt) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Builds a transaction for a given descriptor wallet.
+/// If sweep is set to true, amount value is ignored and will default to None.
+/// Set amount to 0 for sweep.
+/// Utxos frozen in the wallet store at db_path are never spent, including during a sweep.
+/// - *OUTPUT*
+/// ```
+///  WalletPSBT {
+///    pub psbt: String,
+///    pub is_finalized: bool,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn build_tx(
+    deposit_desc: *const c_char,
+    node_address: *const c_char,
+    to_address: *const c_char,
+    amount: *const c_char,
+    fee_absolute: *const c_char,
+    sweep: *const c_char,
+    db_path: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let node_address_cstr = CStr::from_ptr(node_address);
+    let node_address: &str = match node_address_cstr.to_str() {
+        Ok(string) => {
+            if string.contains("electrum") || string.contains("http") {
+                string
+            } else {
+                DEFAULT
+            }
+        }
+        Err(_) => DEFAULT,
+    };
+
+    let config = match WalletConfig::new(
+        deposit_desc,
+        BlockchainBackend::Electrum,
+        node_address,
+        None,
+    ) {
+        Ok(conf) => conf,
+        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
+    };
+
+    let to_address_cstr = CStr::from_ptr(to_address);
+    let to_address: &str = match to_address_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "To-Address").c_stringify(),
+    };
+
+    let sweep_cstr = CStr::from_ptr(sweep);
+    let sweep: bool = match sweep_cstr.to_str() {
+        Ok(string) => string == "true",
+        Err(_) => false,
+    };
+
+    let amount_cstr = CStr::from_ptr(amount);
+    let amount: Option<u64> = match amount_cstr.to_str() {
+        Ok(string) => match string.parse::<u64>() {
+            Ok(i) => {
+                if sweep {
+                    None
+                } else {
+                    Some(i)
+                }
+            }
+            Err(_) => return S5Error::new(ErrorKind::Input, "Invalid Amount.").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Amount").c_stringify(),
+    };
+
+    let fee_absolute_cstr = CStr::from_ptr(fee_absolute);
+    let fee_absolute: u64 = match fee_absolute_cstr.to_str() {
+        Ok(string) => match string.parse::<u64>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Fee Rate").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Fee Rate").c_stringify(),
+    };
+
+    let db_path_cstr = CStr::from_ptr(db_path);
+    let db_path: &str = match db_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "DB-Path").c_stringify(),
+    };
+
+    let store = match WalletStore::load(db_path, deposit_desc) {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
+    };
+
+    // FIX ME. THIS ASSUMES RAFT IS THE ONLY WSH USED.
+
+    // WAYY TOO HACKY.
+    let policy_path: Option<policy::SpendingPolicyPaths> = if deposit_desc.contains("wsh") {
+        if deposit_desc.split("and_v").collect::<Vec<&str>>()[0].contains("tprv")
+            || deposit_desc.split("and_v").collect::<Vec<&str>>()[0].contains("xprv")
+        {
+            Some(policy::raft_policy_paths(config).await.unwrap().primary)
+        } else {
+            Some(policy::raft_policy_paths(config).await.unwrap().secondary)
+        }
+    } else {
+        None
+    };
+
+    let config = match WalletConfig::new(
+        deposit_desc,
+        BlockchainBackend::Electrum,
+        node_address,
+        None,
+    ) {
+        Ok(conf) => conf,
+        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
+    };
+
+    match psbt::build(
+        config,
+        to_address,
+        amount,
+        fee_absolute,
+        sweep,
+        policy_path,
+        &store,
+    )
+    .await
+    {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Decodes a PSBT for review before signing.
+/// deposit_desc is used to flag inputs and outputs belonging to the wallet and to estimate the final size for the fee rate.
+/// Use an empty string if the descriptor is not known. fee is None if any input value is missing from the PSBT.
+/// - *OUTPUT*
+/// ```
+///   DecodedTx {
+///     txid: String,
+///     version: i32,
+///     locktime: u32,
+///     rbf: bool,
+///     inputs: Vec<DecodedInput {
+///       previous_outpoint: String,
+///       value: Option<u64>,
+///       address: Option<String>,
+///       is_mine: bool,
+///       sequence: u32,
+///       sighash_type: String,
+///       signed_by: Vec<String>,
+///       finalized: bool,
+///     }>,
+///     outputs: Vec<DecodedOutput {
+///       vout: u32,
+///       value: u64,
+///       address: String,
+///       is_mine: bool,
+///       is_change: bool,
+///     }>,
+///     fee: Option<u64>,
+///     vsize: Option<usize>,
+///     fee_rate: Option<f32>,
+///   }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn decode_psbt(
+    network: *const c_char,
+    psbt: *const c_char,
+    deposit_desc: *const c_char,
+) -> *mut c_char {
+    let network_cstr = CStr::from_ptr(network);
+    let network_str: &str = match network_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => "test",
+    };
+    let network = match network_str {
+        "main" => Network::Bitcoin,
+        "test" => Network::Testnet,
+        _ => Network::Testnet,
+    };
+
+    let psbt_cstr = CStr::from_ptr(psbt);
+    let psbt: &str = match psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    let deposit_desc: Option<&str> = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok("") => None,
+        Ok(desc) => Some(desc),
+        Err(e) => return e.c_stringify(),
+    };
+
+    match psbt::decode(network, psbt, deposit_desc) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Signs a PSBT with a descriptor.
+/// Can only be used with descriptors containing private key(s).
+/// node_address is not used and is only kept for compatibility. Prefer sign_offline.
+/// - *OUTPUT*
+/// ```
+///  WalletPSBT {
+///    pub psbt: String,
+///    pub is_finalized: bool,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn sign_tx(
+    deposit_desc: *const c_char,
+    _node_address: *const c_char,
+    unsigned_psbt: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let unsigned_psbt_cstr = CStr::from_ptr(unsigned_psbt);
+    let unsigned_psbt: &str = match unsigned_psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    match psbt::sign(deposit_desc, unsigned_psbt, psbt::WalletSignOptions::default()) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Signs a PSBT with a descriptor without any network connection.
+/// Available in builds without the default "online" feature, for air-gapped signers.
+/// Can only be used with descriptors containing private key(s).
+/// sign_options is a JSON object, or an empty string for the defaults:
+/// ```
+///  {
+///    "trust_witness_utxo": false,
+///    "assume_height": null,
+///    "allow_all_sighashes": false,
+///    "remove_partial_sigs": false,
+///    "try_finalize": true
+///  }
+/// ```
+/// - *OUTPUT*
+/// ```
+///  WalletPSBT {
+///    pub psbt: String,
+///    pub is_finalized: bool,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn sign_offline(
+    deposit_desc: *const c_char,
+    unsigned_psbt: *const c_char,
+    sign_options: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let unsigned_psbt_cstr = CStr::from_ptr(unsigned_psbt);
+    let unsigned_psbt: &str = match unsigned_psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    let sign_options_cstr = CStr::from_ptr(sign_options);
+    let sign_options: psbt::WalletSignOptions = match sign_options_cstr.to_str() {
+        Ok("") => psbt::WalletSignOptions::default(),
+        Ok(string) => match serde_json::from_str(string) {
+            Ok(result) => result,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Sign-Options").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Sign-Options").c_stringify(),
+    };
+
+    match psbt::sign(deposit_desc, unsigned_psbt, sign_options) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Converts a PSBT between version 0 and version 2 (BIP370).
+/// version is "0" or "2". All other functions accept either version.
+/// - *OUTPUT*
+/// ```
+///  WalletPSBT {
+///    pub psbt: String,
+///    pub is_finalized: bool,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn convert_psbt(psbt: *const c_char, version: *const c_char) -> *mut c_char {
+    let psbt_cstr = CStr::from_ptr(psbt);
+    let psbt: &str = match psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    let version_cstr = CStr::from_ptr(version);
+    let version: u32 = match version_cstr.to_str() {
+        Ok(string) => match string.parse::<u32>() {
+            Ok(result) => result,
+            Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Version").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Version").c_stringify(),
+    };
+
+    match psbt::convert(psbt, version) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Reads a PSBT file in base64, hex or binary (eg. a Coldcard SD card .psbt).
+/// Returns the PSBT in base64. Every function taking a psbt also accepts hex.
+/// - *OUTPUT*
+/// ```
+///  WalletPSBT {
+///    pub psbt: String,
+///    pub is_finalized: bool,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn read_psbt_file(path: *const c_char) -> *mut c_char {
+    let path_cstr = CStr::from_ptr(path);
+    let path: &str = match path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Path").c_stringify(),
+    };
+
+    match psbt::read_file(path) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Writes a PSBT to a file at path.
+/// encoding is "binary", "hex" or "base64".
+/// - *OUTPUT*
+/// ```
+///  PSBTFile {
+///    pub path: String,
+///    pub encoding: String,
+///    pub size: usize,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn write_psbt_file(
+    psbt: *const c_char,
+    path: *const c_char,
+    encoding: *const c_char,
+) -> *mut c_char {
+    let psbt_cstr = CStr::from_ptr(psbt);
+    let psbt: &str = match psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    let path_cstr = CStr::from_ptr(path);
+    let path: &str = match path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Path").c_stringify(),
+    };
+
+    let encoding_cstr = CStr::from_ptr(encoding);
+    let encoding: psbt::PSBTEncoding = match encoding_cstr.to_str() {
+        Ok(string) => match string.parse::<psbt::PSBTEncoding>() {
+            Ok(result) => result,
+            Err(e) => return e.c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Encoding").c_stringify(),
+    };
+
+    match psbt::write_file(psbt, path, encoding) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Combines copies of a PSBT signed separately by each cosigner.
+/// psbts is a JSON array of base64 PSBTs which must all describe the same unsigned transaction.
+/// - *OUTPUT*
+/// ```
+///  WalletPSBT {
+///    pub psbt: String,
+///    pub is_finalized: bool,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn combine_psbts(psbts: *const c_char) -> *mut c_char {
+    let psbts_cstr = CStr::from_ptr(psbts);
+    let psbts: Vec<String> = match psbts_cstr.to_str() {
+        Ok(string) => match serde_json::from_str(string) {
+            Ok(result) => result,
+            Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-List").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-List").c_stringify(),
+    };
+
+    match psbt::combine(&psbts) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Finalizes a PSBT once it has enough signatures to satisfy the descriptor.
+/// tx contains the raw transaction hex, ready to broadcast, if every input could be finalized.
+/// - *OUTPUT*
+/// ```
+///  FinalizedPSBT {
+///    pub psbt: String,
+///    pub is_finalized: bool,
+///    pub tx: Option<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn finalize_psbt(
+    deposit_desc: *const c_char,
+    psbt: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let psbt_cstr = CStr::from_ptr(psbt);
+    let psbt: &str = match psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    match psbt::finalize(deposit_desc, psbt) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Reports which keys have signed a PSBT and what is still needed to spend it.
+/// branch is the cheapest spending path of the descriptor policy given the signatures already present.
+/// waiting_on lists fingerprints of keys which could provide the missing signatures.
+/// - *OUTPUT*
+/// ```
+///  SigningProgress {
+///    complete: bool,
+///    signed_by: Vec<String>,
+///    missing_signatures: usize,
+///    waiting_on: Vec<String>,
+///    inputs: Vec<InputProgress {
+///      index: usize,
+///      complete: bool,
+///      signed_by: Vec<String>,
+///      branch: String,
+///      missing_signatures: usize,
+///      waiting_on: Vec<String>,
+///      timelocks: Vec<String>,
+///      preimages: Vec<String>,
+///    }>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn signing_progress(
+    deposit_desc: *const c_char,
+    psbt: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let psbt_cstr = CStr::from_ptr(psbt);
+    let psbt: &str = match psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    match progress::progress(deposit_desc, psbt) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Checks a signed PSBT before broadcast: fee rate, dust, non-standard scripts, size, timelocks at the current tip
+/// and inputs already spent by a transaction the wallet knows about.
+/// Issues with severity "error" make broadcast_tx refuse unless force is "true".
+/// - *OUTPUT*
+/// ```
+///  BroadcastReport {
+///    pub txid: String,
+///    pub fee: Option<u64>,
+///    pub vsize: usize,
+///    pub fee_rate: Option<f32>,
+///    pub tip_height: u32,
+///    pub can_broadcast: bool,
+///    pub issues: Vec<TxIssue {
+///      pub code: String,
+///      pub severity: Severity,
+///      pub message: String,
+///    }>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn check_broadcast(
+    deposit_desc: *const c_char,
+    node_address: *const c_char,
+    signed_psbt: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let node_address_cstr = CStr::from_ptr(node_address);
+    let node_address: &str = match node_address_cstr.to_str() {
+        Ok(string) => {
+            if string.contains("electrum") || string.contains("http") {
+                string
+            } else {
+                DEFAULT
+            }
+        }
+        Err(_) => DEFAULT,
+    };
+
+    let config = match WalletConfig::new(
+        deposit_desc,
+        BlockchainBackend::Electrum,
+        node_address,
+        None,
+    ) {
+        Ok(conf) => conf,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let psbt_cstr = CStr::from_ptr(signed_psbt);
+    let signed_psbt: &str = match psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    match precheck::check_broadcast(config, signed_psbt).await {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Broadcasts a signed transaction to a remote node.
+/// Refuses if check_broadcast finds an error, unless force is "true".
+/// - *OUTPUT*
+/// ```
+///  Txid {
+///    pub txid: String,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn broadcast_tx(
+    deposit_desc: *const c_char,
+    node_address: *const c_char,
+    signed_psbt: *const c_char,
+    force: *const c_char,
+) -> *mut c_char {
+    let deposit_desc: &str = match checked_descriptor(deposit_desc, "Deposit-Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
     };
 
     let node_address_cstr = CStr::from_ptr(node_address);
@@ -777,22 +2547,29 @@ pub unsafe extern "C" fn sign_tx(
         None,
     ) {
         Ok(conf) => conf,
-        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
+        Err(e) => return e.c_stringify(),
     };
 
-    let unsigned_psbt_cstr = CStr::from_ptr(unsigned_psbt);
-    let unsigned_psbt: &str = match unsigned_psbt_cstr.to_str() {
+    let psbt_cstr = CStr::from_ptr(signed_psbt);
+    let signed_psbt: &str = match psbt_cstr.to_str() {
         Ok(string) => string,
         Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
     };
 
-    match psbt::sign(config, unsigned_psbt) {
+    let force_cstr = CStr::from_ptr(force);
+    let force: bool = match force_cstr.to_str() {
+        Ok(string) => string == "true",
+        Err(_) => false,
+    };
+
+    match psbt::broadcast(config, signed_psbt, force).await {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
 }
 
-/// Broadcasts a signed transaction to a remote node.
+/// Broadcasts a raw signed transaction hex directly through the backend, without a descriptor or wallet sync.
+/// network is "main" or "test".
 /// - *OUTPUT*
 /// ```
 ///  Txid {
@@ -802,47 +2579,105 @@ pub unsafe extern "C" fn sign_tx(
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
 #[no_mangle]
-pub async unsafe extern "C" fn broadcast_tx(
-    deposit_desc: *const c_char,
+pub async unsafe extern "C" fn broadcast_raw(
+    network: *const c_char,
     node_address: *const c_char,
-    signed_psbt: *const c_char,
+    tx_hex: *const c_char,
 ) -> *mut c_char {
-    let deposit_desc_cstr = CStr::from_ptr(deposit_desc);
-    let deposit_desc: &str = match deposit_desc_cstr.to_str() {
+    let network_cstr = CStr::from_ptr(network);
+    let network: &str = match network_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
+        Err(_) => "test",
+    };
+    let network_enum = match network {
+        "main" => Network::Bitcoin,
+        _ => Network::Testnet,
     };
-
     let node_address_cstr = CStr::from_ptr(node_address);
     let node_address: &str = match node_address_cstr.to_str() {
         Ok(string) => {
-            if string.contains("electrum") || string.contains("http") {
-                string
+            if string == DEFAULT || string.is_empty() {
+                match network_enum {
+                    Network::Bitcoin => DEFAULT_MAINNET_NODE,
+                    _ => DEFAULT_TESTNET_NODE,
+                }
             } else {
-                DEFAULT
+                string
             }
         }
-        Err(_) => DEFAULT,
+        Err(_) => match network_enum {
+            Network::Bitcoin => DEFAULT_MAINNET_NODE,
+            _ => DEFAULT_TESTNET_NODE,
+        },
     };
 
-    let config = match WalletConfig::new(
-        deposit_desc,
-        BlockchainBackend::Electrum,
-        node_address,
-        None,
-    ) {
+    let tx_hex_cstr = CStr::from_ptr(tx_hex);
+    let tx_hex: &str = match tx_hex_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Transaction-Hex").c_stringify(),
+    };
+
+    let config = match WalletConfig::new("/0/*", BlockchainBackend::Electrum, node_address, None) {
         Ok(conf) => conf,
-        Err(e) => return e.c_stringify(),
+        Err(e) => return S5Error::new(ErrorKind::Internal, &e.message).c_stringify(),
     };
+    match psbt::broadcast_raw(config, tx_hex).await {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
 
-    let psbt_cstr = CStr::from_ptr(signed_psbt);
-    let signed_psbt: &str = match psbt_cstr.to_str() {
+/// Decodes a raw transaction hex. network ("main" or "test") is used to display output addresses.
+/// Input values are not part of a raw transaction, so no fee is returned. Use decode_psbt for fees.
+/// - *OUTPUT*
+/// ```
+///  DecodedRawTx {
+///    pub txid: String,
+///    pub wtxid: String,
+///    pub version: i32,
+///    pub locktime: u32,
+///    pub rbf: bool,
+///    pub size: usize,
+///    pub weight: usize,
+///    pub vsize: usize,
+///    pub inputs: Vec<RawTxInput {
+///      pub previous_outpoint: String,
+///      pub sequence: u32,
+///      pub rbf: bool,
+///      pub witness_items: usize,
+///    }>,
+///    pub outputs: Vec<RawTxOutput {
+///      pub vout: u32,
+///      pub value: u64,
+///      pub address: Option<String>,
+///      pub script_pubkey: String,
+///    }>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn decode_raw_tx(network: *const c_char, tx_hex: *const c_char) -> *mut c_char {
+    let network_cstr = CStr::from_ptr(network);
+    let network: &str = match network_cstr.to_str() {
         Ok(string) => string,
-        Err(_) => return S5Error::new(ErrorKind::Input, "Deposit-Descriptor").c_stringify(),
+        Err(_) => "test",
+    };
+    let network_enum = match network {
+        "main" => Network::Bitcoin,
+        _ => Network::Testnet,
     };
 
-    match psbt::broadcast(config, signed_psbt).await {
+    let tx_hex_cstr = CStr::from_ptr(tx_hex);
+    let tx_hex: &str = match tx_hex_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Transaction-Hex").c_stringify(),
+    };
+
+    match psbt::decode_raw_tx(network_enum, tx_hex) {
         Ok(result) => result.c_stringify(),
         Err(e) => e.c_stringify(),
     }
@@ -881,6 +2716,7 @@ pub unsafe extern "C" fn check_xpub(xpub: *const c_char) -> *mut c_char {
 /// # Safety
 /// - This function is unsafe because it dereferences and a returns raw pointer.
 /// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
 #[no_mangle]
 pub async unsafe extern "C" fn get_height(
     network: *const c_char,
@@ -950,6 +2786,391 @@ pub unsafe extern "C" fn days_to_blocks(days: *const c_char) -> *mut c_char {
     height::BlockHeight { height: days * 144 }.c_stringify()
 }
 
+/// Get the value of 1 BTC in fiat_unit from an exchange. Rates are cached for 60 seconds.
+/// source is one of "kraken", "strike", "bullbitcoin", "localbitcoins" or "custom".
+/// custom_rate is the value of 1 BTC in fiat_unit and is only used with the "custom" source. Otherwise use an empty string.
+/// - *OUTPUT*
+/// ```
+/// Rate {
+///   timestamp: u64,
+///   fiat_unit: String,
+///   source: String,
+///   btc: f64,
+///   sats: f64,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn get_exchange_rate(
+    source: *const c_char,
+    fiat_unit: *const c_char,
+    custom_rate: *const c_char,
+) -> *mut c_char {
+    let rate = match parse_rate(source, fiat_unit, custom_rate).await {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
+    };
+    rate.c_stringify()
+}
+
+/// Convert sats to fiat_unit using an exchange rate. See get_exchange_rate for source and custom_rate.
+/// - *OUTPUT*
+/// ```
+/// FiatConversion {
+///   sats: u64,
+///   fiat: f64,
+///   rate: Rate,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn sats_to_fiat(
+    sats: *const c_char,
+    source: *const c_char,
+    fiat_unit: *const c_char,
+    custom_rate: *const c_char,
+) -> *mut c_char {
+    let sats_cstr = CStr::from_ptr(sats);
+    let sats: u64 = match sats_cstr.to_str() {
+        Ok(string) => match string.parse::<u64>() {
+            Ok(i) => i,
+            Err(_) => return S5Error::new(ErrorKind::Input, "Invalid Sats.").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Invalid Sats.").c_stringify(),
+    };
+    let rate = match parse_rate(source, fiat_unit, custom_rate).await {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
+    };
+    exchange::sats_to_fiat(sats, rate).c_stringify()
+}
+
+/// Convert a fiat_unit amount to sats using an exchange rate. See get_exchange_rate for source and custom_rate.
+/// - *OUTPUT*
+/// ```
+/// FiatConversion {
+///   sats: u64,
+///   fiat: f64,
+///   rate: Rate,
+/// }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[cfg(feature = "online")]
+#[no_mangle]
+pub async unsafe extern "C" fn fiat_to_sats(
+    fiat: *const c_char,
+    source: *const c_char,
+    fiat_unit: *const c_char,
+    custom_rate: *const c_char,
+) -> *mut c_char {
+    let fiat_cstr = CStr::from_ptr(fiat);
+    let fiat: f64 = match fiat_cstr.to_str() {
+        Ok(string) => match string.parse::<f64>() {
+            Ok(f) if f.is_finite() && f >= 0.0 => f,
+            _ => return S5Error::new(ErrorKind::Input, "Invalid Fiat Amount.").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "Invalid Fiat Amount.").c_stringify(),
+    };
+    let rate = match parse_rate(source, fiat_unit, custom_rate).await {
+        Ok(result) => result,
+        Err(e) => return e.c_stringify(),
+    };
+    exchange::fiat_to_sats(fiat, rate).c_stringify()
+}
+
+#[cfg(feature = "online")]
+async unsafe fn parse_rate(
+    source: *const c_char,
+    fiat_unit: *const c_char,
+    custom_rate: *const c_char,
+) -> Result<exchange::Rate, S5Error> {
+    let source_cstr = CStr::from_ptr(source);
+    let source: exchange::SourceExchange = match source_cstr.to_str() {
+        Ok(string) => string.parse()?,
+        Err(_) => return Err(S5Error::new(ErrorKind::Input, "Unsupported Exchange.")),
+    };
+    let fiat_unit_cstr = CStr::from_ptr(fiat_unit);
+    let fiat_unit: exchange::FiatUnit = match fiat_unit_cstr.to_str() {
+        Ok(string) => string.parse()?,
+        Err(_) => return Err(S5Error::new(ErrorKind::Input, "Unsupported Fiat Unit.")),
+    };
+    let custom_rate_cstr = CStr::from_ptr(custom_rate);
+    let custom_rate: Option<f64> = match custom_rate_cstr.to_str() {
+        Ok(string) => string.parse::<f64>().ok(),
+        Err(_) => None,
+    };
+    exchange::get_rate(source, fiat_unit, custom_rate, exchange::DEFAULT_RATE_MAX_AGE).await
+}
+
+/// Encodes a PSBT as animated ur:crypto-psbt QR frames for air-gapped signers.
+/// max_fragment_length defaults to 200 bytes. frame_count is the number of frames to return; at least seq_len.
+/// Frames past seq_len are fountain coded, so looping over more frames recovers from missed scans.
+/// - *OUTPUT*
+/// ```
+///  URFrames {
+///    pub ur_type: String,
+///    pub seq_len: usize,
+///    pub frames: Vec<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn ur_encode_psbt(
+    psbt: *const c_char,
+    max_fragment_length: *const c_char,
+    frame_count: *const c_char,
+) -> *mut c_char {
+    let psbt_cstr = CStr::from_ptr(psbt);
+    let psbt: &str = match psbt_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "PSBT-Input").c_stringify(),
+    };
+
+    let max_fragment_length_cstr = CStr::from_ptr(max_fragment_length);
+    let max_fragment_length: usize = match max_fragment_length_cstr.to_str() {
+        Ok(string) => string.parse::<usize>().unwrap_or(ur::DEFAULT_MAX_FRAGMENT_LENGTH),
+        Err(_) => ur::DEFAULT_MAX_FRAGMENT_LENGTH,
+    };
+
+    let frame_count_cstr = CStr::from_ptr(frame_count);
+    let frame_count: usize = match frame_count_cstr.to_str() {
+        Ok(string) => string.parse::<usize>().unwrap_or(0),
+        Err(_) => 0,
+    };
+
+    match ur::encode_psbt(psbt, max_fragment_length, frame_count) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Adds a scanned UR frame to a decoder. Frames can be given in any order.
+/// state is an empty string for a new scan, then the state returned by the previous call.
+/// psbt is set (base64) once a crypto-psbt is complete; cbor (hex) for other UR types.
+/// - *OUTPUT*
+/// ```
+///  URDecodeProgress {
+///    pub state: String,
+///    pub ur_type: Option<String>,
+///    pub complete: bool,
+///    pub progress: f64,
+///    pub received: usize,
+///    pub expected: usize,
+///    pub psbt: Option<String>,
+///    pub cbor: Option<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn ur_decode(state: *const c_char, frame: *const c_char) -> *mut c_char {
+    let state_cstr = CStr::from_ptr(state);
+    let state: &str = match state_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "UR-State").c_stringify(),
+    };
+
+    let frame_cstr = CStr::from_ptr(frame);
+    let frame: &str = match frame_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "UR-Frame").c_stringify(),
+    };
+
+    match ur::decode(state, frame) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Encodes an account xpub from derive_hardened as ur:crypto-account.
+/// The script type follows the purpose of hardened_path (44, 49, 84, 86 or 48 with script type 1h/2h).
+/// max_fragment_length defaults to 200 bytes.
+/// - *OUTPUT*
+/// ```
+///  URFrames {
+///    pub ur_type: String,
+///    pub seq_len: usize,
+///    pub frames: Vec<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn ur_encode_account(
+    fingerprint: *const c_char,
+    hardened_path: *const c_char,
+    xpub: *const c_char,
+    max_fragment_length: *const c_char,
+) -> *mut c_char {
+    let fingerprint_cstr = CStr::from_ptr(fingerprint);
+    let fingerprint: &str = match fingerprint_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Fingerprint").c_stringify(),
+    };
+
+    let hardened_path_cstr = CStr::from_ptr(hardened_path);
+    let hardened_path: &str = match hardened_path_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Derivation-Path").c_stringify(),
+    };
+
+    let xpub_cstr = CStr::from_ptr(xpub);
+    let xpub: &str = match xpub_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "Xpub").c_stringify(),
+    };
+
+    let max_fragment_length_cstr = CStr::from_ptr(max_fragment_length);
+    let max_fragment_length: usize = match max_fragment_length_cstr.to_str() {
+        Ok(string) => string.parse::<usize>().unwrap_or(ur::DEFAULT_MAX_FRAGMENT_LENGTH),
+        Err(_) => ur::DEFAULT_MAX_FRAGMENT_LENGTH,
+    };
+
+    match ur::encode_account(fingerprint, hardened_path, xpub, max_fragment_length) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Encodes a public descriptor, eg. from compile, as ur:crypto-output.
+/// Supports sh, wsh, pk, pkh, wpkh, combo, tr, multi and sortedmulti. Other miniscript cannot be represented.
+/// max_fragment_length defaults to 200 bytes.
+/// - *OUTPUT*
+/// ```
+///  URFrames {
+///    pub ur_type: String,
+///    pub seq_len: usize,
+///    pub frames: Vec<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn ur_encode_output(
+    descriptor: *const c_char,
+    max_fragment_length: *const c_char,
+) -> *mut c_char {
+    let descriptor: &str = match checked_descriptor(descriptor, "Descriptor") {
+        Ok(desc) => desc,
+        Err(e) => return e.c_stringify(),
+    };
+
+    let max_fragment_length_cstr = CStr::from_ptr(max_fragment_length);
+    let max_fragment_length: usize = match max_fragment_length_cstr.to_str() {
+        Ok(string) => string.parse::<usize>().unwrap_or(ur::DEFAULT_MAX_FRAGMENT_LENGTH),
+        Err(_) => ur::DEFAULT_MAX_FRAGMENT_LENGTH,
+    };
+
+    match ur::encode_output(descriptor, max_fragment_length) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Splits a PSBT (base64 or hex), raw transaction (hex) or JSON document into BBQr frames for Coldcard Q.
+/// file_type is psbt, tx or json. encoding is zlib (default), base32 or hex.
+/// max_frame_length is the most chars in a frame, including the 8 char header. Defaults to 1000.
+/// - *OUTPUT*
+/// ```
+///  BBQrFrames {
+///    pub file_type: BBQrFileType,
+///    pub encoding: BBQrEncoding,
+///    pub frames: Vec<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn bbqr_split(
+    data: *const c_char,
+    file_type: *const c_char,
+    encoding: *const c_char,
+    max_frame_length: *const c_char,
+) -> *mut c_char {
+    let data_cstr = CStr::from_ptr(data);
+    let data: &str = match data_cstr.to_str() {
+        Ok(string) => string,
+        Err(_) => return S5Error::new(ErrorKind::Input, "BBQr-Data").c_stringify(),
+    };
+
+    let file_type_cstr = CStr::from_ptr(file_type);
+    let file_type: bbqr::BBQrFileType = match file_type_cstr.to_str() {
+        Ok(string) => match string.parse::<bbqr::BBQrFileType>() {
+            Ok(result) => result,
+            Err(e) => return e.c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "BBQr-File-Type").c_stringify(),
+    };
+
+    let encoding_cstr = CStr::from_ptr(encoding);
+    let encoding: bbqr::BBQrEncoding = match encoding_cstr.to_str() {
+        Ok("") => bbqr::BBQrEncoding::Zlib,
+        Ok(string) => match string.parse::<bbqr::BBQrEncoding>() {
+            Ok(result) => result,
+            Err(e) => return e.c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "BBQr-Encoding").c_stringify(),
+    };
+
+    let max_frame_length_cstr = CStr::from_ptr(max_frame_length);
+    let max_frame_length: usize = match max_frame_length_cstr.to_str() {
+        Ok(string) => string.parse::<usize>().unwrap_or(bbqr::DEFAULT_MAX_FRAME_LENGTH),
+        Err(_) => bbqr::DEFAULT_MAX_FRAME_LENGTH,
+    };
+
+    match bbqr::split(data, file_type, encoding, max_frame_length) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
+/// Joins scanned BBQr frames back into a PSBT (base64), raw transaction (hex) or JSON document.
+/// frames is a JSON array of every frame scanned so far, in any order. Repeated frames are ignored.
+/// Returns an error naming how many frames were received until all of them are present.
+/// - *OUTPUT*
+/// ```
+///  BBQrJoined {
+///    pub file_type: BBQrFileType,
+///    pub encoding: BBQrEncoding,
+///    pub psbt: Option<String>,
+///    pub tx: Option<String>,
+///    pub json: Option<String>,
+///  }
+/// ```
+/// # Safety
+/// - This function is unsafe because it dereferences and a returns raw pointer.
+/// - ENSURE that result is passed into cstring_free(ptr: *mut c_char) after use.
+#[no_mangle]
+pub unsafe extern "C" fn bbqr_join(frames: *const c_char) -> *mut c_char {
+    let frames_cstr = CStr::from_ptr(frames);
+    let frames: Vec<String> = match frames_cstr.to_str() {
+        Ok(string) => match serde_json::from_str(string) {
+            Ok(result) => result,
+            Err(_) => return S5Error::new(ErrorKind::Input, "BBQr-Frames").c_stringify(),
+        },
+        Err(_) => return S5Error::new(ErrorKind::Input, "BBQr-Frames").c_stringify(),
+    };
+
+    match bbqr::join(&frames) {
+        Ok(result) => result.c_stringify(),
+        Err(e) => e.c_stringify(),
+    }
+}
+
 /// After using any other function, pass the output pointer into cstring_free(ptr: *mut c_char) to clear memory.
 /// ALWAYS use this in combination with any other function.
 /// Failure to do so can lead to memory bugs.
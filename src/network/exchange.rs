@@ -1,75 +1,621 @@
-const SATS_DENOMINATOR:usize = 100000000.0;
+use std::ffi::CString;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::os::raw::c_char;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub enum FiatUnit{
-  INR,
-  CAD,
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::e::{ErrorKind, S5Error};
+
+const SATS_DENOMINATOR: f64 = 100_000_000.0;
+
+/// Rates younger than this (in seconds) are served from the cache.
+pub const DEFAULT_RATE_MAX_AGE: u64 = 60;
+
+pub const KRAKEN_URL: &str = "https://api.kraken.com";
+pub const STRIKE_URL: &str = "https://api.strike.me";
+pub const BULLBITCOIN_URL: &str = "https://www.bullbitcoin.com";
+pub const LOCALBITCOINS_URL: &str = "https://localbitcoins.com";
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum FiatUnit {
   USD,
   EUR,
+  GBP,
+  CAD,
+  AUD,
+  CHF,
+  JPY,
+  INR,
+  MXN,
+  BRL,
+  ZAR,
+  NGN,
 }
 
-pub enum SourceExchange{
+impl Display for FiatUnit {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    write!(f, "{:?}", self)
+  }
+}
+
+impl FromStr for FiatUnit {
+  type Err = S5Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_uppercase().as_str() {
+      "USD" => Ok(FiatUnit::USD),
+      "EUR" => Ok(FiatUnit::EUR),
+      "GBP" => Ok(FiatUnit::GBP),
+      "CAD" => Ok(FiatUnit::CAD),
+      "AUD" => Ok(FiatUnit::AUD),
+      "CHF" => Ok(FiatUnit::CHF),
+      "JPY" => Ok(FiatUnit::JPY),
+      "INR" => Ok(FiatUnit::INR),
+      "MXN" => Ok(FiatUnit::MXN),
+      "BRL" => Ok(FiatUnit::BRL),
+      "ZAR" => Ok(FiatUnit::ZAR),
+      "NGN" => Ok(FiatUnit::NGN),
+      _ => Err(S5Error::new(ErrorKind::Input, "Unsupported Fiat Unit.")),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceExchange {
   Custom,
   LocalBitcoins,
   BullBitcoin,
   Strike,
-  Kraken
+  Kraken,
+}
+
+impl FromStr for SourceExchange {
+  type Err = S5Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "custom" => Ok(SourceExchange::Custom),
+      "localbitcoins" => Ok(SourceExchange::LocalBitcoins),
+      "bullbitcoin" => Ok(SourceExchange::BullBitcoin),
+      "strike" => Ok(SourceExchange::Strike),
+      "kraken" => Ok(SourceExchange::Kraken),
+      _ => Err(S5Error::new(ErrorKind::Input, "Unsupported Exchange.")),
+    }
+  }
+}
+
+fn timestamp() -> u64 {
+  match SystemTime::now().duration_since(UNIX_EPOCH) {
+    Ok(duration) => duration.as_secs(),
+    Err(_) => 0,
+  }
 }
 
+/// FFI Output
 /// btc contains value of 1 BTC as FiatUnit
 /// sats contains value of 1 FiatUnit as sats
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
 pub struct Rate {
-  timestamp: u64,
-  fiat_unit: FiatUnit,
-  source: SourceExchange,
-  btc: f64,
-  sats: f64
+  pub timestamp: u64,
+  pub fiat_unit: FiatUnit,
+  pub source: SourceExchange,
+  pub btc: f64,
+  pub sats: f64,
 }
 
-impl Rate{
-  pub fn now(source: Exchange) -> Rate {
-    // TODO: implement with source exchanges
+impl Rate {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+
+  fn from_btc_price(source: SourceExchange, fiat_unit: FiatUnit, btc: f64) -> Rate {
     Rate {
-      timestamp: timestamp,
-      fiat_unit: FiatUnit::INR,
+      timestamp: timestamp(),
+      fiat_unit,
       source,
-      btc: 55000000.0,
-      sats: 18.2
+      btc,
+      sats: SATS_DENOMINATOR / btc,
     }
   }
-  pub fn from_btc_value(fiat_unit: FiatUnit, btc_value: f64, fiat_value: f64) -> Rate{
-    Rate {
-      timestamp: timestamp,
-      fiat_unit: fiat_unit,
-      source: SourceExchange::Custom,
-      btc: fiat_value/btc_value,
-      sats: btc_value*SATS_DENOMINATOR/fiat_value
+
+  /// Fetches the current rate from a provider, bypassing the cache.
+  pub async fn now(provider: &(dyn RateProvider + Send + Sync), fiat_unit: FiatUnit) -> Result<Rate, S5Error> {
+    let btc = provider.btc_price(fiat_unit).await?;
+    if !btc.is_finite() || btc <= 0.0 {
+      return Err(S5Error::new(ErrorKind::Network, "Rate-Invalid"));
     }
+    Ok(Rate::from_btc_price(provider.source(), fiat_unit, btc))
   }
-  pub fn from_sats_value(fiat_unit: FiatUnit, sats_value: f64, fiat_value: f64) -> Rate{
-    Rate {
-      timestamp: timestamp,
-      fiat_unit: fiat_unit,
-      source: SourceExchange::Custom,
-      btc: fiat_value/( sats_value/SATS_DENOMINATOR ),
-      sats: sats_value/fiat_value
-    }
+
+  pub fn from_btc_value(fiat_unit: FiatUnit, btc_value: f64, fiat_value: f64) -> Rate {
+    Rate::from_btc_price(SourceExchange::Custom, fiat_unit, fiat_value / btc_value)
+  }
+
+  pub fn from_sats_value(fiat_unit: FiatUnit, sats_value: f64, fiat_value: f64) -> Rate {
+    Rate::from_btc_price(
+      SourceExchange::Custom,
+      fiat_unit,
+      fiat_value * SATS_DENOMINATOR / sats_value,
+    )
   }
-  pub fn add(&self, percentage: usize) -> Rate{
+
+  /// Adds a percentage premium to the BTC price.
+  pub fn add(&self, percentage: usize) -> Rate {
     Rate {
       timestamp: self.timestamp,
-      fiat_unit: self.fiat_unit,
-      source: SourceExchange::Custom,
-      btc: self.btc + (self.btc * percentage as f64 / 100.0),
-      sats: self.sats + (self.sats * percentage as f64 / 100.0)
+      ..Rate::from_btc_price(
+        SourceExchange::Custom,
+        self.fiat_unit,
+        self.btc + (self.btc * percentage as f64 / 100.0),
+      )
     }
   }
-  pub fn sub(&self, percentage: usize) -> Rate{
+
+  /// Subtracts a percentage discount from the BTC price.
+  pub fn sub(&self, percentage: usize) -> Rate {
     Rate {
       timestamp: self.timestamp,
-      fiat_unit: self.fiat_unit,
-      source: SourceExchange::Custom,
-      btc: self.btc - (self.btc * percentage as f64 / 100.0),
-      sats: self.sats - (self.sats * percentage as f64 / 100.0)
+      ..Rate::from_btc_price(
+        SourceExchange::Custom,
+        self.fiat_unit,
+        self.btc - (self.btc * percentage as f64 / 100.0),
+      )
+    }
+  }
+
+  pub fn to_fiat(&self, sats: u64) -> f64 {
+    sats as f64 * self.btc / SATS_DENOMINATOR
+  }
+
+  pub fn to_sats(&self, fiat: f64) -> u64 {
+    (fiat * self.sats).round() as u64
+  }
+}
+
+/// A source of BTC prices.
+#[async_trait]
+pub trait RateProvider {
+  fn source(&self) -> SourceExchange;
+  /// Value of 1 BTC in fiat_unit.
+  async fn btc_price(&self, fiat_unit: FiatUnit) -> Result<f64, S5Error>;
+}
+
+async fn get_json(url: &str) -> Result<Value, S5Error> {
+  let response = match reqwest::get(url).await {
+    Ok(response) => response,
+    Err(_) => return Err(S5Error::new(ErrorKind::Network, "Rate-Request")),
+  };
+  match response.json::<Value>().await {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Network, "Rate-Parse")),
+  }
+}
+
+async fn post_json(url: &str, body: &Value) -> Result<Value, S5Error> {
+  let response = match reqwest::Client::new().post(url).json(body).send().await {
+    Ok(response) => response,
+    Err(_) => return Err(S5Error::new(ErrorKind::Network, "Rate-Request")),
+  };
+  match response.json::<Value>().await {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Network, "Rate-Parse")),
+  }
+}
+
+/// Exchanges return prices either as numbers or as strings.
+fn price_value(value: Option<&Value>) -> Result<f64, S5Error> {
+  let price = match value {
+    Some(Value::String(price)) => price.parse::<f64>().ok(),
+    Some(Value::Number(price)) => price.as_f64(),
+    _ => None,
+  };
+  match price {
+    Some(price) => Ok(price),
+    None => Err(S5Error::new(ErrorKind::Network, "Rate-Unavailable")),
+  }
+}
+
+pub struct Kraken {
+  pub base_url: String,
+}
+
+impl Default for Kraken {
+  fn default() -> Self {
+    Kraken {
+      base_url: KRAKEN_URL.to_string(),
+    }
+  }
+}
+
+#[async_trait]
+impl RateProvider for Kraken {
+  fn source(&self) -> SourceExchange {
+    SourceExchange::Kraken
+  }
+
+  async fn btc_price(&self, fiat_unit: FiatUnit) -> Result<f64, S5Error> {
+    let url = format!("{}/0/public/Ticker?pair=XBT{}", self.base_url, fiat_unit);
+    let ticker = get_json(&url).await?;
+    // result is keyed by kraken's internal pair name, eg. XXBTZUSD
+    let pair = ticker["result"].as_object().and_then(|result| result.values().next());
+    match pair {
+      Some(pair) => price_value(pair["c"].get(0)),
+      None => Err(S5Error::new(ErrorKind::Network, "Rate-Unavailable")),
+    }
+  }
+}
+
+pub struct Strike {
+  pub base_url: String,
+}
+
+impl Default for Strike {
+  fn default() -> Self {
+    Strike {
+      base_url: STRIKE_URL.to_string(),
+    }
+  }
+}
+
+#[async_trait]
+impl RateProvider for Strike {
+  fn source(&self) -> SourceExchange {
+    SourceExchange::Strike
+  }
+
+  async fn btc_price(&self, fiat_unit: FiatUnit) -> Result<f64, S5Error> {
+    let url = format!("{}/v1/rates/ticker", self.base_url);
+    let ticker = get_json(&url).await?;
+    let rates = match ticker.as_array() {
+      Some(rates) => rates,
+      None => return Err(S5Error::new(ErrorKind::Network, "Rate-Parse")),
+    };
+    let rate = rates
+      .iter()
+      .find(|rate| rate["sourceCurrency"] == "BTC" && rate["targetCurrency"] == fiat_unit.to_string().as_str());
+    price_value(rate.and_then(|rate| rate.get("amount")))
+  }
+}
+
+pub struct BullBitcoin {
+  pub base_url: String,
+}
+
+impl Default for BullBitcoin {
+  fn default() -> Self {
+    BullBitcoin {
+      base_url: BULLBITCOIN_URL.to_string(),
     }
   }
 }
+
+#[async_trait]
+impl RateProvider for BullBitcoin {
+  fn source(&self) -> SourceExchange {
+    SourceExchange::BullBitcoin
+  }
+
+  async fn btc_price(&self, fiat_unit: FiatUnit) -> Result<f64, S5Error> {
+    let url = format!("{}/api/price", self.base_url);
+    let request = json!({
+      "id": 1,
+      "jsonrpc": "2.0",
+      "method": "getRate",
+      "params": {
+        "element": {
+          "fromCurrency": "BTC",
+          "toCurrency": fiat_unit.to_string()
+        }
+      }
+    });
+    let response = post_json(&url, &request).await?;
+    let element = &response["result"]["element"];
+    // price is an integer scaled by precision decimal places
+    let price = price_value(element.get("price"))?;
+    let precision = element["precision"].as_i64().unwrap_or(0);
+    Ok(price / 10f64.powi(precision as i32))
+  }
+}
+
+pub struct LocalBitcoins {
+  pub base_url: String,
+}
+
+impl Default for LocalBitcoins {
+  fn default() -> Self {
+    LocalBitcoins {
+      base_url: LOCALBITCOINS_URL.to_string(),
+    }
+  }
+}
+
+#[async_trait]
+impl RateProvider for LocalBitcoins {
+  fn source(&self) -> SourceExchange {
+    SourceExchange::LocalBitcoins
+  }
+
+  async fn btc_price(&self, fiat_unit: FiatUnit) -> Result<f64, S5Error> {
+    let url = format!("{}/bitcoinaverage/ticker-all-currencies/", self.base_url);
+    let ticker = get_json(&url).await?;
+    price_value(ticker[fiat_unit.to_string()]["rates"].get("last"))
+  }
+}
+
+/// A fixed, user supplied price.
+pub struct Custom {
+  pub btc: f64,
+}
+
+#[async_trait]
+impl RateProvider for Custom {
+  fn source(&self) -> SourceExchange {
+    SourceExchange::Custom
+  }
+
+  async fn btc_price(&self, _fiat_unit: FiatUnit) -> Result<f64, S5Error> {
+    Ok(self.btc)
+  }
+}
+
+/// Default provider for an exchange. Custom rates must be built with Custom { btc }.
+pub fn provider(source: SourceExchange) -> Result<Box<dyn RateProvider + Send + Sync>, S5Error> {
+  match source {
+    SourceExchange::Kraken => Ok(Box::new(Kraken::default())),
+    SourceExchange::Strike => Ok(Box::new(Strike::default())),
+    SourceExchange::BullBitcoin => Ok(Box::new(BullBitcoin::default())),
+    SourceExchange::LocalBitcoins => Ok(Box::new(LocalBitcoins::default())),
+    SourceExchange::Custom => Err(S5Error::new(ErrorKind::Input, "Invalid Custom Rate.")),
+  }
+}
+
+/// Latest rate per source and fiat unit.
+pub struct RateCache {
+  rates: Mutex<Vec<Rate>>,
+}
+
+impl RateCache {
+  pub const fn new() -> Self {
+    RateCache {
+      rates: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn cached(&self, source: SourceExchange, fiat_unit: FiatUnit, max_age: u64) -> Option<Rate> {
+    let now = timestamp();
+    match self.rates.lock() {
+      Ok(rates) => rates
+        .iter()
+        .find(|rate| rate.source == source && rate.fiat_unit == fiat_unit)
+        .filter(|rate| now.saturating_sub(rate.timestamp) <= max_age)
+        .copied(),
+      Err(_) => None,
+    }
+  }
+
+  pub fn insert(&self, rate: Rate) {
+    if let Ok(mut rates) = self.rates.lock() {
+      rates.retain(|existing| !(existing.source == rate.source && existing.fiat_unit == rate.fiat_unit));
+      rates.push(rate);
+    }
+  }
+
+  /// Returns a cached rate younger than max_age seconds, otherwise fetches and caches a new one.
+  /// Custom rates are never cached.
+  pub async fn get(
+    &self,
+    provider: &(dyn RateProvider + Send + Sync),
+    fiat_unit: FiatUnit,
+    max_age: u64,
+  ) -> Result<Rate, S5Error> {
+    if provider.source() != SourceExchange::Custom {
+      if let Some(rate) = self.cached(provider.source(), fiat_unit, max_age) {
+        return Ok(rate);
+      }
+    }
+    let rate = Rate::now(provider, fiat_unit).await?;
+    if rate.source != SourceExchange::Custom {
+      self.insert(rate);
+    }
+    Ok(rate)
+  }
+}
+
+impl Default for RateCache {
+  fn default() -> Self {
+    RateCache::new()
+  }
+}
+
+static RATE_CACHE: RateCache = RateCache::new();
+
+/// Rate from the given source using the shared cache. custom_rate is the value of 1 BTC and is only used with Custom.
+pub async fn get_rate(
+  source: SourceExchange,
+  fiat_unit: FiatUnit,
+  custom_rate: Option<f64>,
+  max_age: u64,
+) -> Result<Rate, S5Error> {
+  match source {
+    SourceExchange::Custom => match custom_rate {
+      Some(btc) if btc.is_finite() && btc > 0.0 => RATE_CACHE.get(&Custom { btc }, fiat_unit, max_age).await,
+      _ => Err(S5Error::new(ErrorKind::Input, "Invalid Custom Rate.")),
+    },
+    _ => RATE_CACHE.get(provider(source)?.as_ref(), fiat_unit, max_age).await,
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FiatConversion {
+  pub sats: u64,
+  pub fiat: f64,
+  pub rate: Rate,
+}
+
+impl FiatConversion {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+pub fn sats_to_fiat(sats: u64, rate: Rate) -> FiatConversion {
+  FiatConversion {
+    sats,
+    fiat: rate.to_fiat(sats),
+    rate,
+  }
+}
+
+pub fn fiat_to_sats(fiat: f64, rate: Rate) -> FiatConversion {
+  FiatConversion {
+    sats: rate.to_sats(fiat),
+    fiat,
+    rate,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::{Read, Write};
+  use std::net::TcpListener;
+  use std::thread;
+
+  /// Serves a fixed JSON body to the next `requests` connections and returns the base url.
+  fn stand_in(body: &'static str, requests: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+      for stream in listener.incoming().take(requests) {
+        let mut stream = stream.unwrap();
+        let mut buffer = [0; 4096];
+        let _ = stream.read(&mut buffer);
+        let response = format!(
+          "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+          body.len(),
+          body
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+      }
+    });
+    format!("http://{}", address)
+  }
+
+  fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+      .enable_all()
+      .build()
+      .unwrap()
+      .block_on(future)
+  }
+
+  #[test]
+  fn test_providers() {
+    let kraken = Kraken {
+      base_url: stand_in(
+        r#"{"error":[],"result":{"XXBTZUSD":{"a":["30001.0","1","1.000"],"c":["30000.5","0.001"]}}}"#,
+        1,
+      ),
+    };
+    let rate = block_on(Rate::now(&kraken, FiatUnit::USD)).unwrap();
+    assert_eq!(rate.btc, 30000.5);
+    assert_eq!(rate.source, SourceExchange::Kraken);
+
+    let strike = Strike {
+      base_url: stand_in(
+        r#"[{"amount":"0.00003","sourceCurrency":"USD","targetCurrency":"BTC"},{"amount":"28000.10","sourceCurrency":"BTC","targetCurrency":"EUR"}]"#,
+        2,
+      ),
+    };
+    assert_eq!(block_on(Rate::now(&strike, FiatUnit::EUR)).unwrap().btc, 28000.10);
+    let error = block_on(Rate::now(&strike, FiatUnit::INR)).err().unwrap();
+    assert_eq!(error.message, "Rate-Unavailable");
+
+    let bullbitcoin = BullBitcoin {
+      base_url: stand_in(
+        r#"{"jsonrpc":"2.0","id":1,"result":{"element":{"fromCurrency":"BTC","toCurrency":"CAD","price":4000012,"precision":2}}}"#,
+        1,
+      ),
+    };
+    assert_eq!(block_on(Rate::now(&bullbitcoin, FiatUnit::CAD)).unwrap().btc, 40000.12);
+
+    let localbitcoins = LocalBitcoins {
+      base_url: stand_in(r#"{"INR":{"volume_btc":"1.0","rates":{"last":"2500000.00"},"avg_1h":2400000.0}}"#, 1),
+    };
+    let rate = block_on(Rate::now(&localbitcoins, FiatUnit::INR)).unwrap();
+    assert_eq!(rate.btc, 2500000.0);
+    assert_eq!(rate.sats, 40.0);
+
+    let error = block_on(Rate::now(&Kraken { base_url: stand_in("not json", 1) }, FiatUnit::USD))
+      .err()
+      .unwrap();
+    assert_eq!(error.message, "Rate-Parse");
+  }
+
+  #[test]
+  fn test_rate_cache() {
+    // the stand in only answers once, so a second fetch would fail
+    let kraken = Kraken {
+      base_url: stand_in(r#"{"error":[],"result":{"XXBTZGBP":{"c":["20000.0","0.1"]}}}"#, 1),
+    };
+    let cache = RateCache::new();
+    let first = block_on(cache.get(&kraken, FiatUnit::GBP, DEFAULT_RATE_MAX_AGE)).unwrap();
+    let second = block_on(cache.get(&kraken, FiatUnit::GBP, DEFAULT_RATE_MAX_AGE)).unwrap();
+    assert_eq!(first, second);
+    assert!(cache.cached(SourceExchange::Kraken, FiatUnit::GBP, 60).is_some());
+    assert!(cache.cached(SourceExchange::Kraken, FiatUnit::USD, 60).is_none());
+
+    let mut stale = first;
+    stale.timestamp -= 120;
+    cache.insert(stale);
+    assert!(cache.cached(SourceExchange::Kraken, FiatUnit::GBP, 60).is_none());
+  }
+
+  #[test]
+  fn test_conversions() {
+    let rate = Rate::from_btc_value(FiatUnit::USD, 0.5, 25000.0);
+    assert_eq!(rate.btc, 50000.0);
+    assert_eq!(rate.sats, 2000.0);
+    assert_eq!(Rate::from_sats_value(FiatUnit::USD, 2000.0, 1.0).btc, 50000.0);
+
+    assert_eq!(sats_to_fiat(150_000, rate).fiat, 75.0);
+    assert_eq!(fiat_to_sats(75.0, rate).sats, 150_000);
+    assert_eq!(rate.add(10).btc, 55000.0);
+    assert_eq!(rate.sub(10).btc, 45000.0);
+
+    let custom = block_on(get_rate(SourceExchange::Custom, FiatUnit::EUR, Some(40000.0), 0)).unwrap();
+    assert_eq!(custom.source, SourceExchange::Custom);
+    let error = block_on(get_rate(SourceExchange::Custom, FiatUnit::EUR, None, 0)).err().unwrap();
+    assert_eq!(error.message, "Invalid Custom Rate.");
+
+    assert_eq!(FiatUnit::from_str("cad").unwrap(), FiatUnit::CAD);
+    assert!(FiatUnit::from_str("XYZ").is_err());
+    assert_eq!(SourceExchange::from_str("BullBitcoin").unwrap(), SourceExchange::BullBitcoin);
+  }
+}
@@ -3,10 +3,13 @@ use std::os::raw::c_char;
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "online")]
 use bdk::blockchain::Blockchain;
 use bdk::FeeRate;
 
+#[cfg(feature = "online")]
 use crate::config::WalletConfig;
+#[cfg(feature = "online")]
 use crate::e::{ErrorKind, S5Error};
 
 /// FFI Output
@@ -30,6 +33,7 @@ impl NetworkFee {
     }
 }
 
+#[cfg(feature = "online")]
 pub async fn estimate_rate(config: WalletConfig, target: usize) -> Result<NetworkFee, S5Error> {
     let fee = match config.client.estimate_fee(target).await {
         Ok(result) => result,
@@ -1,3 +1,4 @@
+#[cfg(feature = "online")]
 use crate::e::{ErrorKind, S5Error};
 
 use std::ffi::CString;
@@ -5,7 +6,9 @@ use std::os::raw::c_char;
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "online")]
 use crate::config::WalletConfig;
+#[cfg(feature = "online")]
 use bdk::blockchain::Blockchain;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -27,6 +30,7 @@ impl BlockHeight {
   }
 }
 
+#[cfg(feature = "online")]
 pub async fn get_height(config: WalletConfig) -> Result<BlockHeight, S5Error> {
   let height = match config.client.get_height().await {
     Ok(result) => result,
@@ -1,3 +1,5 @@
+#[cfg(feature = "online")]
+pub mod exchange;
 pub mod fees;
 pub mod height;
 // pub mod tor;
@@ -0,0 +1,430 @@
+// BBQr (Better Bitcoin QR) multi-part QR codes, as used by Coldcard Q.
+// Every frame is "B$" + encoding + file type + total parts and part index (2 base36 digits each) + payload.
+// Payloads are uppercase Base32 or hex so that frames fit the QR alphanumeric mode.
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use bitcoin::base64;
+use bitcoin::blockdata::transaction::Transaction;
+use bitcoin::consensus::deserialize;
+
+use crate::e::{ErrorKind, S5Error};
+use crate::qr::deflate;
+use crate::wallet::psbt;
+
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 1000;
+
+const HEADER_LENGTH: usize = 8;
+const MAX_PARTS: usize = 1295;
+/// Largest payload a Zlib frame set may inflate to.
+const MAX_PAYLOAD_LENGTH: usize = 4_000_000;
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BASE36_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BBQrFileType {
+  Psbt,
+  Tx,
+  Json,
+}
+
+impl BBQrFileType {
+  fn code(&self) -> char {
+    match self {
+      BBQrFileType::Psbt => 'P',
+      BBQrFileType::Tx => 'T',
+      BBQrFileType::Json => 'J',
+    }
+  }
+
+  fn from_code(code: char) -> Result<Self, S5Error> {
+    match code {
+      'P' => Ok(BBQrFileType::Psbt),
+      'T' => Ok(BBQrFileType::Tx),
+      'J' => Ok(BBQrFileType::Json),
+      _ => Err(S5Error::new(ErrorKind::Input, "Unsupported BBQr file type.")),
+    }
+  }
+}
+
+impl FromStr for BBQrFileType {
+  type Err = S5Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "psbt" => Ok(BBQrFileType::Psbt),
+      "tx" => Ok(BBQrFileType::Tx),
+      "json" => Ok(BBQrFileType::Json),
+      _ => Err(S5Error::new(ErrorKind::Input, "Invalid BBQr File Type.")),
+    }
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BBQrEncoding {
+  Hex,
+  Base32,
+  Zlib,
+}
+
+impl BBQrEncoding {
+  fn code(&self) -> char {
+    match self {
+      BBQrEncoding::Hex => 'H',
+      BBQrEncoding::Base32 => '2',
+      BBQrEncoding::Zlib => 'Z',
+    }
+  }
+
+  fn from_code(code: char) -> Result<Self, S5Error> {
+    match code {
+      'H' => Ok(BBQrEncoding::Hex),
+      '2' => Ok(BBQrEncoding::Base32),
+      'Z' => Ok(BBQrEncoding::Zlib),
+      _ => Err(S5Error::new(ErrorKind::Input, "Unsupported BBQr encoding.")),
+    }
+  }
+
+  /// Number of payload chars which decode independently (8 Base32 chars = 5 bytes, 2 hex chars = 1 byte).
+  fn char_block(&self) -> usize {
+    match self {
+      BBQrEncoding::Hex => 2,
+      _ => 8,
+    }
+  }
+}
+
+impl FromStr for BBQrEncoding {
+  type Err = S5Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "hex" => Ok(BBQrEncoding::Hex),
+      "base32" => Ok(BBQrEncoding::Base32),
+      "zlib" => Ok(BBQrEncoding::Zlib),
+      _ => Err(S5Error::new(ErrorKind::Input, "Invalid BBQr Encoding.")),
+    }
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BBQrFrames {
+  pub file_type: BBQrFileType,
+  pub encoding: BBQrEncoding,
+  pub frames: Vec<String>,
+}
+
+impl BBQrFrames {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BBQrJoined {
+  pub file_type: BBQrFileType,
+  pub encoding: BBQrEncoding,
+  pub psbt: Option<String>,
+  pub tx: Option<String>,
+  pub json: Option<String>,
+}
+
+impl BBQrJoined {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+fn base32_encode(data: &[u8]) -> String {
+  let mut out = String::new();
+  let mut buffer: u32 = 0;
+  let mut bits = 0;
+  for byte in data {
+    buffer = (buffer << 8) | *byte as u32;
+    bits += 8;
+    while bits >= 5 {
+      bits -= 5;
+      out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+    }
+  }
+  if bits > 0 {
+    out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+  }
+  out
+}
+
+fn base32_decode(text: &str) -> Result<Vec<u8>, S5Error> {
+  let mut out = vec![];
+  let mut buffer: u32 = 0;
+  let mut bits = 0;
+  for c in text.trim_end_matches('=').bytes() {
+    let value = match BASE32_ALPHABET.iter().position(|a| *a == c.to_ascii_uppercase()) {
+      Some(value) => value as u32,
+      None => return Err(S5Error::new(ErrorKind::Input, "BBQr-Base32")),
+    };
+    buffer = (buffer << 5) | value;
+    bits += 5;
+    if bits >= 8 {
+      bits -= 8;
+      out.push((buffer >> bits) as u8);
+    }
+  }
+  Ok(out)
+}
+
+fn base36_encode(value: usize) -> String {
+  format!(
+    "{}{}",
+    BASE36_ALPHABET[value / 36] as char,
+    BASE36_ALPHABET[value % 36] as char
+  )
+}
+
+fn base36_decode(text: &str) -> Result<usize, S5Error> {
+  match usize::from_str_radix(text, 36) {
+    Ok(value) => Ok(value),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "BBQr-Header")),
+  }
+}
+
+/// Returns the bytes carried for a payload, checking it is a valid PSBT, transaction or JSON.
+/// PSBTs can be base64 or hex, version 0 or 2, and keep their version. Transactions are hex.
+fn payload_bytes(data: &str, file_type: BBQrFileType) -> Result<Vec<u8>, S5Error> {
+  match file_type {
+    BBQrFileType::Psbt => {
+      let (psbt, version) = psbt::parse_psbt_versioned(data)?;
+      psbt::serialize_psbt(&psbt, version)
+    }
+    BBQrFileType::Tx => {
+      let bytes = match hex::decode(data.trim()) {
+        Ok(result) => result,
+        Err(_) => return Err(S5Error::new(ErrorKind::Input, "Transaction-Hex")),
+      };
+      match deserialize::<Transaction>(&bytes) {
+        Ok(_) => Ok(bytes),
+        Err(_) => Err(S5Error::new(ErrorKind::Input, "Transaction-Deserialize")),
+      }
+    }
+    BBQrFileType::Json => match serde_json::from_str::<serde_json::Value>(data) {
+      Ok(_) => Ok(data.as_bytes().to_vec()),
+      Err(_) => Err(S5Error::new(ErrorKind::Input, "Invalid JSON.")),
+    },
+  }
+}
+
+/// Splits a PSBT, raw transaction or JSON document into BBQr frames of at most max_frame_length chars.
+/// Zlib falls back to Base32 when compression does not make the payload smaller.
+/// Parts are balanced so every frame except the last has the same length.
+pub fn split(
+  data: &str,
+  file_type: BBQrFileType,
+  encoding: BBQrEncoding,
+  max_frame_length: usize,
+) -> Result<BBQrFrames, S5Error> {
+  let bytes = payload_bytes(data, file_type)?;
+  let (encoding, payload) = match encoding {
+    BBQrEncoding::Hex => (encoding, hex::encode_upper(&bytes)),
+    BBQrEncoding::Base32 => (encoding, base32_encode(&bytes)),
+    BBQrEncoding::Zlib => {
+      let compressed = deflate::compress(&bytes);
+      if compressed.len() < bytes.len() {
+        (encoding, base32_encode(&compressed))
+      } else {
+        (BBQrEncoding::Base32, base32_encode(&bytes))
+      }
+    }
+  };
+
+  let block = encoding.char_block();
+  let max_part_length = max_frame_length.saturating_sub(HEADER_LENGTH) / block * block;
+  if max_part_length == 0 {
+    return Err(S5Error::new(ErrorKind::Input, "BBQr frame length is too small."));
+  }
+  let part_count = std::cmp::max(1, payload.len().div_ceil(max_part_length));
+  if part_count > MAX_PARTS {
+    return Err(S5Error::new(ErrorKind::Input, "Payload is too large for BBQr."));
+  }
+  let part_length = payload.len().div_ceil(part_count).div_ceil(block) * block;
+
+  let frames = (0..part_count)
+    .map(|index| {
+      let start = std::cmp::min(index * part_length, payload.len());
+      let end = std::cmp::min(start + part_length, payload.len());
+      format!(
+        "B${}{}{}{}{}",
+        encoding.code(),
+        file_type.code(),
+        base36_encode(part_count),
+        base36_encode(index),
+        &payload[start..end]
+      )
+    })
+    .collect();
+
+  Ok(BBQrFrames {
+    file_type,
+    encoding,
+    frames,
+  })
+}
+
+/// Joins scanned BBQr frames, given in any order and possibly repeated, back into the original payload.
+pub fn join(frames: &[String]) -> Result<BBQrJoined, S5Error> {
+  let mut header: Option<(char, char, usize)> = None;
+  let mut parts: BTreeMap<usize, &str> = BTreeMap::new();
+  for frame in frames.iter().map(|frame| frame.trim()) {
+    if frame.len() < HEADER_LENGTH || !frame.starts_with("B$") || !frame.as_bytes()[..HEADER_LENGTH].is_ascii() {
+      return Err(S5Error::new(ErrorKind::Input, "BBQr-Header"));
+    }
+    let mut codes = frame[2..4].chars();
+    let encoding = codes.next().unwrap();
+    let file_type = codes.next().unwrap();
+    let total = base36_decode(&frame[4..6])?;
+    let index = base36_decode(&frame[6..8])?;
+    if index >= total {
+      return Err(S5Error::new(ErrorKind::Input, "BBQr-Header"));
+    }
+    match header {
+      Some(expected) if expected != (encoding, file_type, total) => {
+        return Err(S5Error::new(ErrorKind::Input, "BBQr frames are from different payloads."))
+      }
+      _ => header = Some((encoding, file_type, total)),
+    }
+    parts.insert(index, &frame[HEADER_LENGTH..]);
+  }
+
+  let (encoding, file_type, total) = match header {
+    Some(result) => result,
+    None => return Err(S5Error::new(ErrorKind::Input, "No BBQr frames.")),
+  };
+  if parts.len() != total {
+    return Err(S5Error::new(
+      ErrorKind::Input,
+      &format!("Missing BBQr frames: received {} of {}.", parts.len(), total),
+    ));
+  }
+  let encoding = BBQrEncoding::from_code(encoding)?;
+  let file_type = BBQrFileType::from_code(file_type)?;
+
+  let payload: String = parts.values().cloned().collect();
+  let bytes = match encoding {
+    BBQrEncoding::Hex => match hex::decode(&payload) {
+      Ok(result) => result,
+      Err(_) => return Err(S5Error::new(ErrorKind::Input, "BBQr-Hex")),
+    },
+    BBQrEncoding::Base32 => base32_decode(&payload)?,
+    BBQrEncoding::Zlib => deflate::decompress(&base32_decode(&payload)?, MAX_PAYLOAD_LENGTH)?,
+  };
+
+  let mut joined = BBQrJoined {
+    file_type,
+    encoding,
+    psbt: None,
+    tx: None,
+    json: None,
+  };
+  match file_type {
+    BBQrFileType::Psbt => {
+      let encoded = base64::encode(&bytes);
+      psbt::parse_psbt(&encoded)?;
+      joined.psbt = Some(encoded);
+    }
+    BBQrFileType::Tx => {
+      if deserialize::<Transaction>(&bytes).is_err() {
+        return Err(S5Error::new(ErrorKind::Input, "Transaction-Deserialize"));
+      }
+      joined.tx = Some(hex::encode(&bytes));
+    }
+    BBQrFileType::Json => match String::from_utf8(bytes) {
+      Ok(text) => joined.json = Some(text),
+      Err(_) => return Err(S5Error::new(ErrorKind::Input, "Invalid JSON.")),
+    },
+  }
+  Ok(joined)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bitcoin::consensus::encode::serialize_hex;
+
+  const PSBT: &str = "cHNidP8BAHQBAAAAAf3cLERUN9+6X5+1yk3x9XzSCq1417WtB+gB5qNyj+xpAAAAAAD9////AnRxAQAAAAAAFgAUVyorkNVSCsiE4/7OspP52IwquzqIEwAAAAAAABl2qRQ0Sg9IyhUOwrkDgXZgubaLE6ZwJoisAAAAAAABAN4CAAAAAAEByvn9X3PvFqemGsrTv8ivAO07IOeRhBz7J0huqXJLfVgBAAAAAP7///8CoIYBAAAAAAAWABQTXAMs/1Qr5n6pDVK9O15ODZ/UCVZWjQAAAAAAFgAUIixaISTPlO8fwyT3hCL+An5+Km4CRzBEAiBFsQJfBur3eQgO5Vw+EvEgr2CagcVGXw9oYw3FOaMSSgIgch0CV+W3oRCKNBwxqiqIK0C5b1TsGk32HvNM+4Z7IksBIQNP/rsBHKbA98977TzmriFrOuO8hQjNg4ON3goI9/Uwjp0BIAABAR+ghgEAAAAAABYAFBNcAyz/VCvmfqkNUr07Xk4Nn9QJIgYD9WhlKKSeNh6567KTmyKrlitDWZOz/+mms7emVsWjGTsY230ltVQAAIABAACABgAAgAAAAAABAAAAACICAgHPrE7CShQkK90ApPF8xdr+8o7T/sHggOlZNOHIUft/GNt9JbVUAACAAQAAgAYAAIABAAAAAQAAAAAA";
+
+  #[test]
+  fn test_bbqr_json() {
+    let json = r#"{"chain":"XTN","xfp":"0F056943"}"#;
+    // python: base64.b32encode(json).rstrip("=")
+    let frames = split(json, BBQrFileType::Json, BBQrEncoding::Base32, 32).unwrap();
+    assert_eq!(
+      frames.frames,
+      vec![
+        "B$2J0300PMRGG2DBNFXCEORCLBKE4IRM",
+        "B$2J0301EJ4GM4BCHIRDARRQGU3DSNBT",
+        "B$2J0302EJ6Q",
+      ]
+    );
+    let mut reversed = frames.frames.clone();
+    reversed.reverse();
+    reversed.push(frames.frames[1].clone());
+    let joined = join(&reversed).unwrap();
+    assert_eq!(joined.file_type, BBQrFileType::Json);
+    assert_eq!(joined.json.unwrap(), json);
+
+    let error = join(&frames.frames[..2]).unwrap_err();
+    assert_eq!(error.message, "Missing BBQr frames: received 2 of 3.");
+    assert!(split("not json", BBQrFileType::Json, BBQrEncoding::Base32, 32).is_err());
+    assert_eq!(join(&["B$aéb0100AAAA".to_string()]).unwrap_err().message, "BBQr-Header");
+  }
+
+  #[test]
+  fn test_bbqr_psbt_and_tx() {
+    for encoding in [BBQrEncoding::Hex, BBQrEncoding::Base32, BBQrEncoding::Zlib] {
+      let frames = split(PSBT, BBQrFileType::Psbt, encoding, 100).unwrap();
+      assert!(frames.frames.len() > 1);
+      assert!(frames.frames.iter().all(|frame| frame.len() <= 100));
+      let mut rotated = frames.frames.clone();
+      rotated.rotate_left(1);
+      let joined = join(&rotated).unwrap();
+      assert_eq!(joined.file_type, BBQrFileType::Psbt);
+      assert_eq!(joined.encoding, frames.encoding);
+      assert_eq!(joined.psbt.unwrap(), PSBT);
+    }
+
+    let tx = serialize_hex(&psbt::parse_psbt(PSBT).unwrap().global.unsigned_tx);
+    let frames = split(&tx, BBQrFileType::Tx, BBQrEncoding::Hex, DEFAULT_MAX_FRAME_LENGTH).unwrap();
+    assert_eq!(frames.frames.len(), 1);
+    assert!(frames.frames[0].starts_with("B$HT0100"));
+    assert_eq!(join(&frames.frames).unwrap().tx.unwrap(), tx);
+    assert!(split("00", BBQrFileType::Tx, BBQrEncoding::Hex, DEFAULT_MAX_FRAME_LENGTH).is_err());
+
+    let other = split(r#"{"a":1}"#, BBQrFileType::Json, BBQrEncoding::Hex, 100).unwrap();
+    let mixed = vec![frames.frames[0].clone(), other.frames[0].clone()];
+    assert!(join(&mixed).is_err());
+  }
+}
@@ -0,0 +1,106 @@
+// Bytewords (BCR-2020-012): bytes as four letter words, with a CRC32 checksum appended.
+// UR uses the minimal style, the first and last letter of each word.
+use crate::e::{ErrorKind, S5Error};
+
+const WORDS: [&str; 256] = [
+  "able", "acid", "also", "apex", "aqua", "arch", "atom", "aunt", "away", "axis", "back", "bald", "barn", "belt", "beta", "bias",
+  "blue", "body", "brag", "brew", "bulb", "buzz", "calm", "cash", "cats", "chef", "city", "claw", "code", "cola", "cook", "cost",
+  "crux", "curl", "cusp", "cyan", "dark", "data", "days", "deli", "dice", "diet", "door", "down", "draw", "drop", "drum", "dull",
+  "duty", "each", "easy", "echo", "edge", "epic", "even", "exam", "exit", "eyes", "fact", "fair", "fern", "figs", "film", "fish",
+  "fizz", "flap", "flew", "flux", "foxy", "free", "frog", "fuel", "fund", "gala", "game", "gear", "gems", "gift", "girl", "glow",
+  "good", "gray", "grim", "guru", "gush", "gyro", "half", "hang", "hard", "hawk", "heat", "help", "high", "hill", "holy", "hope",
+  "horn", "huts", "iced", "idea", "idle", "inch", "inky", "into", "iris", "iron", "item", "jade", "jazz", "join", "jolt", "jowl",
+  "judo", "jugs", "jump", "junk", "jury", "keep", "keno", "kept", "keys", "kick", "kiln", "king", "kite", "kiwi", "knob", "lamb",
+  "lava", "lazy", "leaf", "legs", "liar", "limp", "lion", "list", "logo", "loud", "love", "luau", "luck", "lung", "main", "many",
+  "math", "maze", "memo", "menu", "meow", "mild", "mint", "miss", "monk", "nail", "navy", "need", "news", "next", "noon", "note",
+  "numb", "obey", "oboe", "omit", "onyx", "open", "oval", "owls", "paid", "part", "peck", "play", "plus", "poem", "pool", "pose",
+  "puff", "puma", "purr", "quad", "quiz", "race", "ramp", "real", "redo", "rich", "road", "rock", "roof", "ruby", "ruin", "runs",
+  "rust", "safe", "saga", "scar", "sets", "silk", "skew", "slot", "soap", "solo", "song", "stub", "surf", "swan", "taco", "task",
+  "taxi", "tent", "tied", "time", "tiny", "toil", "tomb", "toys", "trip", "tuna", "twin", "ugly", "undo", "unit", "urge", "user",
+  "vast", "very", "veto", "vial", "vibe", "view", "visa", "void", "vows", "wall", "wand", "warm", "wasp", "wave", "waxy", "webs",
+  "what", "when", "whiz", "wolf", "work", "yank", "yawn", "yell", "yoga", "yurt", "zaps", "zero", "zest", "zinc", "zone", "zoom",
+];
+
+/// CRC32 (ISO-HDLC), as used by UR and zlib.
+pub fn crc32(data: &[u8]) -> u32 {
+  let mut crc = 0xFFFFFFFFu32;
+  for byte in data {
+    crc ^= *byte as u32;
+    for _ in 0..8 {
+      crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+    }
+  }
+  !crc
+}
+
+fn with_checksum(data: &[u8]) -> Vec<u8> {
+  let mut bytes = data.to_vec();
+  bytes.extend_from_slice(&crc32(data).to_be_bytes());
+  bytes
+}
+
+fn strip_checksum(bytes: Vec<u8>) -> Result<Vec<u8>, S5Error> {
+  if bytes.len() < 4 {
+    return Err(S5Error::new(ErrorKind::Input, "Bytewords-Checksum"));
+  }
+  let (data, checksum) = bytes.split_at(bytes.len() - 4);
+  if crc32(data).to_be_bytes() != checksum {
+    return Err(S5Error::new(ErrorKind::Input, "Bytewords-Checksum"));
+  }
+  Ok(data.to_vec())
+}
+
+/// Encodes as space separated full words.
+pub fn encode_standard(data: &[u8]) -> String {
+  with_checksum(data)
+    .iter()
+    .map(|byte| WORDS[*byte as usize])
+    .collect::<Vec<&str>>()
+    .join(" ")
+}
+
+/// Encodes as the first and last letter of each word, with no separator.
+pub fn encode_minimal(data: &[u8]) -> String {
+  with_checksum(data)
+    .iter()
+    .map(|byte| {
+      let word = WORDS[*byte as usize].as_bytes();
+      format!("{}{}", word[0] as char, word[3] as char)
+    })
+    .collect()
+}
+
+/// Decodes minimal bytewords. Case insensitive.
+pub fn decode_minimal(encoded: &str) -> Result<Vec<u8>, S5Error> {
+  let encoded = encoded.to_lowercase();
+  if !encoded.len().is_multiple_of(2) || !encoded.is_ascii() {
+    return Err(S5Error::new(ErrorKind::Input, "Bytewords-Decode"));
+  }
+  let mut bytes = vec![];
+  for pair in encoded.as_bytes().chunks(2) {
+    match WORDS
+      .iter()
+      .position(|word| word.as_bytes()[0] == pair[0] && word.as_bytes()[3] == pair[1])
+    {
+      Some(index) => bytes.push(index as u8),
+      None => return Err(S5Error::new(ErrorKind::Input, "Bytewords-Decode")),
+    }
+  }
+  strip_checksum(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_bytewords() {
+    assert_eq!(crc32(b"Hello, world!"), 0xebe6c6e6);
+    let data = [0, 1, 2, 128, 255];
+    assert_eq!(encode_standard(&data), "able acid also lava zoom jade need echo taxi");
+    assert_eq!(encode_minimal(&data), "aeadaolazmjendeoti");
+    assert_eq!(decode_minimal("AEADAOLAZMJENDEOTI").unwrap(), data.to_vec());
+    assert_eq!(decode_minimal("aeadaolazmjendeott").err().unwrap().message, "Bytewords-Checksum");
+    assert!(decode_minimal("xxadaolazmjendeoti").is_err());
+  }
+}
@@ -0,0 +1,204 @@
+// Minimal CBOR (RFC 8949) for UR payloads.
+// Only definite lengths and the major types UR uses: unsigned ints, byte and text strings, arrays, maps, tags and bools.
+use crate::e::{ErrorKind, S5Error};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cbor {
+  Unsigned(u64),
+  Bytes(Vec<u8>),
+  Text(String),
+  Array(Vec<Cbor>),
+  Map(Vec<(Cbor, Cbor)>),
+  Tag(u64, Box<Cbor>),
+  Bool(bool),
+}
+
+fn write_head(out: &mut Vec<u8>, major: u8, value: u64) {
+  let major = major << 5;
+  if value < 24 {
+    out.push(major | value as u8);
+  } else if value <= u8::MAX as u64 {
+    out.push(major | 24);
+    out.push(value as u8);
+  } else if value <= u16::MAX as u64 {
+    out.push(major | 25);
+    out.extend_from_slice(&(value as u16).to_be_bytes());
+  } else if value <= u32::MAX as u64 {
+    out.push(major | 26);
+    out.extend_from_slice(&(value as u32).to_be_bytes());
+  } else {
+    out.push(major | 27);
+    out.extend_from_slice(&value.to_be_bytes());
+  }
+}
+
+/// Deepest nesting of arrays, maps and tags accepted. UR registry types only nest a few levels.
+const MAX_DEPTH: usize = 32;
+
+fn decode_error() -> S5Error {
+  S5Error::new(ErrorKind::Input, "CBOR-Decode")
+}
+
+struct Reader<'a> {
+  data: &'a [u8],
+  position: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, len: usize) -> Result<&'a [u8], S5Error> {
+    let end = match self.position.checked_add(len).filter(|end| *end <= self.data.len()) {
+      Some(end) => end,
+      None => return Err(decode_error()),
+    };
+    let bytes = &self.data[self.position..end];
+    self.position = end;
+    Ok(bytes)
+  }
+
+  fn head(&mut self) -> Result<(u8, u64), S5Error> {
+    let initial = self.take(1)?[0];
+    let major = initial >> 5;
+    let value = match initial & 0x1f {
+      short if short < 24 => short as u64,
+      24 => self.take(1)?[0] as u64,
+      25 => self.take(2)?.iter().fold(0, |acc, byte| (acc << 8) | *byte as u64),
+      26 => self.take(4)?.iter().fold(0, |acc, byte| (acc << 8) | *byte as u64),
+      27 => self.take(8)?.iter().fold(0, |acc, byte| (acc << 8) | *byte as u64),
+      _ => return Err(decode_error()),
+    };
+    Ok((major, value))
+  }
+
+  fn item(&mut self, depth: usize) -> Result<Cbor, S5Error> {
+    if depth > MAX_DEPTH {
+      return Err(decode_error());
+    }
+    let (major, value) = self.head()?;
+    match major {
+      0 => Ok(Cbor::Unsigned(value)),
+      2 => Ok(Cbor::Bytes(self.take(value as usize)?.to_vec())),
+      3 => match String::from_utf8(self.take(value as usize)?.to_vec()) {
+        Ok(text) => Ok(Cbor::Text(text)),
+        Err(_) => Err(decode_error()),
+      },
+      4 => {
+        let mut items = vec![];
+        for _ in 0..value {
+          items.push(self.item(depth + 1)?);
+        }
+        Ok(Cbor::Array(items))
+      }
+      5 => {
+        let mut entries = vec![];
+        for _ in 0..value {
+          entries.push((self.item(depth + 1)?, self.item(depth + 1)?));
+        }
+        Ok(Cbor::Map(entries))
+      }
+      6 => Ok(Cbor::Tag(value, Box::new(self.item(depth + 1)?))),
+      7 => match value {
+        20 => Ok(Cbor::Bool(false)),
+        21 => Ok(Cbor::Bool(true)),
+        _ => Err(decode_error()),
+      },
+      _ => Err(decode_error()),
+    }
+  }
+}
+
+impl Cbor {
+  /// Builds a map with unsigned int keys, the form used by every UR registry type.
+  pub fn int_map(entries: Vec<(u64, Cbor)>) -> Cbor {
+    Cbor::Map(
+      entries
+        .into_iter()
+        .map(|(key, value)| (Cbor::Unsigned(key), value))
+        .collect(),
+    )
+  }
+
+  pub fn tagged(tag: u64, item: Cbor) -> Cbor {
+    Cbor::Tag(tag, Box::new(item))
+  }
+
+  pub fn encode(&self) -> Vec<u8> {
+    let mut out = vec![];
+    self.write(&mut out);
+    out
+  }
+
+  fn write(&self, out: &mut Vec<u8>) {
+    match self {
+      Cbor::Unsigned(value) => write_head(out, 0, *value),
+      Cbor::Bytes(bytes) => {
+        write_head(out, 2, bytes.len() as u64);
+        out.extend_from_slice(bytes);
+      }
+      Cbor::Text(text) => {
+        write_head(out, 3, text.len() as u64);
+        out.extend_from_slice(text.as_bytes());
+      }
+      Cbor::Array(items) => {
+        write_head(out, 4, items.len() as u64);
+        for item in items {
+          item.write(out);
+        }
+      }
+      Cbor::Map(entries) => {
+        write_head(out, 5, entries.len() as u64);
+        for (key, value) in entries {
+          key.write(out);
+          value.write(out);
+        }
+      }
+      Cbor::Tag(tag, item) => {
+        write_head(out, 6, *tag);
+        item.write(out);
+      }
+      Cbor::Bool(value) => out.push(if *value { 0xf5 } else { 0xf4 }),
+    }
+  }
+
+  /// Decodes a single CBOR item which must span all of data.
+  pub fn decode(data: &[u8]) -> Result<Cbor, S5Error> {
+    let mut reader = Reader { data, position: 0 };
+    let item = reader.item(0)?;
+    if reader.position != data.len() {
+      return Err(decode_error());
+    }
+    Ok(item)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_cbor() {
+    // RFC 8949 Appendix A
+    assert_eq!(Cbor::Unsigned(23).encode(), vec![0x17]);
+    assert_eq!(Cbor::Unsigned(1000).encode(), vec![0x19, 0x03, 0xe8]);
+    assert_eq!(Cbor::Unsigned(1000000).encode(), vec![0x1a, 0x00, 0x0f, 0x42, 0x40]);
+    assert_eq!(Cbor::Bytes(vec![1, 2, 3, 4]).encode(), vec![0x44, 1, 2, 3, 4]);
+    assert_eq!(Cbor::Text("IETF".to_string()).encode(), vec![0x64, 0x49, 0x45, 0x54, 0x46]);
+    assert_eq!(Cbor::Bool(true).encode(), vec![0xf5]);
+    let nested = Cbor::Array(vec![
+      Cbor::Unsigned(1),
+      Cbor::tagged(303, Cbor::int_map(vec![(1, Cbor::Bytes(vec![0xff])), (2, Cbor::Bool(false))])),
+    ]);
+    let encoded = nested.encode();
+    assert_eq!(hex::encode(&encoded), "8201d9012fa20141ff02f4");
+    assert_eq!(Cbor::decode(&encoded).unwrap(), nested);
+    assert!(Cbor::decode(&encoded[..encoded.len() - 1]).is_err());
+
+    // byte string of u64::MAX length
+    assert!(Cbor::decode(&[0x5b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+    let mut deep = vec![0x81; MAX_DEPTH];
+    deep.push(0x01);
+    assert!(Cbor::decode(&deep).is_ok());
+    let mut too_deep = vec![0x81; 500_000];
+    too_deep.push(0x01);
+    assert!(Cbor::decode(&too_deep).is_err());
+  }
+}
@@ -0,0 +1,380 @@
+// Raw DEFLATE (RFC 1951) for BBQr "Z" frames, which are compressed with zlib wbits=-10 (no header, 1KB window).
+// compress uses LZ77 with the fixed Huffman codes; decompress handles stored, fixed and dynamic blocks.
+use std::collections::HashMap;
+
+use crate::e::{ErrorKind, S5Error};
+
+const WINDOW_SIZE: usize = 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+
+const LENGTH_BASE: [u16; 29] = [
+  3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [
+  1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+  8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+  0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct BitWriter {
+  out: Vec<u8>,
+  buffer: u32,
+  count: u32,
+}
+
+impl BitWriter {
+  fn write(&mut self, value: u32, bits: u32) {
+    self.buffer |= value << self.count;
+    self.count += bits;
+    while self.count >= 8 {
+      self.out.push(self.buffer as u8);
+      self.buffer >>= 8;
+      self.count -= 8;
+    }
+  }
+
+  /// Huffman codes are packed starting from their most significant bit.
+  fn write_code(&mut self, code: u32, bits: u32) {
+    let mut reversed = 0;
+    for i in 0..bits {
+      reversed |= ((code >> i) & 1) << (bits - 1 - i);
+    }
+    self.write(reversed, bits);
+  }
+
+  fn finish(mut self) -> Vec<u8> {
+    if self.count > 0 {
+      self.out.push(self.buffer as u8);
+    }
+    self.out
+  }
+}
+
+fn write_literal(writer: &mut BitWriter, symbol: u16) {
+  match symbol {
+    0..=143 => writer.write_code(0x30 + symbol as u32, 8),
+    144..=255 => writer.write_code(0x190 + (symbol as u32 - 144), 9),
+    256..=279 => writer.write_code(symbol as u32 - 256, 7),
+    _ => writer.write_code(0xC0 + (symbol as u32 - 280), 8),
+  }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+  let code = LENGTH_BASE.iter().rposition(|base| *base as usize <= length).unwrap();
+  write_literal(writer, 257 + code as u16);
+  writer.write((length - LENGTH_BASE[code] as usize) as u32, LENGTH_EXTRA[code] as u32);
+  let code = DIST_BASE.iter().rposition(|base| *base as usize <= distance).unwrap();
+  writer.write_code(code as u32, 5);
+  writer.write((distance - DIST_BASE[code] as usize) as u32, DIST_EXTRA[code] as u32);
+}
+
+/// Compresses into a single fixed Huffman block, with match distances inside a 1KB window.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+  let mut writer = BitWriter {
+    out: vec![],
+    buffer: 0,
+    count: 0,
+  };
+  // final block, fixed Huffman codes
+  writer.write(1, 1);
+  writer.write(1, 2);
+
+  let mut chains: HashMap<[u8; 3], Vec<usize>> = HashMap::new();
+  let mut position = 0;
+  while position < data.len() {
+    let mut best = (0, 0);
+    if position + MIN_MATCH <= data.len() {
+      let key = [data[position], data[position + 1], data[position + 2]];
+      if let Some(candidates) = chains.get(&key) {
+        for candidate in candidates.iter().rev().take(MAX_CHAIN) {
+          let distance = position - candidate;
+          if distance > WINDOW_SIZE {
+            break;
+          }
+          let length = data[position..]
+            .iter()
+            .zip(data[*candidate..].iter())
+            .take(MAX_MATCH)
+            .take_while(|(a, b)| a == b)
+            .count();
+          if length > best.0 {
+            best = (length, distance);
+          }
+        }
+      }
+    }
+    let advance = if best.0 >= MIN_MATCH {
+      write_match(&mut writer, best.0, best.1);
+      best.0
+    } else {
+      write_literal(&mut writer, data[position] as u16);
+      1
+    };
+    for index in position..position + advance {
+      if index + MIN_MATCH <= data.len() {
+        chains
+          .entry([data[index], data[index + 1], data[index + 2]])
+          .or_default()
+          .push(index);
+      }
+    }
+    position += advance;
+  }
+  write_literal(&mut writer, 256);
+  writer.finish()
+}
+
+fn inflate_error() -> S5Error {
+  S5Error::new(ErrorKind::Input, "Inflate-Error")
+}
+
+struct BitReader<'a> {
+  data: &'a [u8],
+  position: usize,
+  bit: u32,
+}
+
+impl BitReader<'_> {
+  fn bit(&mut self) -> Result<u32, S5Error> {
+    let byte = *self.data.get(self.position).ok_or_else(inflate_error)?;
+    let value = (byte >> self.bit) & 1;
+    self.bit += 1;
+    if self.bit == 8 {
+      self.bit = 0;
+      self.position += 1;
+    }
+    Ok(value as u32)
+  }
+
+  fn bits(&mut self, count: u32) -> Result<u32, S5Error> {
+    let mut value = 0;
+    for i in 0..count {
+      value |= self.bit()? << i;
+    }
+    Ok(value)
+  }
+
+  fn align(&mut self) {
+    if self.bit > 0 {
+      self.bit = 0;
+      self.position += 1;
+    }
+  }
+}
+
+/// Canonical Huffman decoding table: code counts per length, and symbols in code order.
+struct Huffman {
+  counts: [u16; 16],
+  symbols: Vec<u16>,
+}
+
+impl Huffman {
+  fn new(lengths: &[u8]) -> Huffman {
+    let mut counts = [0u16; 16];
+    for length in lengths {
+      counts[*length as usize] += 1;
+    }
+    counts[0] = 0;
+    let mut symbols = vec![];
+    for length in 1..16 {
+      for (symbol, symbol_length) in lengths.iter().enumerate() {
+        if *symbol_length as usize == length {
+          symbols.push(symbol as u16);
+        }
+      }
+    }
+    Huffman { counts, symbols }
+  }
+
+  fn decode(&self, reader: &mut BitReader) -> Result<u16, S5Error> {
+    let mut code: i32 = 0;
+    let mut first: i32 = 0;
+    let mut index: i32 = 0;
+    for length in 1..16 {
+      code |= reader.bit()? as i32;
+      let count = self.counts[length] as i32;
+      if code - count < first {
+        return Ok(self.symbols[(index + (code - first)) as usize]);
+      }
+      index += count;
+      first += count;
+      first <<= 1;
+      code <<= 1;
+    }
+    Err(inflate_error())
+  }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+  let mut lengths = [0u8; 288];
+  for (symbol, length) in lengths.iter_mut().enumerate() {
+    *length = match symbol {
+      0..=143 => 8,
+      144..=255 => 9,
+      256..=279 => 7,
+      _ => 8,
+    };
+  }
+  (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), S5Error> {
+  let literal_count = reader.bits(5)? as usize + 257;
+  let distance_count = reader.bits(5)? as usize + 1;
+  let code_length_count = reader.bits(4)? as usize + 4;
+  let mut code_lengths = [0u8; 19];
+  for index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+    code_lengths[*index] = reader.bits(3)? as u8;
+  }
+  let code_length_table = Huffman::new(&code_lengths);
+
+  let mut lengths = vec![];
+  while lengths.len() < literal_count + distance_count {
+    let symbol = code_length_table.decode(reader)?;
+    let (value, repeat) = match symbol {
+      0..=15 => (symbol as u8, 1),
+      16 => (*lengths.last().ok_or_else(inflate_error)?, 3 + reader.bits(2)?),
+      17 => (0, 3 + reader.bits(3)?),
+      18 => (0, 11 + reader.bits(7)?),
+      _ => return Err(inflate_error()),
+    };
+    for _ in 0..repeat {
+      lengths.push(value);
+    }
+  }
+  if lengths.len() != literal_count + distance_count {
+    return Err(inflate_error());
+  }
+  Ok((
+    Huffman::new(&lengths[..literal_count]),
+    Huffman::new(&lengths[literal_count..]),
+  ))
+}
+
+/// Decompresses raw DEFLATE data (no zlib header).
+/// Fails once the output would be larger than max_output, so a small input cannot expand without bound.
+pub fn decompress(data: &[u8], max_output: usize) -> Result<Vec<u8>, S5Error> {
+  let mut reader = BitReader {
+    data,
+    position: 0,
+    bit: 0,
+  };
+  let mut out: Vec<u8> = vec![];
+  loop {
+    let last = reader.bit()? == 1;
+    match reader.bits(2)? {
+      0 => {
+        reader.align();
+        let header = data
+          .get(reader.position..reader.position + 4)
+          .ok_or_else(inflate_error)?;
+        let length = u16::from_le_bytes([header[0], header[1]]);
+        if length != !u16::from_le_bytes([header[2], header[3]]) {
+          return Err(inflate_error());
+        }
+        reader.position += 4;
+        let stored = data
+          .get(reader.position..reader.position + length as usize)
+          .ok_or_else(inflate_error)?;
+        if out.len() + stored.len() > max_output {
+          return Err(inflate_error());
+        }
+        out.extend_from_slice(stored);
+        reader.position += length as usize;
+      }
+      block_type @ 1..=2 => {
+        let (literals, distances) = if block_type == 1 {
+          fixed_tables()
+        } else {
+          dynamic_tables(&mut reader)?
+        };
+        loop {
+          let symbol = literals.decode(&mut reader)? as usize;
+          if symbol < 256 {
+            if out.len() >= max_output {
+              return Err(inflate_error());
+            }
+            out.push(symbol as u8);
+          } else if symbol == 256 {
+            break;
+          } else {
+            let code = symbol - 257;
+            if code >= LENGTH_BASE.len() {
+              return Err(inflate_error());
+            }
+            let length = LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code] as u32)? as usize;
+            let code = distances.decode(&mut reader)? as usize;
+            if code >= DIST_BASE.len() {
+              return Err(inflate_error());
+            }
+            let distance = DIST_BASE[code] as usize + reader.bits(DIST_EXTRA[code] as u32)? as usize;
+            if distance > out.len() || out.len() + length > max_output {
+              return Err(inflate_error());
+            }
+            let start = out.len() - distance;
+            for i in 0..length {
+              out.push(out[start + i]);
+            }
+          }
+        }
+      }
+      _ => return Err(inflate_error()),
+    }
+    if last {
+      return Ok(out);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_deflate() {
+    // vectors from python zlib.compressobj(9, zlib.DEFLATED, -10)
+    let fixed = hex::decode("cb48cdc9c957c8402701").unwrap();
+    assert_eq!(decompress(&fixed, 1000).unwrap(), b"hello hello hello hello".to_vec());
+
+    let text: Vec<u8> = (0..300)
+      .flat_map(|i: usize| format!("{}:{};", i, &"abcdefghij"[..(i * 7) % 10 + 1]).into_bytes())
+      .collect();
+    let dynamic = hex::decode(DYNAMIC).unwrap();
+    assert_eq!(dynamic[0] & 0b110, 0b100);
+    assert_eq!(decompress(&dynamic, 10_000).unwrap(), text);
+
+    let compressed = compress(&text);
+    assert!(compressed.len() < text.len() / 2);
+    assert_eq!(decompress(&compressed, 10_000).unwrap(), text);
+    assert_eq!(decompress(&compress(b""), 0).unwrap(), b"".to_vec());
+    assert!(decompress(&compressed[..compressed.len() / 2], 10_000).is_err());
+    assert!(decompress(&compressed, text.len() - 1).is_err());
+    assert!(decompress(&fixed, 22).is_err());
+    assert!(decompress(&compressed, text.len()).is_ok());
+    let zeros = compress(&[0; 100_000]);
+    assert!(zeros.len() < 1_000);
+    assert!(decompress(&zeros, 50_000).is_err());
+  }
+
+  const DYNAMIC: &str = concat!(
+    "55d2db91d8300840d196040221d6d56cb279f65f406281787c19db33f77864c6c7e7031f9fdfbe7ffdf8f9ebf783363ef3ff",
+    "f5a178fee7619f9ff50e8fe4abbfcfbe378f9ee981f1564b166e174e186a19220dd6861687ac83e7f1cd63fdea9bc793c79a",
+    "c7c8a3e5b1e531f3e8f9f9e667c9cf3895939f353f233f2d3f5b7e667e7a9ede3c953cdd3cd9a9d73c459e2c4f2d4f9927cf",
+    "f39be792e79be793e7f65723cf96e796e7ccb3e7d79b5f25bf6e7e9dfcaaf9955b63f9d5f22bf3cbf3f2e6a5e4e5e6e5e4a5",
+    "e625f2e25bd9f29279f1fc7ef3bbe4f7cdef93df35bf23bf2dbffbd6677e7b5edfbc96bcdebc9ebcd6bc465e2daf2daf9957",
+    "cfc378fb300a00e30a300e01a31a300281610a8cc6c04807c685e040502108080c82064142e01074080a0417c20361853020",
+    "34081b8409a143d8212c105e681e68566806340d9a0d9a094d8766876681e685e84054210a880ca2065142e41075880a4417",
+    "e2037185382036881bc409b143dc212e105f681d68556805b40c5a0d5a092d8756875681d685e440522109480c92064942e2",
+    "9074480a2417da07da15da016d8376837642dba1dda15da07d213d905648035283b4419a903aa41dd202a943385e08478170",
+    "5c08c7817054084740380cc2d1201c09e1b8101c082a04018141d02048081c820e4181e0427820ac10068406618330217408",
+    "3b8405c20bcd03cd0acd80a641b34133a1e9d0ecd02cd0bc101d882a44019141d4204a881ca20e5181e8427c20ae1007c406",
+    "7183382176883bc405e20bad03ad0aad809641ab412ba1e5d0ead02ad0ba901c482a24018941d22049481c920e4981e442fb",
+    "40bb423ba06dd06ed04e683bb43bb40bb42fa407d20a69406a903648135287b4435a2075e81f",
+  );
+}
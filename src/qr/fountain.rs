@@ -0,0 +1,416 @@
+// Fountain codes for multi-part URs (BCR-2020-005).
+// Parts past the first seq_len are XORs of pseudo-randomly chosen fragments, so a scanner can
+// rebuild the message from any large enough set of parts. The choice of fragments must match
+// the reference implementation bit for bit, including Xoshiro256** and the alias sampler.
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use bitcoin::hashes::{sha256, Hash};
+
+use crate::e::{ErrorKind, S5Error};
+use crate::qr::bytewords::crc32;
+use crate::qr::cbor::Cbor;
+
+const MIN_FRAGMENT_LENGTH: usize = 10;
+/// Bounds the work a malformed part can cause when choosing fragments.
+const MAX_SEQ_LEN: u64 = 10_000;
+
+struct Xoshiro256 {
+  s: [u64; 4],
+}
+
+impl Xoshiro256 {
+  fn from_seed(seed: &[u8]) -> Self {
+    let digest = sha256::Hash::hash(seed).into_inner();
+    let mut s = [0u64; 4];
+    for (i, word) in s.iter_mut().enumerate() {
+      *word = digest[i * 8..i * 8 + 8]
+        .iter()
+        .fold(0, |acc, byte| (acc << 8) | *byte as u64);
+    }
+    Xoshiro256 { s }
+  }
+
+  fn next(&mut self) -> u64 {
+    let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+    let t = self.s[1] << 17;
+    self.s[2] ^= self.s[0];
+    self.s[3] ^= self.s[1];
+    self.s[1] ^= self.s[2];
+    self.s[0] ^= self.s[3];
+    self.s[2] ^= t;
+    self.s[3] = self.s[3].rotate_left(45);
+    result
+  }
+
+  fn next_double(&mut self) -> f64 {
+    self.next() as f64 / (u64::MAX as f64 + 1.0)
+  }
+
+  fn next_int(&mut self, low: u64, high: u64) -> u64 {
+    (self.next_double() * (high - low + 1) as f64) as u64 + low
+  }
+}
+
+/// Walker's alias method, as in the reference implementation.
+struct RandomSampler {
+  probs: Vec<f64>,
+  aliases: Vec<usize>,
+}
+
+impl RandomSampler {
+  fn new(weights: &[f64]) -> Self {
+    let n = weights.len();
+    let sum: f64 = weights.iter().sum();
+    let mut p: Vec<f64> = weights.iter().map(|weight| weight * n as f64 / sum).collect();
+    let mut small = vec![];
+    let mut large = vec![];
+    for i in (0..n).rev() {
+      if p[i] < 1.0 {
+        small.push(i);
+      } else {
+        large.push(i);
+      }
+    }
+    let mut probs = vec![0.0; n];
+    let mut aliases = vec![0; n];
+    while !small.is_empty() && !large.is_empty() {
+      let a = small.pop().unwrap();
+      let g = large.pop().unwrap();
+      probs[a] = p[a];
+      aliases[a] = g;
+      p[g] += p[a] - 1.0;
+      if p[g] < 1.0 {
+        small.push(g);
+      } else {
+        large.push(g);
+      }
+    }
+    for i in large.into_iter().chain(small) {
+      probs[i] = 1.0;
+    }
+    RandomSampler { probs, aliases }
+  }
+
+  fn next(&self, rng: &mut Xoshiro256) -> usize {
+    let r1 = rng.next_double();
+    let r2 = rng.next_double();
+    let i = (self.probs.len() as f64 * r1) as usize;
+    if r2 < self.probs[i] {
+      i
+    } else {
+      self.aliases[i]
+    }
+  }
+}
+
+/// Indexes of the fragments XORed into part seq_num.
+fn choose_fragments(seq_num: u32, seq_len: usize, checksum: u32) -> BTreeSet<usize> {
+  if seq_num as usize <= seq_len {
+    return [seq_num as usize - 1].iter().cloned().collect();
+  }
+  let mut seed = seq_num.to_be_bytes().to_vec();
+  seed.extend_from_slice(&checksum.to_be_bytes());
+  let mut rng = Xoshiro256::from_seed(&seed);
+
+  let weights: Vec<f64> = (1..=seq_len).map(|i| 1.0 / i as f64).collect();
+  let degree = RandomSampler::new(&weights).next(&mut rng) + 1;
+
+  let mut remaining: Vec<usize> = (0..seq_len).collect();
+  let mut shuffled = vec![];
+  while !remaining.is_empty() {
+    let index = rng.next_int(0, remaining.len() as u64 - 1) as usize;
+    shuffled.push(remaining.remove(index));
+  }
+  shuffled.into_iter().take(degree).collect()
+}
+
+/// Smallest fragment length, no longer than max_fragment_length, that splits the message evenly.
+fn fragment_length(message_len: usize, max_fragment_length: usize) -> usize {
+  let max_fragment_count = std::cmp::max(message_len / MIN_FRAGMENT_LENGTH, 1);
+  for fragment_count in 1..=max_fragment_count {
+    let length = message_len.div_ceil(fragment_count);
+    if length <= max_fragment_length {
+      return length;
+    }
+  }
+  max_fragment_length
+}
+
+/// Reads hex fragment data back from a decoder state, which the app passes in and may be corrupted.
+pub(crate) fn state_bytes(data: &str) -> Result<Vec<u8>, S5Error> {
+  match hex::decode(data) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "UR-State")),
+  }
+}
+
+fn xor_into(target: &mut [u8], other: &[u8]) {
+  for (a, b) in target.iter_mut().zip(other.iter()) {
+    *a ^= b;
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+  pub seq_num: u32,
+  pub seq_len: usize,
+  pub message_len: usize,
+  pub checksum: u32,
+  pub data: Vec<u8>,
+}
+
+impl Part {
+  pub fn to_cbor(&self) -> Vec<u8> {
+    Cbor::Array(vec![
+      Cbor::Unsigned(self.seq_num as u64),
+      Cbor::Unsigned(self.seq_len as u64),
+      Cbor::Unsigned(self.message_len as u64),
+      Cbor::Unsigned(self.checksum as u64),
+      Cbor::Bytes(self.data.clone()),
+    ])
+    .encode()
+  }
+
+  pub fn from_cbor(data: &[u8]) -> Result<Part, S5Error> {
+    let invalid = || S5Error::new(ErrorKind::Input, "UR-Part");
+    let items = match Cbor::decode(data)? {
+      Cbor::Array(items) => items,
+      _ => return Err(invalid()),
+    };
+    match items.as_slice() {
+      [Cbor::Unsigned(seq_num), Cbor::Unsigned(seq_len), Cbor::Unsigned(message_len), Cbor::Unsigned(checksum), Cbor::Bytes(data)]
+        if *seq_num > 0
+          && *seq_len > 0
+          && *seq_len <= MAX_SEQ_LEN
+          && *seq_num <= u32::MAX as u64
+          && *checksum <= u32::MAX as u64 =>
+      {
+        Ok(Part {
+          seq_num: *seq_num as u32,
+          seq_len: *seq_len as usize,
+          message_len: *message_len as usize,
+          checksum: *checksum as u32,
+          data: data.clone(),
+        })
+      }
+      _ => Err(invalid()),
+    }
+  }
+}
+
+pub struct FountainEncoder {
+  fragments: Vec<Vec<u8>>,
+  message_len: usize,
+  checksum: u32,
+  seq_num: u32,
+}
+
+impl FountainEncoder {
+  pub fn new(message: &[u8], max_fragment_length: usize) -> FountainEncoder {
+    let length = std::cmp::max(fragment_length(message.len(), max_fragment_length), 1);
+    let mut padded = message.to_vec();
+    let count = std::cmp::max(message.len().div_ceil(length), 1);
+    padded.resize(count * length, 0);
+    FountainEncoder {
+      fragments: padded.chunks(length).map(|chunk| chunk.to_vec()).collect(),
+      message_len: message.len(),
+      checksum: crc32(message),
+      seq_num: 0,
+    }
+  }
+
+  pub fn seq_len(&self) -> usize {
+    self.fragments.len()
+  }
+
+  pub fn next_part(&mut self) -> Part {
+    self.seq_num += 1;
+    let indexes = choose_fragments(self.seq_num, self.seq_len(), self.checksum);
+    let mut data = vec![0; self.fragments[0].len()];
+    for index in indexes {
+      xor_into(&mut data, &self.fragments[index]);
+    }
+    Part {
+      seq_num: self.seq_num,
+      seq_len: self.seq_len(),
+      message_len: self.message_len,
+      checksum: self.checksum,
+      data,
+    }
+  }
+}
+
+/// Decoder state. Serializable so an app can carry it between scans.
+/// Fragment data is hex encoded.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FountainDecoder {
+  seq_len: usize,
+  message_len: usize,
+  checksum: u32,
+  received: BTreeSet<u32>,
+  simple: BTreeMap<usize, String>,
+  mixed: Vec<(BTreeSet<usize>, String)>,
+}
+
+impl FountainDecoder {
+  pub fn is_complete(&self) -> bool {
+    self.seq_len > 0 && self.simple.len() == self.seq_len
+  }
+
+  /// Estimated fraction of the message received, between 0 and 1.
+  pub fn progress(&self) -> f64 {
+    if self.seq_len == 0 {
+      return 0.0;
+    }
+    self.simple.len() as f64 / self.seq_len as f64
+  }
+
+  pub fn received_count(&self) -> usize {
+    self.received.len()
+  }
+
+  pub fn seq_len(&self) -> usize {
+    self.seq_len
+  }
+
+  /// Adds a part. Returns false if it was a duplicate.
+  pub fn receive(&mut self, part: &Part) -> Result<bool, S5Error> {
+    if self.received.is_empty() {
+      self.seq_len = part.seq_len;
+      self.message_len = part.message_len;
+      self.checksum = part.checksum;
+    } else if part.seq_len != self.seq_len
+      || part.message_len != self.message_len
+      || part.checksum != self.checksum
+    {
+      return Err(S5Error::new(ErrorKind::Input, "UR part is from a different message."));
+    }
+    if self.is_complete() || !self.received.insert(part.seq_num) {
+      return Ok(false);
+    }
+
+    let indexes = choose_fragments(part.seq_num, part.seq_len, part.checksum);
+    let mut queue = vec![(indexes, part.data.clone())];
+    while let Some((mut indexes, mut data)) = queue.pop() {
+      // remove fragments we already know
+      for index in indexes.clone() {
+        if let Some(known) = self.simple.get(&index) {
+          xor_into(&mut data, &state_bytes(known)?);
+          indexes.remove(&index);
+        }
+      }
+      // remove mixed parts which are a subset of this one
+      for (mixed_indexes, mixed_data) in self.mixed.iter() {
+        if mixed_indexes.len() < indexes.len() && mixed_indexes.is_subset(&indexes) {
+          xor_into(&mut data, &state_bytes(mixed_data)?);
+          indexes = indexes.difference(mixed_indexes).cloned().collect();
+        }
+      }
+      match indexes.len() {
+        0 => (),
+        1 => {
+          let index = *indexes.iter().next().unwrap();
+          self.simple.insert(index, hex::encode(&data));
+          // every mixed part containing the new fragment can be reduced again
+          let (reducible, rest): (Vec<_>, Vec<_>) = self
+            .mixed
+            .drain(..)
+            .partition(|(mixed_indexes, _)| mixed_indexes.contains(&index));
+          self.mixed = rest;
+          for (mixed_indexes, mixed_data) in reducible {
+            queue.push((mixed_indexes, state_bytes(&mixed_data)?));
+          }
+        }
+        _ => {
+          if !self.mixed.iter().any(|(mixed_indexes, _)| *mixed_indexes == indexes) {
+            self.mixed.push((indexes, hex::encode(&data)));
+          }
+        }
+      }
+    }
+    Ok(true)
+  }
+
+  /// The reassembled message, once complete and matching its checksum.
+  pub fn message(&self) -> Result<Option<Vec<u8>>, S5Error> {
+    if !self.is_complete() {
+      return Ok(None);
+    }
+    let mut message = vec![];
+    for fragment in self.simple.values() {
+      message.extend(state_bytes(fragment)?);
+    }
+    message.truncate(self.message_len);
+    if crc32(&message) != self.checksum {
+      return Err(S5Error::new(ErrorKind::Input, "UR message checksum mismatch."));
+    }
+    Ok(Some(message))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn make_message(seed: &str, size: usize) -> Vec<u8> {
+    let mut rng = Xoshiro256::from_seed(seed.as_bytes());
+    (0..size).map(|_| rng.next_int(0, 255) as u8).collect()
+  }
+
+  #[test]
+  fn test_choose_fragments() {
+    // reference vectors from BCR-2020-005 implementations
+    let message = make_message("Wolf", 1024);
+    let checksum = crc32(&message);
+    let length = fragment_length(message.len(), 100);
+    let seq_len = message.len().div_ceil(length);
+    assert_eq!(seq_len, 11);
+    let chosen: Vec<Vec<usize>> = (1..=17)
+      .map(|seq_num| choose_fragments(seq_num, seq_len, checksum).into_iter().collect())
+      .collect();
+    let expected: Vec<Vec<usize>> = vec![
+      vec![0], vec![1], vec![2], vec![3], vec![4], vec![5], vec![6], vec![7], vec![8], vec![9], vec![10],
+      vec![9], vec![2, 5, 6, 8, 9, 10], vec![8], vec![1, 5], vec![1], vec![0, 2, 4, 5, 8, 10],
+    ];
+    assert_eq!(chosen, expected);
+  }
+
+  #[test]
+  fn test_fountain_round_trip() {
+    let message = make_message("Wolf", 1024);
+    let mut encoder = FountainEncoder::new(&message, 100);
+    let mut decoder = FountainDecoder::default();
+    // skip the simple parts, forcing recovery from mixed parts only
+    for _ in 0..encoder.seq_len() {
+      encoder.next_part();
+    }
+    while !decoder.is_complete() {
+      let part = encoder.next_part();
+      let part = Part::from_cbor(&part.to_cbor()).unwrap();
+      decoder.receive(&part).unwrap();
+      assert!(encoder.seq_num < 100);
+    }
+    assert_eq!(decoder.message().unwrap().unwrap(), message);
+
+    let other = FountainEncoder::new(&make_message("Other", 1024), 100).next_part();
+    let mut decoder = FountainDecoder::default();
+    decoder.receive(&FountainEncoder::new(&message, 100).next_part()).unwrap();
+    assert!(decoder.receive(&other).is_err());
+
+    // decoder state comes back from the app and may be corrupted
+    let mut corrupted = decoder.clone();
+    corrupted.simple.insert(0, "zz".to_string());
+    let mut encoder = FountainEncoder::new(&message, 100);
+    // part 17 mixes fragment 0 with others
+    let part = (0..17).map(|_| encoder.next_part()).last().unwrap();
+    assert_eq!(corrupted.receive(&part).err().unwrap().message, "UR-State");
+    let corrupted = FountainDecoder {
+      seq_len: 1,
+      simple: vec![(0, "zz".to_string())].into_iter().collect(),
+      ..Default::default()
+    };
+    assert_eq!(corrupted.message().err().unwrap().message, "UR-State");
+  }
+}
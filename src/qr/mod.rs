@@ -0,0 +1,6 @@
+pub mod bbqr;
+pub mod bytewords;
+pub mod cbor;
+pub mod deflate;
+pub mod fountain;
+pub mod ur;
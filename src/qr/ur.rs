@@ -0,0 +1,480 @@
+// Uniform Resources (BCR-2020-005) for QR based signers.
+// Encodes crypto-psbt as fountain coded frames and crypto-account / crypto-output from xpubs and descriptors.
+// The decoder is stateless from the FFI side: its state is returned as JSON and passed back with each scanned frame.
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use bdk::miniscript::descriptor::{DescriptorPublicKey, Wildcard};
+use bitcoin::base64;
+use bitcoin::network::constants::Network;
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint};
+
+use crate::e::{ErrorKind, S5Error};
+use crate::qr::bytewords;
+use crate::qr::cbor::Cbor;
+use crate::qr::fountain::{state_bytes, FountainDecoder, FountainEncoder, Part};
+use crate::wallet::descriptor;
+use crate::wallet::psbt;
+
+pub const DEFAULT_MAX_FRAGMENT_LENGTH: usize = 200;
+
+const CRYPTO_PSBT: &str = "crypto-psbt";
+const CRYPTO_ACCOUNT: &str = "crypto-account";
+const CRYPTO_OUTPUT: &str = "crypto-output";
+
+// Registry tags (BCR-2020-006, BCR-2020-007, BCR-2020-010, BCR-2020-015)
+const TAG_HDKEY: u64 = 303;
+const TAG_KEYPATH: u64 = 304;
+const TAG_COIN_INFO: u64 = 305;
+const TAG_ECKEY: u64 = 306;
+const TAG_OUTPUT: u64 = 308;
+const TAG_SH: u64 = 400;
+const TAG_WSH: u64 = 401;
+const TAG_PK: u64 = 402;
+const TAG_PKH: u64 = 403;
+const TAG_WPKH: u64 = 404;
+const TAG_COMBO: u64 = 405;
+const TAG_MULTI: u64 = 406;
+const TAG_SORTED_MULTI: u64 = 407;
+const TAG_TR: u64 = 409;
+const TAG_COSIGNER: u64 = 410;
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct URFrames {
+  pub ur_type: String,
+  pub seq_len: usize,
+  pub frames: Vec<String>,
+}
+
+impl URFrames {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Splits a CBOR message into UR frames.
+/// A message which fits in one fragment is a single part UR. Otherwise at least seq_len frames are returned,
+/// and any frames past seq_len are fountain coded so a scanner can recover from missed frames.
+pub fn encode(ur_type: &str, message: &[u8], max_fragment_length: usize, frame_count: usize) -> URFrames {
+  let mut encoder = FountainEncoder::new(message, std::cmp::max(max_fragment_length, 10));
+  let seq_len = encoder.seq_len();
+  let frames = if seq_len == 1 {
+    vec![format!("ur:{}/{}", ur_type, bytewords::encode_minimal(message))]
+  } else {
+    (0..std::cmp::max(frame_count, seq_len))
+      .map(|_| {
+        let part = encoder.next_part();
+        format!(
+          "ur:{}/{}-{}/{}",
+          ur_type,
+          part.seq_num,
+          part.seq_len,
+          bytewords::encode_minimal(&part.to_cbor())
+        )
+      })
+      .collect()
+  };
+  URFrames {
+    ur_type: ur_type.to_string(),
+    seq_len,
+    frames,
+  }
+}
+
+/// Encodes a PSBT (any encoding accepted by the psbt module) as ur:crypto-psbt frames.
+pub fn encode_psbt(psbt: &str, max_fragment_length: usize, frame_count: usize) -> Result<URFrames, S5Error> {
+  let psbt_struct = psbt::parse_psbt(psbt)?;
+  let message = Cbor::Bytes(bitcoin::consensus::encode::serialize(&psbt_struct)).encode();
+  Ok(encode(CRYPTO_PSBT, &message, max_fragment_length, frame_count))
+}
+
+fn keypath(path: &DerivationPath, wildcard: Wildcard, source: Option<Fingerprint>, depth: bool) -> Cbor {
+  let mut components = vec![];
+  for child in path.into_iter() {
+    match child {
+      ChildNumber::Normal { index } => {
+        components.push(Cbor::Unsigned(*index as u64));
+        components.push(Cbor::Bool(false));
+      }
+      ChildNumber::Hardened { index } => {
+        components.push(Cbor::Unsigned(*index as u64));
+        components.push(Cbor::Bool(true));
+      }
+    }
+  }
+  if wildcard != Wildcard::None {
+    components.push(Cbor::Array(vec![]));
+    components.push(Cbor::Bool(wildcard == Wildcard::Hardened));
+  }
+  let mut entries = vec![(1, Cbor::Array(components))];
+  if let Some(fingerprint) = source {
+    entries.push((2, Cbor::Unsigned(u32::from_be_bytes(*fingerprint.as_bytes()) as u64)));
+  }
+  if depth {
+    entries.push((3, Cbor::Unsigned(path.as_ref().len() as u64)));
+  }
+  Cbor::tagged(TAG_KEYPATH, Cbor::int_map(entries))
+}
+
+fn hdkey(
+  xpub: &ExtendedPubKey,
+  origin: Option<(Fingerprint, DerivationPath)>,
+  children: Option<(DerivationPath, Wildcard)>,
+) -> Cbor {
+  let mut entries = vec![
+    (3, Cbor::Bytes(xpub.public_key.to_bytes())),
+    (4, Cbor::Bytes(xpub.chain_code.as_bytes().to_vec())),
+  ];
+  if xpub.network != Network::Bitcoin {
+    entries.push((5, Cbor::tagged(TAG_COIN_INFO, Cbor::int_map(vec![(2, Cbor::Unsigned(1))]))));
+  }
+  if let Some((fingerprint, path)) = origin {
+    entries.push((6, keypath(&path, Wildcard::None, Some(fingerprint), true)));
+  }
+  if let Some((path, wildcard)) = children {
+    if !path.as_ref().is_empty() || wildcard != Wildcard::None {
+      entries.push((7, keypath(&path, wildcard, None, false)));
+    }
+  }
+  entries.push((8, Cbor::Unsigned(u32::from_be_bytes(*xpub.parent_fingerprint.as_bytes()) as u64)));
+  Cbor::tagged(TAG_HDKEY, Cbor::int_map(entries))
+}
+
+fn descriptor_key(key: &str) -> Result<Cbor, S5Error> {
+  match DescriptorPublicKey::from_str(key) {
+    Ok(DescriptorPublicKey::XPub(xkey)) => Ok(hdkey(
+      &xkey.xkey,
+      xkey.origin,
+      Some((xkey.derivation_path, xkey.wildcard)),
+    )),
+    Ok(DescriptorPublicKey::SinglePub(single)) => Ok(Cbor::tagged(
+      TAG_ECKEY,
+      Cbor::int_map(vec![(3, Cbor::Bytes(single.key.to_bytes()))]),
+    )),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "Invalid Descriptor.")),
+  }
+}
+
+/// Splits "name(a,b(c,d))" into name and its top level arguments.
+fn split_expression(expression: &str) -> Result<(&str, Vec<&str>), S5Error> {
+  let invalid = || S5Error::new(ErrorKind::Input, "Invalid Descriptor.");
+  let open = expression.find('(').ok_or_else(invalid)?;
+  if !expression.ends_with(')') {
+    return Err(invalid());
+  }
+  let inner = &expression[open + 1..expression.len() - 1];
+  let mut args = vec![];
+  let mut depth = 0;
+  let mut start = 0;
+  for (i, c) in inner.char_indices() {
+    match c {
+      '(' => depth += 1,
+      ')' => depth -= 1,
+      ',' if depth == 0 => {
+        args.push(&inner[start..i]);
+        start = i + 1;
+      }
+      _ => (),
+    }
+  }
+  args.push(&inner[start..]);
+  Ok((&expression[..open], args))
+}
+
+fn output_expression(expression: &str) -> Result<Cbor, S5Error> {
+  let (name, args) = split_expression(expression)?;
+  let single = |tag: u64, inner: Result<Cbor, S5Error>| -> Result<Cbor, S5Error> {
+    if args.len() != 1 {
+      return Err(S5Error::new(ErrorKind::Input, "Invalid Descriptor."));
+    }
+    Ok(Cbor::tagged(tag, inner?))
+  };
+  match name {
+    "sh" => single(TAG_SH, output_expression(args[0])),
+    "wsh" => single(TAG_WSH, output_expression(args[0])),
+    "pk" => single(TAG_PK, descriptor_key(args[0])),
+    "pkh" => single(TAG_PKH, descriptor_key(args[0])),
+    "wpkh" => single(TAG_WPKH, descriptor_key(args[0])),
+    "combo" => single(TAG_COMBO, descriptor_key(args[0])),
+    "tr" => single(TAG_TR, descriptor_key(args[0])),
+    "multi" | "sortedmulti" => {
+      let threshold = match args[0].parse::<u64>() {
+        Ok(result) if args.len() > 1 => result,
+        _ => return Err(S5Error::new(ErrorKind::Input, "Invalid Descriptor.")),
+      };
+      let keys = args[1..]
+        .iter()
+        .map(|key| descriptor_key(key))
+        .collect::<Result<Vec<Cbor>, S5Error>>()?;
+      let tag = if name == "multi" { TAG_MULTI } else { TAG_SORTED_MULTI };
+      Ok(Cbor::tagged(
+        tag,
+        Cbor::int_map(vec![(1, Cbor::Unsigned(threshold)), (2, Cbor::Array(keys))]),
+      ))
+    }
+    _ => Err(S5Error::new(
+      ErrorKind::Input,
+      "Descriptor cannot be encoded as crypto-output. Only sh, wsh, pk, pkh, wpkh, combo, tr, multi and sortedmulti are supported.",
+    )),
+  }
+}
+
+/// Encodes a public descriptor (eg. from policy::compile) as ur:crypto-output.
+pub fn encode_output(descriptor: &str, max_fragment_length: usize) -> Result<URFrames, S5Error> {
+  let descriptor = descriptor::strip_checksum(descriptor.trim())?;
+  let message = output_expression(descriptor)?.encode();
+  Ok(encode(CRYPTO_OUTPUT, &message, max_fragment_length, 0))
+}
+
+/// Encodes an account xpub (eg. from child::derive) as ur:crypto-account.
+/// The script type is taken from the purpose in hardened_path: 44, 49, 84, 86 or 48 (with script type 1' or 2').
+pub fn encode_account(
+  fingerprint: &str,
+  hardened_path: &str,
+  xpub: &str,
+  max_fragment_length: usize,
+) -> Result<URFrames, S5Error> {
+  let fingerprint = match Fingerprint::from_str(fingerprint) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Input, "Fingerprint")),
+  };
+  let path = match DerivationPath::from_str(hardened_path) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Input, "Derivation-Path")),
+  };
+  let xpub = match ExtendedPubKey::from_str(xpub) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Key, "Invalid Xpub.")),
+  };
+
+  let key = hdkey(&xpub, Some((fingerprint, path.clone())), None);
+  let components: Vec<ChildNumber> = path.into_iter().cloned().collect();
+  let hardened = |index: u32| ChildNumber::Hardened { index };
+  let output = match components.as_slice() {
+    [purpose, ..] if *purpose == hardened(44) => Cbor::tagged(TAG_PKH, key),
+    [purpose, ..] if *purpose == hardened(49) => Cbor::tagged(TAG_SH, Cbor::tagged(TAG_WPKH, key)),
+    [purpose, ..] if *purpose == hardened(84) => Cbor::tagged(TAG_WPKH, key),
+    [purpose, ..] if *purpose == hardened(86) => Cbor::tagged(TAG_TR, key),
+    [purpose, _, _, script] if *purpose == hardened(48) && *script == hardened(1) => Cbor::tagged(
+      TAG_SH,
+      Cbor::tagged(TAG_WSH, Cbor::tagged(TAG_COSIGNER, key)),
+    ),
+    [purpose, _, _, script] if *purpose == hardened(48) && *script == hardened(2) => {
+      Cbor::tagged(TAG_WSH, Cbor::tagged(TAG_COSIGNER, key))
+    }
+    _ => return Err(S5Error::new(ErrorKind::Input, "Unsupported account purpose.")),
+  };
+
+  let message = Cbor::int_map(vec![
+    (1, Cbor::Unsigned(u32::from_be_bytes(*fingerprint.as_bytes()) as u64)),
+    (2, Cbor::Array(vec![Cbor::tagged(TAG_OUTPUT, output)])),
+  ])
+  .encode();
+  Ok(encode(CRYPTO_ACCOUNT, &message, max_fragment_length, 0))
+}
+
+/// Decoder state, carried by the app between scans as JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct URDecoder {
+  ur_type: Option<String>,
+  single: Option<String>,
+  fountain: FountainDecoder,
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct URDecodeProgress {
+  /// Pass back with the next frame
+  pub state: String,
+  pub ur_type: Option<String>,
+  pub complete: bool,
+  pub progress: f64,
+  pub received: usize,
+  pub expected: usize,
+  /// base64 PSBT, for crypto-psbt
+  pub psbt: Option<String>,
+  /// hex CBOR message, for other types
+  pub cbor: Option<String>,
+}
+
+impl URDecodeProgress {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+impl URDecoder {
+  /// Restores a decoder from its JSON state. An empty state starts a new decoder.
+  pub fn from_state(state: &str) -> Result<URDecoder, S5Error> {
+    if state.is_empty() {
+      return Ok(URDecoder::default());
+    }
+    match serde_json::from_str(state) {
+      Ok(result) => Ok(result),
+      Err(_) => Err(S5Error::new(ErrorKind::Input, "UR-State")),
+    }
+  }
+
+  /// Adds one scanned frame. Frames may arrive in any order and repeat.
+  pub fn receive(&mut self, frame: &str) -> Result<(), S5Error> {
+    let invalid = || S5Error::new(ErrorKind::Input, "Invalid UR.");
+    let frame = frame.trim().to_lowercase();
+    if !frame.starts_with("ur:") {
+      return Err(invalid());
+    }
+    let components: Vec<&str> = frame[3..].split('/').collect();
+    let ur_type = components[0];
+    if ur_type.is_empty() || !ur_type.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+      return Err(invalid());
+    }
+    match &self.ur_type {
+      Some(current) if current != ur_type => {
+        return Err(S5Error::new(ErrorKind::Input, "UR frame is from a different type."))
+      }
+      _ => self.ur_type = Some(ur_type.to_string()),
+    }
+
+    match components.as_slice() {
+      [_, body] => {
+        self.single = Some(hex::encode(bytewords::decode_minimal(body)?));
+        Ok(())
+      }
+      [_, sequence, body] => {
+        let part = Part::from_cbor(&bytewords::decode_minimal(body)?)?;
+        if *sequence != format!("{}-{}", part.seq_num, part.seq_len) {
+          return Err(invalid());
+        }
+        self.fountain.receive(&part)?;
+        Ok(())
+      }
+      _ => Err(invalid()),
+    }
+  }
+
+  pub fn message(&self) -> Result<Option<Vec<u8>>, S5Error> {
+    match &self.single {
+      Some(single) => Ok(Some(state_bytes(single)?)),
+      None => self.fountain.message(),
+    }
+  }
+
+  pub fn progress(&self) -> Result<URDecodeProgress, S5Error> {
+    let message = self.message()?;
+    let (psbt, cbor) = match (&message, self.ur_type.as_deref()) {
+      (Some(message), Some(CRYPTO_PSBT)) | (Some(message), Some("psbt")) => match Cbor::decode(message)? {
+        Cbor::Bytes(psbt) => (Some(base64::encode(&psbt)), None),
+        _ => return Err(S5Error::new(ErrorKind::Input, "Invalid UR.")),
+      },
+      (Some(message), _) => (None, Some(hex::encode(message))),
+      (None, _) => (None, None),
+    };
+    let state = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => return Err(S5Error::new(ErrorKind::Internal, "UR-State")),
+    };
+    let complete = message.is_some();
+    Ok(URDecodeProgress {
+      state,
+      ur_type: self.ur_type.clone(),
+      complete,
+      progress: if complete { 1.0 } else { self.fountain.progress() },
+      received: if self.single.is_some() { 1 } else { self.fountain.received_count() },
+      expected: if self.single.is_some() { 1 } else { self.fountain.seq_len() },
+      psbt,
+      cbor,
+    })
+  }
+}
+
+/// Adds a frame to a decoder state ("" for a new scan) and reports progress.
+pub fn decode(state: &str, frame: &str) -> Result<URDecodeProgress, S5Error> {
+  let mut decoder = URDecoder::from_state(state)?;
+  decoder.receive(frame)?;
+  decoder.progress()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PSBT: &str = "cHNidP8BAHQBAAAAAf3cLERUN9+6X5+1yk3x9XzSCq1417WtB+gB5qNyj+xpAAAAAAD9////AnRxAQAAAAAAFgAUVyorkNVSCsiE4/7OspP52IwquzqIEwAAAAAAABl2qRQ0Sg9IyhUOwrkDgXZgubaLE6ZwJoisAAAAAAABAN4CAAAAAAEByvn9X3PvFqemGsrTv8ivAO07IOeRhBz7J0huqXJLfVgBAAAAAP7///8CoIYBAAAAAAAWABQTXAMs/1Qr5n6pDVK9O15ODZ/UCVZWjQAAAAAAFgAUIixaISTPlO8fwyT3hCL+An5+Km4CRzBEAiBFsQJfBur3eQgO5Vw+EvEgr2CagcVGXw9oYw3FOaMSSgIgch0CV+W3oRCKNBwxqiqIK0C5b1TsGk32HvNM+4Z7IksBIQNP/rsBHKbA98977TzmriFrOuO8hQjNg4ON3goI9/Uwjp0BIAABAR+ghgEAAAAAABYAFBNcAyz/VCvmfqkNUr07Xk4Nn9QJIgYD9WhlKKSeNh6567KTmyKrlitDWZOz/+mms7emVsWjGTsY230ltVQAAIABAACABgAAgAAAAAABAAAAACICAgHPrE7CShQkK90ApPF8xdr+8o7T/sHggOlZNOHIUft/GNt9JbVUAACAAQAAgAYAAIABAAAAAQAAAAAA";
+
+  #[test]
+  fn test_psbt_frames() {
+    let frames = encode_psbt(PSBT, 100, 10).unwrap();
+    assert_eq!(frames.ur_type, "crypto-psbt");
+    assert!(frames.seq_len > 1);
+    assert_eq!(frames.frames.len(), 10);
+    assert!(frames.frames[0].starts_with(&format!("ur:crypto-psbt/1-{}/", frames.seq_len)));
+
+    // decode in reverse, from uppercase QR frames, skipping the first simple part
+    let mut state = String::new();
+    let mut result = None;
+    for frame in frames.frames.iter().skip(1).rev() {
+      let progress = decode(&state, &frame.to_uppercase()).unwrap();
+      state = progress.state.clone();
+      if progress.complete {
+        result = progress.psbt;
+        break;
+      }
+    }
+    assert_eq!(result.unwrap(), PSBT);
+
+    let single = encode_psbt(PSBT, 1000, 0).unwrap();
+    assert_eq!(single.frames.len(), 1);
+    assert!(!single.frames[0].contains("1-1"));
+    assert_eq!(decode("", &single.frames[0]).unwrap().psbt.unwrap(), PSBT);
+
+    assert!(decode("", "ur:crypto-psbt/1-3/lpadaxcsencylobemohsgmoyadtaaeehtl").is_err());
+    let other = encode("bytes", &Cbor::Bytes(vec![1; 300]).encode(), 100, 0);
+    let progress = decode("", &frames.frames[0]).unwrap();
+    assert_eq!(decode(&progress.state, &other.frames[0]).err().unwrap().message, "UR frame is from a different type.");
+
+    let mut corrupted = URDecoder::from_state("").unwrap();
+    corrupted.single = Some("not hex".to_string());
+    assert_eq!(corrupted.progress().err().unwrap().message, "UR-State");
+  }
+
+  #[test]
+  fn test_account_and_output() {
+    let xpub = "tpubDDXskyWJLq5pUioZn8sGQ46aieCybzsjLb5BGmRPBAdwfGyvwiyXaoho8EYJcgJa5QGHGYpDjLQ8gWzczWbxadeRkCuExW32Boh696yuQ9m";
+    let account = encode_account("eb79e0ff", "m/84h/1h/0h", xpub, 1000).unwrap();
+    assert_eq!(account.frames.len(), 1);
+    assert!(account.frames[0].starts_with("ur:crypto-account/"));
+    let cbor = decode("", &account.frames[0]).unwrap().cbor.unwrap();
+    // {1: 0xeb79e0ff, 2: [308(404(303({...
+    assert!(cbor.starts_with("a2011aeb79e0ff0281d90134d90194d9012fa5035821"));
+    assert!(encode_account("eb79e0ff", "m/0h/1h/0h", xpub, 1000).is_err());
+
+    let descriptor = format!("wsh(multi(2,[eb79e0ff/48h/1h/0h/2h]{}/0/*,[db7d25b5/48h/1h/0h/2h]{}/0/*))", xpub, xpub);
+    let output = encode_output(&descriptor, 1000).unwrap();
+    assert!(encode_output(&format!("{}#qqqqqqqq", descriptor), 1000).is_err());
+    let cbor = decode("", &output.frames[0]).unwrap().cbor.unwrap();
+    // 401(406({1: 2, 2: [303(...
+    assert!(cbor.starts_with("d90191d90196a201020282d9012f"));
+    assert!(encode_output("wsh(or_d(pk(A),pk(B)))", 1000).is_err());
+    assert!(encode_output("pk(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)", 1000).is_ok());
+  }
+}
@@ -3,17 +3,44 @@ use std::os::raw::c_char;
 
 use serde::{Serialize,Deserialize};
 
+#[cfg(feature = "online")]
+use std::collections::BTreeSet;
+
+#[cfg(feature = "online")]
 use bdk::Wallet;
-use bdk::database::MemoryDatabase;
+#[cfg(feature = "online")]
+use bdk::blockchain::{noop_progress, Blockchain};
+#[cfg(feature = "online")]
+use bdk::database::{BatchDatabase, MemoryDatabase};
+#[cfg(feature = "online")]
+use bdk::KeychainKind;
+#[cfg(feature = "online")]
 use bdk::wallet::AddressIndex::Peek;
+use bdk::descriptor::Descriptor;
+use bdk::miniscript::descriptor::DescriptorPublicKey;
+use bdk::miniscript::{DescriptorTrait, ForEachKey, TranslatePk2};
+
+use bitcoin::blockdata::script::Script;
+use bitcoin::network::constants::Network;
+use bitcoin::secp256k1::{Secp256k1, Verification};
+use bitcoin::util::address::{Address, AddressType, Payload};
+use std::str::FromStr;
 
+use crate::config;
 use crate::e::{S5Error,ErrorKind};
+use crate::wallet::descriptor;
+#[cfg(feature = "online")]
 use crate::config::{WalletConfig};
+#[cfg(feature = "online")]
+use crate::wallet::labels::LabelType;
+#[cfg(feature = "online")]
+use crate::wallet::store::WalletStore;
 
 /// FFI Output
 #[derive(Serialize,Deserialize,Debug)]
 pub struct WalletAddress {
     pub address: String,
+    pub label: Option<String>,
 }
 impl WalletAddress{
   pub fn c_stringify(&self)->*mut c_char{
@@ -26,9 +53,11 @@ impl WalletAddress{
   }
 }
 
+#[cfg(feature = "online")]
 pub async fn generate(
   config: WalletConfig,
   index: u32,
+  store: &WalletStore,
 ) -> Result<WalletAddress, S5Error> {
    
   let wallet = match Wallet::new(
@@ -44,13 +73,586 @@ pub async fn generate(
 
   match wallet.get_address(Peek(index)){
     Ok(address) => Ok(WalletAddress{
-      address:address.to_string()
+      address:address.to_string(),
+      label: store.get_label(LabelType::Addr, &address.to_string()),
     }),
     Err(e) => Err(S5Error::new(ErrorKind::Internal,&e.to_string()))
   }
 
 }
 
+/// Restoring wallets stop looking for addresses after this many unused ones in a row.
+pub const GAP_LIMIT: u32 = 20;
+
+/// FFI Output
+/// gap is the number of unused addresses from the last used one up to and including index.
+#[derive(Serialize,Deserialize,Debug)]
+pub struct NewAddress {
+    pub index: u32,
+    pub address: String,
+    pub label: Option<String>,
+    pub last_used: Option<u32>,
+    pub gap: u32,
+    pub warning: Option<String>,
+}
+impl NewAddress{
+  pub fn c_stringify(&self)->*mut c_char{
+    let stringified = match serde_json::to_string(self){
+        Ok(result)=>result,
+        Err(_)=>return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.").unwrap().into_raw()
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+fn index_after(index: Option<u32>) -> u32 {
+  index.map_or(0, |index| index + 1)
+}
+
+/// Picks the next receive index: after both the last revealed and last used indexes.
+/// With reuse_unused, the last revealed index is returned again if nothing has been received on it.
+pub fn next_index(last_revealed: Option<u32>, last_used: Option<u32>, reuse_unused: bool) -> u32 {
+  match last_revealed {
+    Some(revealed) if reuse_unused && last_used.is_none_or(|used| revealed > used) => revealed,
+    _ => std::cmp::max(index_after(last_revealed), index_after(last_used)),
+  }
+}
+
+/// Unused addresses from the last used one up to index, with a warning once a restore would not find index.
+pub fn gap_warning(index: u32, last_used: Option<u32>) -> (u32, Option<String>) {
+  let gap = (index + 1).saturating_sub(index_after(last_used));
+  let warning = if gap > GAP_LIMIT {
+    Some(format!(
+      "{} unused addresses in a row. Wallets restoring with a gap limit of {} will not find funds sent to this address.",
+      gap, GAP_LIMIT
+    ))
+  } else {
+    None
+  };
+  (gap, warning)
+}
+
+/// Receive indexes of every wallet output seen during sync.
+#[cfg(feature = "online")]
+fn used_indexes<B: Blockchain, D: BatchDatabase>(wallet: &Wallet<B, D>) -> Result<BTreeSet<u32>, S5Error> {
+  let transactions = match wallet.list_transactions(true) {
+    Ok(result) => result,
+    Err(e) => return Err(S5Error::new(ErrorKind::Internal, &e.to_string())),
+  };
+  let database = wallet.database();
+  let mut used = BTreeSet::new();
+  for tx in transactions.iter().filter_map(|detail| detail.transaction.as_ref()) {
+    for txout in tx.output.iter() {
+      if let Ok(Some((KeychainKind::External, index))) = database.get_path_from_script_pubkey(&txout.script_pubkey) {
+        used.insert(index);
+      }
+    }
+  }
+  Ok(used)
+}
+
+/// Syncs the wallet and returns the next receive address, tracking revealed indexes in the wallet store at db_path.
+/// reuse_unused returns the last revealed address again while nothing has been received on it.
+#[cfg(feature = "online")]
+pub async fn next_address(
+  config: WalletConfig,
+  db_path: &str,
+  reuse_unused: bool,
+) -> Result<NewAddress, S5Error> {
+  if db_path.is_empty() {
+    return Err(S5Error::new(ErrorKind::Input, "Storage-Path"));
+  }
+  let deposit_desc = config.deposit_desc.clone();
+
+  let wallet = match Wallet::new(
+    &config.deposit_desc,
+    Some(&config.change_desc),
+    config.network,
+    MemoryDatabase::default(),
+    config.client,
+  ).await{
+    Ok(result) => result,
+    Err(e) => return Err(S5Error::new(ErrorKind::Internal,&e.to_string()))
+  };
+
+  match wallet.sync(noop_progress(), None).await {
+    Ok(_) => (),
+    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Sync")),
+  };
+
+  let used = used_indexes(&wallet)?;
+  let last_used = used.iter().next_back().copied();
+  // loaded after the sync, so labels or frozen utxos saved while it ran are kept
+  let mut store = WalletStore::load(db_path, &deposit_desc)?;
+  let index = next_index(store.last_revealed, last_used, reuse_unused);
+  let address = match wallet.get_address(Peek(index)) {
+    Ok(address) => address.to_string(),
+    Err(e) => return Err(S5Error::new(ErrorKind::Internal,&e.to_string()))
+  };
+
+  store.last_used = last_used;
+  store.last_revealed = Some(std::cmp::max(index, store.last_revealed.unwrap_or(0)));
+  store.save(db_path, &deposit_desc)?;
+
+  let (gap, warning) = gap_warning(index, last_used);
+  Ok(NewAddress {
+    index,
+    label: store.get_label(LabelType::Addr, &address),
+    address,
+    last_used,
+    gap,
+    warning,
+  })
+}
+
+/// Parses a public or private descriptor into its public form, checking its checksum if it has one.
+pub fn public_descriptor(descriptor: &str) -> Result<Descriptor<DescriptorPublicKey>, S5Error> {
+  let secp = Secp256k1::new();
+  let descriptor = descriptor::strip_checksum(descriptor)?;
+  match Descriptor::<DescriptorPublicKey>::parse_descriptor(&secp, descriptor) {
+    Ok((public_desc, _)) => Ok(public_desc),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "Invalid Descriptor.")),
+  }
+}
+
+/// Script pubkey of a descriptor at a child index. Index is ignored for descriptors without a wildcard.
+pub fn derive_script<C: Verification>(
+  descriptor: &Descriptor<DescriptorPublicKey>,
+  index: u32,
+  secp: &Secp256k1<C>,
+) -> Result<Script, S5Error> {
+  match descriptor
+    .derive(index)
+    .translate_pk2(|key| key.derive_public_key(secp))
+  {
+    Ok(derived) => Ok(derived.script_pubkey()),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "Descriptor-Derivation")),
+  }
+}
+
+/// Number of deposit and change addresses searched by validate to find whether an address is in the wallet.
+pub const ADDRESS_SCAN_DEPTH: u32 = 1000;
+
+/// FFI Output
+#[derive(Serialize,Deserialize,Debug)]
+pub struct AddressInfo {
+    pub address: String,
+    pub network: String,
+    pub script_type: String,
+    pub witness_version: Option<u8>,
+    pub script_pubkey: String,
+    pub is_mine: bool,
+    pub is_change: bool,
+    pub index: Option<u32>,
+}
+impl AddressInfo{
+  pub fn c_stringify(&self)->*mut c_char{
+    let stringified = match serde_json::to_string(self){
+        Ok(result)=>result,
+        Err(_)=>return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.").unwrap().into_raw()
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+fn network_name(network: Network) -> &'static str {
+  match network {
+    Network::Bitcoin => "main",
+    Network::Regtest => "regtest",
+    _ => "test",
+  }
+}
+
+/// Base58 addresses share a prefix on testnet, signet and regtest, and bech32 addresses share one on testnet and signet.
+/// These parse as Testnet, so they are accepted for any of the test networks they could belong to.
+fn is_valid_for_network(address: &Address, network: Network) -> bool {
+  match (address.network, network) {
+    (Network::Bitcoin, Network::Bitcoin) => true,
+    (Network::Bitcoin, _) | (_, Network::Bitcoin) => false,
+    (Network::Regtest, Network::Regtest) => true,
+    (Network::Regtest, _) => false,
+    (_, Network::Regtest) => !matches!(address.payload, Payload::WitnessProgram { .. }),
+    _ => true,
+  }
+}
+
+fn script_type(address: &Address) -> String {
+  match (address.address_type(), &address.payload) {
+    (Some(AddressType::P2pkh), _) => "p2pkh".to_string(),
+    (Some(AddressType::P2sh), _) => "p2sh".to_string(),
+    (Some(AddressType::P2wpkh), _) => "p2wpkh".to_string(),
+    (Some(AddressType::P2wsh), _) => "p2wsh".to_string(),
+    (_, Payload::WitnessProgram { version, program }) if version.to_u8() == 1 && program.len() == 32 => {
+      "p2tr".to_string()
+    }
+    _ => "unknown".to_string(),
+  }
+}
+
+/// Parses an address and checks that it is for the wallet's network.
+pub fn parse(address: &str, network: Network) -> Result<Address, S5Error> {
+  let parsed = match Address::from_str(address.trim()) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Input, "Invalid Address.")),
+  };
+  if !is_valid_for_network(&parsed, network) {
+    return Err(S5Error::new(
+      ErrorKind::Input,
+      &format!(
+        "Address is for the {} network but the wallet is on the {} network.",
+        network_name(parsed.network),
+        network_name(network)
+      ),
+    ));
+  }
+  Ok(parsed)
+}
+
+/// Deposit or change index of a script within the first depth addresses of a descriptor.
+fn wallet_index(deposit_desc: &str, script: &Script, depth: u32) -> Result<Option<(bool, u32)>, S5Error> {
+  let secp = Secp256k1::verification_only();
+  let deposit = public_descriptor(deposit_desc)?;
+  let change_desc = config::change_descriptor(deposit_desc);
+  let change = if change_desc == deposit_desc {
+    None
+  } else {
+    Some(public_descriptor(&change_desc)?)
+  };
+  for index in 0..depth {
+    if &derive_script(&deposit, index, &secp)? == script {
+      return Ok(Some((false, index)));
+    }
+    if let Some(change) = &change {
+      if &derive_script(change, index, &secp)? == script {
+        return Ok(Some((true, index)));
+      }
+    }
+  }
+  Ok(None)
+}
+
+/// Describes an address. With a deposit_desc, also finds whether it belongs to the wallet, searching the first
+/// ADDRESS_SCAN_DEPTH deposit and change addresses. Addresses for another network are never the wallet's.
+pub fn validate(address: &str, deposit_desc: Option<&str>) -> Result<AddressInfo, S5Error> {
+  let parsed = match Address::from_str(address.trim()) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Input, "Invalid Address.")),
+  };
+  let script_pubkey = parsed.script_pubkey();
+  let wallet_index = match deposit_desc {
+    Some(desc) if is_valid_for_network(&parsed, config::descriptor_network(desc)) => {
+      wallet_index(desc, &script_pubkey, ADDRESS_SCAN_DEPTH)?
+    }
+    _ => None,
+  };
+  Ok(AddressInfo {
+    address: parsed.to_string(),
+    network: network_name(parsed.network).to_string(),
+    script_type: script_type(&parsed),
+    witness_version: match &parsed.payload {
+      Payload::WitnessProgram { version, .. } => Some(version.to_u8()),
+      _ => None,
+    },
+    script_pubkey: hex::encode(script_pubkey.as_bytes()),
+    is_mine: wallet_index.is_some(),
+    is_change: matches!(wallet_index, Some((true, _))),
+    index: wallet_index.map(|(_, index)| index),
+  })
+}
+
+/// Most addresses derive_range returns in one call.
+pub const MAX_DERIVE_COUNT: u32 = 1000;
+
+/// FFI Output
+/// fingerprint is the master key fingerprint (or the key itself without an origin) and path its full derivation path.
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct KeyPath {
+    pub fingerprint: String,
+    pub path: String,
+    pub public_key: String,
+}
+
+/// FFI Output
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct DerivedAddress {
+    pub index: u32,
+    pub is_change: bool,
+    pub address: String,
+    pub script_pubkey: String,
+    pub key_paths: Vec<KeyPath>,
+}
+impl DerivedAddress{
+  pub fn c_stringify(&self)->*mut c_char{
+    let stringified = match serde_json::to_string(self){
+        Ok(result)=>result,
+        Err(_)=>return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.").unwrap().into_raw()
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// FFI Output
+#[derive(Serialize,Deserialize,Debug)]
+pub struct DerivedAddresses {
+    pub addresses: Vec<DerivedAddress>,
+}
+impl DerivedAddresses{
+  pub fn c_stringify(&self)->*mut c_char{
+    let stringified = match serde_json::to_string(self){
+        Ok(result)=>result,
+        Err(_)=>return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.").unwrap().into_raw()
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Parses "receive" or "change" (also "external" or "internal") into whether the keychain is change.
+pub fn parse_keychain(keychain: &str) -> Result<bool, S5Error> {
+  match keychain {
+    "receive" | "external" => Ok(false),
+    "change" | "internal" => Ok(true),
+    _ => Err(S5Error::new(ErrorKind::Input, "Invalid Keychain. Use receive or change.")),
+  }
+}
+
+/// Origin and public key of every key in a descriptor derived at an index, in descriptor order.
+fn key_paths<C: Verification>(
+  descriptor: &Descriptor<DescriptorPublicKey>,
+  secp: &Secp256k1<C>,
+) -> Result<Vec<KeyPath>, S5Error> {
+  let mut paths = vec![];
+  let mut derived = true;
+  descriptor.for_each_key(|key| {
+    let key = key.as_key();
+    match key.derive_public_key(secp) {
+      Ok(public_key) => paths.push(KeyPath {
+        fingerprint: key.master_fingerprint().to_string(),
+        path: key.full_derivation_path().to_string(),
+        public_key: public_key.to_string(),
+      }),
+      Err(_) => derived = false,
+    }
+    derived
+  });
+  if derived {
+    Ok(paths)
+  } else {
+    Err(S5Error::new(ErrorKind::Input, "Descriptor-Derivation"))
+  }
+}
+
+/// Derives addresses from index start to end (exclusive) without a blockchain client.
+pub fn derive_range(deposit_desc: &str, is_change: bool, start: u32, end: u32) -> Result<DerivedAddresses, S5Error> {
+  if end <= start || end - start > MAX_DERIVE_COUNT {
+    return Err(S5Error::new(
+      ErrorKind::Input,
+      &format!("Address range must contain 1 to {} addresses.", MAX_DERIVE_COUNT),
+    ));
+  }
+  let network = config::descriptor_network(deposit_desc);
+  let descriptor = if is_change {
+    public_descriptor(&config::change_descriptor(deposit_desc))?
+  } else {
+    public_descriptor(deposit_desc)?
+  };
+  let secp = Secp256k1::verification_only();
+  let mut addresses = vec![];
+  for index in start..end {
+    let script = derive_script(&descriptor, index, &secp)?;
+    let address = match Address::from_script(&script, network) {
+      Some(address) => address,
+      None => return Err(S5Error::new(ErrorKind::Input, "Descriptor has no address form.")),
+    };
+    addresses.push(DerivedAddress {
+      index,
+      is_change,
+      address: address.to_string(),
+      script_pubkey: hex::encode(script.as_bytes()),
+      key_paths: key_paths(&descriptor.derive(index), &secp)?,
+    });
+  }
+  Ok(DerivedAddresses { addresses })
+}
+
+/// Derives a single address at index without a blockchain client.
+pub fn derive(deposit_desc: &str, is_change: bool, index: u32) -> Result<DerivedAddress, S5Error> {
+  let end = match index.checked_add(1) {
+    Some(end) => end,
+    None => return Err(S5Error::new(ErrorKind::Input, "Address-Index")),
+  };
+  Ok(derive_range(deposit_desc, is_change, index, end)?.addresses.remove(0))
+}
+
+/// Highest max_index find accepts.
+pub const MAX_FIND_INDEX: u32 = 100_000;
+
+/// FFI Output
+/// key_paths has the derived public key of every cosigner, to compare against a hardware wallet display.
+#[derive(Serialize,Deserialize,Debug)]
+pub struct FoundAddress {
+    pub address: String,
+    pub found: bool,
+    pub keychain: Option<String>,
+    pub index: Option<u32>,
+    pub key_paths: Vec<KeyPath>,
+}
+impl FoundAddress{
+  pub fn c_stringify(&self)->*mut c_char{
+    let stringified = match serde_json::to_string(self){
+        Ok(result)=>result,
+        Err(_)=>return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.").unwrap().into_raw()
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Searches indexes 0 to max_index of both keychains of a descriptor for an address, without a blockchain client.
+/// An address that is not found is not an error; an address for another network is.
+pub fn find(descriptor: &str, address: &str, max_index: u32) -> Result<FoundAddress, S5Error> {
+  if max_index > MAX_FIND_INDEX {
+    return Err(S5Error::new(
+      ErrorKind::Input,
+      &format!("Max index must be at most {}.", MAX_FIND_INDEX),
+    ));
+  }
+  let parsed = parse(address, config::descriptor_network(descriptor))?;
+  match wallet_index(descriptor, &parsed.script_pubkey(), max_index + 1)? {
+    Some((is_change, index)) => {
+      let derived = derive(descriptor, is_change, index)?;
+      Ok(FoundAddress {
+        address: derived.address,
+        found: true,
+        keychain: Some(if is_change { "change" } else { "receive" }.to_string()),
+        index: Some(index),
+        key_paths: derived.key_paths,
+      })
+    }
+    None => Ok(FoundAddress {
+      address: parsed.to_string(),
+      found: false,
+      keychain: None,
+      index: None,
+      key_paths: vec![],
+    }),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DEPOSIT_DESC: &str = "wpkh([db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)";
+
+  #[test]
+  fn test_validate_address() {
+    let info = validate("tb1qzdwqxt8l2s47vl4fp4ft6w67fcxel4qf5j96ld", Some(DEPOSIT_DESC)).unwrap();
+    assert_eq!(info.network, "test");
+    assert_eq!(info.script_type, "p2wpkh");
+    assert_eq!(info.witness_version, Some(0));
+    assert!(info.is_mine);
+    assert!(!info.is_change);
+    assert_eq!(info.index, Some(1));
+
+    let info = validate("tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c", Some(DEPOSIT_DESC)).unwrap();
+    assert_eq!(info.script_type, "p2tr");
+    assert_eq!(info.witness_version, Some(1));
+    assert!(!info.is_mine);
+
+    let info = validate("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", None).unwrap();
+    assert_eq!(info.network, "main");
+    assert_eq!(info.script_type, "p2pkh");
+    assert_eq!(info.witness_version, None);
+    assert_eq!(validate("bc1qnotanaddress", None).err().unwrap().message, "Invalid Address.");
+
+    assert!(parse("tb1qzdwqxt8l2s47vl4fp4ft6w67fcxel4qf5j96ld", Network::Testnet).is_ok());
+    assert!(parse("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", Network::Regtest).is_ok());
+    let error = parse("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", Network::Testnet).err().unwrap();
+    assert_eq!(error.kind, ErrorKind::Input.to_string());
+    assert_eq!(error.message, "Address is for the main network but the wallet is on the test network.");
+    assert!(parse("tb1qzdwqxt8l2s47vl4fp4ft6w67fcxel4qf5j96ld", Network::Regtest).is_err());
+  }
+
+  #[test]
+  fn test_address_index() {
+    assert_eq!(next_index(None, None, false), 0);
+    assert_eq!(next_index(None, None, true), 0);
+    assert_eq!(next_index(Some(0), None, false), 1);
+    assert_eq!(next_index(Some(0), None, true), 0);
+    // funds received on the last revealed address
+    assert_eq!(next_index(Some(3), Some(3), true), 4);
+    // funds received beyond what this app revealed, eg. from another device
+    assert_eq!(next_index(Some(3), Some(7), false), 8);
+    assert_eq!(next_index(Some(3), Some(7), true), 8);
+    assert_eq!(next_index(Some(9), Some(7), true), 9);
+    assert_eq!(next_index(Some(9), Some(7), false), 10);
+
+    assert_eq!(gap_warning(0, None), (1, None));
+    assert_eq!(gap_warning(19, None), (20, None));
+    assert!(gap_warning(20, None).1.is_some());
+    assert_eq!(gap_warning(8, Some(7)), (1, None));
+    assert_eq!(gap_warning(27, Some(7)).0, 20);
+    assert!(gap_warning(28, Some(7)).1.is_some());
+  }
+
+  #[test]
+  fn test_derive_offline() {
+    let address = derive(DEPOSIT_DESC, false, 1).unwrap();
+    assert_eq!(address.address, "tb1qzdwqxt8l2s47vl4fp4ft6w67fcxel4qf5j96ld");
+    assert_eq!(address.script_pubkey, "0014135c032cff542be67ea90d52bd3b5e4e0d9fd409");
+    assert_eq!(address.key_paths.len(), 1);
+    assert_eq!(address.key_paths[0].fingerprint, "db7d25b5");
+    assert_eq!(address.key_paths[0].path, "m/84'/1'/6'/0/1");
+
+    let range = derive_range(DEPOSIT_DESC, false, 0, 3).unwrap();
+    assert_eq!(range.addresses.len(), 3);
+    assert_eq!(range.addresses[0].address, "tb1q093gl5yxww0hlvlkajdmf8wh3a6rlvsdk9e6d3");
+    assert_eq!(range.addresses[1].address, address.address);
+
+    let change = derive(DEPOSIT_DESC, parse_keychain("change").unwrap(), 0).unwrap();
+    assert!(change.is_change);
+    assert_eq!(change.key_paths[0].path, "m/84'/1'/6'/1/0");
+    assert!(validate(&change.address, Some(DEPOSIT_DESC)).unwrap().is_change);
+
+    assert!(derive_range(DEPOSIT_DESC, false, 5, 5).is_err());
+    assert!(derive_range(DEPOSIT_DESC, false, 0, MAX_DERIVE_COUNT + 1).is_err());
+    assert!(derive(DEPOSIT_DESC, false, u32::MAX).is_err());
+    assert!(parse_keychain("savings").is_err());
+  }
+
+  #[test]
+  fn test_find_address() {
+    let change = derive(DEPOSIT_DESC, true, 7).unwrap();
+    let found = find(DEPOSIT_DESC, &change.address, 10).unwrap();
+    assert!(found.found);
+    assert_eq!(found.keychain, Some("change".to_string()));
+    assert_eq!(found.index, Some(7));
+    assert_eq!(found.key_paths[0].path, "m/84'/1'/6'/1/7");
+    assert_eq!(found.key_paths[0].public_key.len(), 66);
+
+    let missed = find(DEPOSIT_DESC, &change.address, 6).unwrap();
+    assert!(!missed.found);
+    assert_eq!(missed.index, None);
+    assert!(missed.key_paths.is_empty());
+    assert!(find(DEPOSIT_DESC, "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq", 10).is_err());
+    assert!(find(DEPOSIT_DESC, &change.address, MAX_FIND_INDEX + 1).is_err());
+
+    let multisig = "wsh(multi(2,[db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*,[66a0c105/84'/1'/5']tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R/0/*))";
+    let receive = derive(multisig, false, 3).unwrap();
+    let found = find(multisig, &receive.address, 10).unwrap();
+    assert_eq!(found.keychain, Some("receive".to_string()));
+    assert_eq!(found.index, Some(3));
+    assert_eq!(found.key_paths.len(), 2);
+    assert_eq!(found.key_paths[0].public_key, derive(DEPOSIT_DESC, false, 3).unwrap().key_paths[0].public_key);
+    assert_eq!(found.key_paths[1].fingerprint, "66a0c105");
+    assert_eq!(found.key_paths[1].path, "m/84'/1'/5'/0/3");
+  }
+}
+
 // #[cfg(test)]
 // mod tests {
 //   use super::*;
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+use serde::{Deserialize, Serialize};
+
+use bitcoin::network::constants::Network;
+
+use crate::e::{ErrorKind, S5Error};
+use crate::wallet::address;
+
+const SCHEME: &str = "bitcoin:";
+const SATS_PER_BTC: u64 = 100_000_000;
+
+/// FFI Output
+/// amount is in sats. params holds any other optional parameters, percent-decoded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PaymentRequest {
+  pub address: String,
+  pub amount: Option<u64>,
+  pub label: Option<String>,
+  pub message: Option<String>,
+  pub lightning: Option<String>,
+  pub params: BTreeMap<String, String>,
+}
+
+impl PaymentRequest {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaymentUri {
+  pub uri: String,
+}
+
+impl PaymentUri {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+fn uri_error(message: &str) -> S5Error {
+  S5Error::new(ErrorKind::Input, message)
+}
+
+/// Percent-encodes everything except RFC 3986 unreserved characters.
+fn percent_encode(value: &str) -> String {
+  let mut out = String::new();
+  for byte in value.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(byte as char),
+      _ => out.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+  out
+}
+
+fn percent_decode(value: &str) -> Result<String, S5Error> {
+  let bytes = value.as_bytes();
+  let mut out = vec![];
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i] == b'%' {
+      let byte = bytes
+        .get(i + 1..i + 3)
+        .and_then(|hex| std::str::from_utf8(hex).ok())
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+      match byte {
+        Some(byte) => out.push(byte),
+        None => return Err(uri_error("Invalid percent-encoding in BIP21 URI.")),
+      }
+      i += 3;
+    } else {
+      out.push(bytes[i]);
+      i += 1;
+    }
+  }
+  match String::from_utf8(out) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(uri_error("Invalid percent-encoding in BIP21 URI.")),
+  }
+}
+
+/// Parses a decimal BTC amount into sats. No exponents, signs or more than 8 decimal places.
+fn parse_amount(amount: &str) -> Result<u64, S5Error> {
+  let invalid = || uri_error("Invalid BIP21 amount.");
+  let (whole, fraction) = match amount.split_once('.') {
+    Some((whole, fraction)) => (whole, fraction),
+    None => (amount, ""),
+  };
+  if (whole.is_empty() && fraction.is_empty())
+    || fraction.len() > 8
+    || !whole.bytes().chain(fraction.bytes()).all(|byte| byte.is_ascii_digit())
+  {
+    return Err(invalid());
+  }
+  let whole: u64 = if whole.is_empty() {
+    0
+  } else {
+    whole.parse().map_err(|_| invalid())?
+  };
+  let fraction: u64 = format!("{:0<8}", fraction).parse().map_err(|_| invalid())?;
+  match whole.checked_mul(SATS_PER_BTC).and_then(|sats| sats.checked_add(fraction)) {
+    Some(sats) if sats <= 21_000_000 * SATS_PER_BTC => Ok(sats),
+    _ => Err(invalid()),
+  }
+}
+
+fn format_amount(sats: u64) -> String {
+  let fraction = format!("{:08}", sats % SATS_PER_BTC);
+  let fraction = fraction.trim_end_matches('0');
+  if fraction.is_empty() {
+    format!("{}", sats / SATS_PER_BTC)
+  } else {
+    format!("{}.{}", sats / SATS_PER_BTC, fraction)
+  }
+}
+
+/// Parses a BIP21 URI and checks the address is for network.
+/// Unknown req- parameters are rejected, as BIP21 requires.
+pub fn parse(uri: &str, network: Network) -> Result<PaymentRequest, S5Error> {
+  let uri = uri.trim();
+  if !uri.get(..SCHEME.len()).is_some_and(|scheme| scheme.eq_ignore_ascii_case(SCHEME)) {
+    return Err(uri_error("Not a bitcoin: URI."));
+  }
+  let (address, query) = match uri[SCHEME.len()..].split_once('?') {
+    Some((address, query)) => (address, query),
+    None => (&uri[SCHEME.len()..], ""),
+  };
+  if address.is_empty() {
+    return Err(uri_error("BIP21 URI has no address."));
+  }
+  let address = address::parse(&percent_decode(address)?, network)?;
+
+  let mut request = PaymentRequest {
+    address: address.to_string(),
+    amount: None,
+    label: None,
+    message: None,
+    lightning: None,
+    params: BTreeMap::new(),
+  };
+  for param in query.split('&').filter(|param| !param.is_empty()) {
+    let (key, value) = match param.split_once('=') {
+      Some((key, value)) => (key, percent_decode(value)?),
+      None => (param, String::new()),
+    };
+    let key = key.to_lowercase();
+    let duplicate = match key.as_str() {
+      "amount" => request.amount.replace(parse_amount(&value)?).is_some(),
+      "label" => request.label.replace(value).is_some(),
+      "message" => request.message.replace(value).is_some(),
+      "lightning" => request.lightning.replace(value).is_some(),
+      _ if key.starts_with("req-") => {
+        return Err(uri_error(&format!("Unsupported required BIP21 parameter: {}.", key)))
+      }
+      _ => request.params.insert(key.clone(), value).is_some(),
+    };
+    if duplicate {
+      return Err(uri_error(&format!("Duplicate BIP21 parameter: {}.", key)));
+    }
+  }
+  Ok(request)
+}
+
+/// Creates a BIP21 URI for an address, eg. from address::generate. amount is in sats.
+pub fn create(
+  address: &str,
+  network: Network,
+  amount: Option<u64>,
+  label: Option<&str>,
+  message: Option<&str>,
+) -> Result<PaymentUri, S5Error> {
+  let address = address::parse(address, network)?;
+  let mut params = vec![];
+  if let Some(amount) = amount {
+    params.push(format!("amount={}", format_amount(amount)));
+  }
+  if let Some(label) = label {
+    params.push(format!("label={}", percent_encode(label)));
+  }
+  if let Some(message) = message {
+    params.push(format!("message={}", percent_encode(message)));
+  }
+  let uri = if params.is_empty() {
+    format!("{}{}", SCHEME, address)
+  } else {
+    format!("{}{}?{}", SCHEME, address, params.join("&"))
+  };
+  Ok(PaymentUri { uri })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const ADDRESS: &str = "tb1qzdwqxt8l2s47vl4fp4ft6w67fcxel4qf5j96ld";
+
+  #[test]
+  fn test_bip21() {
+    let uri = format!(
+      "bitcoin:{}?amount=0.001&label=Rent%20%26%20bills&message=June&lightning=lntb1u1p&pj=https://example.com",
+      ADDRESS
+    );
+    let request = parse(&uri, Network::Testnet).unwrap();
+    assert_eq!(request.address, ADDRESS);
+    assert_eq!(request.amount, Some(100_000));
+    assert_eq!(request.label, Some("Rent & bills".to_string()));
+    assert_eq!(request.message, Some("June".to_string()));
+    assert_eq!(request.lightning, Some("lntb1u1p".to_string()));
+    assert_eq!(request.params.get("pj"), Some(&"https://example.com".to_string()));
+
+    let upper = format!("BITCOIN:{}?AMOUNT=20.3", ADDRESS.to_uppercase());
+    assert_eq!(parse(&upper, Network::Testnet).unwrap().amount, Some(2_030_000_000));
+    assert_eq!(parse(&format!("bitcoin:{}", ADDRESS), Network::Testnet).unwrap().amount, None);
+    assert_eq!(
+      parse(&format!("bitcoin:{}?req-somethingyoudontunderstand=50", ADDRESS), Network::Testnet)
+        .err()
+        .unwrap()
+        .message,
+      "Unsupported required BIP21 parameter: req-somethingyoudontunderstand."
+    );
+    assert!(parse(&format!("bitcoin:{}?somethingelse=50", ADDRESS), Network::Testnet).is_ok());
+    for amount in ["1e3", "-1", "0.000000001", ".", "21000001", "1,5"] {
+      let uri = format!("bitcoin:{}?amount={}", ADDRESS, amount);
+      assert_eq!(parse(&uri, Network::Testnet).err().unwrap().message, "Invalid BIP21 amount.");
+    }
+    assert!(parse(&format!("bitcoin:{}?amount=1&amount=2", ADDRESS), Network::Testnet).is_err());
+    assert!(parse(&format!("bitcoin:{}", ADDRESS), Network::Bitcoin).is_err());
+    assert!(parse(ADDRESS, Network::Testnet).is_err());
+    assert_eq!(parse("bitcoinñ:x", Network::Testnet).err().unwrap().message, "Not a bitcoin: URI.");
+
+    let created = create(ADDRESS, Network::Testnet, Some(150_000_000), Some("Rent & bills"), Some("Ünïcode")).unwrap();
+    assert_eq!(
+      created.uri,
+      format!("bitcoin:{}?amount=1.5&label=Rent%20%26%20bills&message=%C3%9Cn%C3%AFcode", ADDRESS)
+    );
+    let round_trip = parse(&created.uri, Network::Testnet).unwrap();
+    assert_eq!(round_trip.amount, Some(150_000_000));
+    assert_eq!(round_trip.message, Some("Ünïcode".to_string()));
+    assert_eq!(create(ADDRESS, Network::Testnet, None, None, None).unwrap().uri, format!("bitcoin:{}", ADDRESS));
+  }
+}
@@ -0,0 +1,594 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use aes::Aes256;
+use ctr::cipher::{NewCipher, StreamCipher};
+use ctr::Ctr128BE;
+
+use bdk::miniscript::descriptor::{DescriptorPublicKey, Wildcard};
+
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, sha512, Hash, HashEngine};
+use bitcoin::secp256k1::rand::rngs::OsRng;
+use bitcoin::secp256k1::rand::RngCore;
+use bitcoin::secp256k1::{Message, Secp256k1};
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use bitcoin::util::misc::{signed_msg_hash, MessageSignature};
+
+use crate::config;
+use crate::e::{ErrorKind, S5Error};
+use crate::key::child;
+use crate::wallet::address;
+use crate::wallet::descriptor;
+use crate::wallet::export;
+
+const VERSION: &str = "BSMS 1.0";
+/// TOKEN of a session without encryption.
+pub const NO_ENCRYPTION: &str = "00";
+const PATH_RESTRICTIONS: &str = "/0/*,/1/*";
+const MAX_DESCRIPTION_LENGTH: usize = 80;
+const KDF_PASSWORD: &[u8] = b"No SPOF";
+const KDF_ROUNDS: u32 = 2048;
+const MAC_LENGTH: usize = 32;
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BsmsToken {
+  pub token: String,
+}
+
+impl BsmsToken {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// FFI Output
+/// record is plain text without encryption, otherwise hex.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BsmsRecord {
+  pub record: String,
+  pub xpub: String,
+}
+
+impl BsmsRecord {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// A key record received by the coordinator, with the TOKEN it was given to that signer.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BsmsSubmission {
+  pub token: String,
+  pub record: String,
+}
+
+/// FFI Output
+/// records has the descriptor record for each submission, in the same order and encrypted with its TOKEN.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BsmsSetup {
+  pub deposit_desc: String,
+  pub template: String,
+  pub first_address: String,
+  pub records: Vec<String>,
+}
+
+impl BsmsSetup {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BsmsWallet {
+  pub deposit_desc: String,
+  pub change_desc: String,
+  pub first_address: String,
+}
+
+impl BsmsWallet {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+fn bsms_error(message: &str) -> S5Error {
+  S5Error::new(ErrorKind::Input, message)
+}
+
+/// Creates a TOKEN for one signer. mode is "none" (00), "standard" (64 bit) or "extended" (128 bit).
+pub fn token(mode: &str) -> Result<BsmsToken, S5Error> {
+  let length = match mode {
+    "none" => return Ok(BsmsToken { token: NO_ENCRYPTION.to_string() }),
+    "standard" => 8,
+    "extended" => 16,
+    _ => return Err(bsms_error("Invalid BSMS encryption mode. Use none, standard or extended.")),
+  };
+  let mut rng = match OsRng::new() {
+    Ok(r) => r,
+    Err(e) => return Err(S5Error::new(ErrorKind::Key, &e.to_string())),
+  };
+  let mut token = vec![0u8; length];
+  rng.fill_bytes(&mut token);
+  Ok(BsmsToken { token: hex::encode(token) })
+}
+
+/// TOKEN bytes, or None without encryption.
+fn token_bytes(token: &str) -> Result<Option<Vec<u8>>, S5Error> {
+  if token == NO_ENCRYPTION {
+    return Ok(None);
+  }
+  match hex::decode(token) {
+    Ok(bytes) if bytes.len() == 8 || bytes.len() == 16 => Ok(Some(bytes)),
+    _ => Err(bsms_error("Invalid BSMS token.")),
+  }
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+  let mut engine = HmacEngine::<sha512::Hash>::new(key);
+  engine.input(data);
+  Hmac::from_engine(engine).into_inner()
+}
+
+/// PBKDF2-HMAC-SHA512 of a single 64 byte block, which is all BIP129 needs.
+fn pbkdf2_sha512(password: &[u8], salt: &[u8], rounds: u32) -> [u8; 64] {
+  let mut block = salt.to_vec();
+  block.extend_from_slice(&1u32.to_be_bytes());
+  let mut round = hmac_sha512(password, &block);
+  let mut output = round;
+  for _ in 1..rounds {
+    round = hmac_sha512(password, &round);
+    output.iter_mut().zip(round.iter()).for_each(|(out, byte)| *out ^= byte);
+  }
+  output
+}
+
+fn encryption_key(token_bytes: &[u8]) -> [u8; 32] {
+  let mut key = [0u8; 32];
+  key.copy_from_slice(&pbkdf2_sha512(KDF_PASSWORD, token_bytes, KDF_ROUNDS)[..32]);
+  key
+}
+
+/// HMAC over TOKEN || data. Its first 16 bytes are the AES-CTR IV.
+fn mac(key: &[u8; 32], token: &str, data: &[u8]) -> [u8; MAC_LENGTH] {
+  let hmac_key = sha256::Hash::hash(key);
+  let mut engine = HmacEngine::<sha256::Hash>::new(&hmac_key[..]);
+  engine.input(token.as_bytes());
+  engine.input(data);
+  Hmac::from_engine(engine).into_inner()
+}
+
+fn aes_ctr(key: &[u8; 32], mac: &[u8; MAC_LENGTH], data: &mut [u8]) {
+  let mut cipher = Ctr128BE::<Aes256>::new(key.into(), mac[..16].into());
+  cipher.apply_keystream(data);
+}
+
+/// Encrypts a record with a TOKEN as hex MAC || CIPHERTEXT. Records for TOKEN 00 stay plain text.
+fn encrypt(token: &str, record: &str) -> Result<String, S5Error> {
+  let token_bytes = match token_bytes(token)? {
+    Some(bytes) => bytes,
+    None => return Ok(record.to_string()),
+  };
+  let key = encryption_key(&token_bytes);
+  let record_mac = mac(&key, token, record.as_bytes());
+  let mut data = record.as_bytes().to_vec();
+  aes_ctr(&key, &record_mac, &mut data);
+  Ok(hex::encode([&record_mac[..], &data[..]].concat()))
+}
+
+fn decrypt(token: &str, record: &str) -> Result<String, S5Error> {
+  let token_bytes = match token_bytes(token)? {
+    Some(bytes) => bytes,
+    None => return Ok(record.to_string()),
+  };
+  let encrypted = match hex::decode(record.trim()) {
+    Ok(bytes) if bytes.len() > MAC_LENGTH => bytes,
+    _ => return Err(bsms_error("Encrypted BSMS records must be hex.")),
+  };
+  let mut record_mac = [0u8; MAC_LENGTH];
+  record_mac.copy_from_slice(&encrypted[..MAC_LENGTH]);
+  let mut data = encrypted[MAC_LENGTH..].to_vec();
+  let key = encryption_key(&token_bytes);
+  aes_ctr(&key, &record_mac, &mut data);
+  if mac(&key, token, &data) != record_mac {
+    return Err(bsms_error("BSMS record does not match the token."));
+  }
+  match String::from_utf8(data) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(bsms_error("BSMS record does not match the token.")),
+  }
+}
+
+/// Lines of a record, which must start with BSMS 1.0 and have count lines.
+fn record_lines(record: &str, count: usize) -> Result<Vec<String>, S5Error> {
+  let lines: Vec<String> = record.trim().lines().map(|line| line.trim().to_string()).collect();
+  if lines.len() != count || lines[0] != VERSION {
+    return Err(bsms_error(&format!("BSMS records must have {} lines starting with {}.", count, VERSION)));
+  }
+  Ok(lines)
+}
+
+/// Signs the first four lines of a key record with the key in KEY, as a Bitcoin signed message.
+fn sign_record(message: &str, xprv: &ExtendedPrivKey) -> Result<String, S5Error> {
+  let secp = Secp256k1::signing_only();
+  let msg = match Message::from_slice(&signed_msg_hash(message)[..]) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Message-Hash")),
+  };
+  let signature = secp.sign_recoverable(&msg, &xprv.private_key.key);
+  Ok(MessageSignature::new(signature, true).to_base64())
+}
+
+/// BIP48 script type index of a multisig account, the last level of m/48'/coin'/account'/script_type'.
+fn bip48_script_type(script_type: &str) -> Result<u32, S5Error> {
+  match script_type {
+    "sh-wsh" => Ok(1),
+    "wsh" => Ok(2),
+    _ => Err(bsms_error("BSMS key records use BIP48 paths, which are only defined for wsh and sh-wsh.")),
+  }
+}
+
+/// Creates a signer's key record for the BIP48 account at m/48'/coin'/account'/script_type' of a master key,
+/// the key a hardware signer like ColdCard gives for the same seed. script_type is wsh or sh-wsh.
+/// The record is encrypted with the TOKEN the coordinator gave this signer, unless it is 00.
+/// xpub is returned to check the descriptor record with verify_descriptor_record.
+pub fn key_record(
+  master_xprv: &str,
+  script_type: &str,
+  account: &str,
+  token: &str,
+  description: &str,
+) -> Result<BsmsRecord, S5Error> {
+  token_bytes(token)?;
+  if description.len() > MAX_DESCRIPTION_LENGTH || description.contains('\n') {
+    return Err(bsms_error(&format!(
+      "BSMS descriptions must be one line of at most {} characters.",
+      MAX_DESCRIPTION_LENGTH
+    )));
+  }
+  let script_index = match ChildNumber::from_hardened_idx(bip48_script_type(script_type)?) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Key, "Invalid derivation path.")),
+  };
+  let child_keys = child::derive(master_xprv, "48", account)?;
+  let path = match DerivationPath::from_str(&child_keys.hardened_path) {
+    Ok(result) => result.child(script_index),
+    Err(_) => return Err(S5Error::new(ErrorKind::Key, "Invalid derivation path.")),
+  };
+  let secp = Secp256k1::new();
+  let xprv = match ExtendedPrivKey::from_str(&child_keys.xprv).map(|account| account.ckd_priv(&secp, script_index)) {
+    Ok(Ok(result)) => result,
+    _ => return Err(S5Error::new(ErrorKind::Key, "Invalid Child Key.")),
+  };
+  let xpub = ExtendedPubKey::from_private(&secp, &xprv);
+  let message = format!(
+    "{}\n{}\n[{}{}]{}\n{}",
+    VERSION,
+    token,
+    child_keys.fingerprint,
+    &path.to_string()[1..],
+    xpub,
+    description
+  );
+  let signature = sign_record(&message, &xprv)?;
+  Ok(BsmsRecord {
+    record: encrypt(token, &format!("{}\n{}", message, signature))?,
+    xpub: xpub.to_string(),
+  })
+}
+
+/// Reads a key record, checking its TOKEN and signature. Returns the KEY and its xpub.
+fn read_key_record(submission: &BsmsSubmission) -> Result<(String, ExtendedPubKey), S5Error> {
+  let record = decrypt(&submission.token, &submission.record)?;
+  let lines = record_lines(&record, 5)?;
+  if lines[1] != submission.token {
+    return Err(bsms_error("BSMS key record has a different token."));
+  }
+  let xpub = match DescriptorPublicKey::from_str(&lines[2]) {
+    Ok(DescriptorPublicKey::XPub(xkey))
+      if xkey.origin.is_some() && xkey.derivation_path.as_ref().is_empty() && xkey.wildcard == Wildcard::None =>
+    {
+      xkey.xkey
+    }
+    _ => return Err(bsms_error("BSMS KEY must be an xpub with its key origin.")),
+  };
+  let secp = Secp256k1::verification_only();
+  let signed = MessageSignature::from_base64(&lines[4])
+    .ok()
+    .and_then(|signature| signature.recover_pubkey(&secp, signed_msg_hash(&lines[..4].join("\n"))).ok());
+  match signed {
+    Some(public_key) if public_key.key == xpub.public_key.key => Ok((lines[2].clone(), xpub)),
+    _ => Err(bsms_error(&format!("Invalid signature on the BSMS key record of {}.", lines[3]))),
+  }
+}
+
+/// Coordinates a threshold of N sortedmulti wallet from the signers' key records.
+/// script_type is wsh, sh-wsh or sh. Every record's signature is checked before the descriptor record is built.
+pub fn coordinate(threshold: usize, script_type: &str, submissions: &[BsmsSubmission]) -> Result<BsmsSetup, S5Error> {
+  let mut keys: Vec<String> = vec![];
+  let mut network = None;
+  for submission in submissions {
+    let (key, xpub) = read_key_record(submission)?;
+    if network.is_some() && network != Some(xpub.network) {
+      return Err(bsms_error("BSMS keys are for different networks."));
+    }
+    network = Some(xpub.network);
+    if keys.iter().any(|existing| existing == &key) {
+      return Err(bsms_error("BSMS key records contain the same key twice."));
+    }
+    keys.push(key);
+  }
+  if threshold == 0 || threshold > keys.len() {
+    return Err(bsms_error("Threshold must be between 1 and the number of key records."));
+  }
+
+  let multi = format!(
+    "sortedmulti({},{})",
+    threshold,
+    keys.iter().map(|key| format!("{}/**", key)).collect::<Vec<String>>().join(",")
+  );
+  let template = match script_type {
+    "wsh" => format!("wsh({})", multi),
+    "sh-wsh" => format!("sh(wsh({}))", multi),
+    "sh" => format!("sh({})", multi),
+    _ => return Err(bsms_error("Invalid script type. Use wsh, sh-wsh or sh.")),
+  };
+  let template = descriptor::checksum(&template)?.descriptor;
+  let deposit_desc = deposit_descriptor(&template)?;
+  let first_address = address::derive(&deposit_desc, false, 0)?.address;
+
+  let record = format!("{}\n{}\n{}\n{}", VERSION, template, PATH_RESTRICTIONS, first_address);
+  let records = submissions
+    .iter()
+    .map(|submission| encrypt(&submission.token, &record))
+    .collect::<Result<Vec<String>, S5Error>>()?;
+  Ok(BsmsSetup {
+    deposit_desc,
+    template,
+    first_address,
+    records,
+  })
+}
+
+/// Deposit descriptor of a checksummed BSMS descriptor template, eg. wsh(sortedmulti(2,KEY/**,KEY/**))#checksum
+fn deposit_descriptor(template: &str) -> Result<String, S5Error> {
+  if !template.contains('#') {
+    return Err(bsms_error("BSMS descriptor has no checksum."));
+  }
+  let deposit_desc = descriptor::strip_checksum(template)?.replace("/**", "/0/*");
+  export::account_keys(&deposit_desc)?;
+  Ok(deposit_desc)
+}
+
+/// Checks the descriptor record a signer received: it must contain xpub and derive the first address it lists.
+pub fn verify_descriptor_record(record: &str, token: &str, xpub: &str) -> Result<BsmsWallet, S5Error> {
+  let lines = record_lines(&decrypt(token, record)?, 4)?;
+  if lines[2] != PATH_RESTRICTIONS && lines[2] != "No path restrictions" {
+    return Err(bsms_error("Unsupported BSMS path restrictions."));
+  }
+  let deposit_desc = deposit_descriptor(&lines[1])?;
+  let own_key = match ExtendedPubKey::from_str(xpub) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Key, "Invalid Extended Public Key.")),
+  };
+  if !export::account_keys(&deposit_desc)?
+    .cosigners
+    .iter()
+    .any(|cosigner| cosigner.xpub == own_key)
+  {
+    return Err(bsms_error("BSMS descriptor does not contain this signer's key."));
+  }
+  let first_address = address::derive(&deposit_desc, false, 0)?.address;
+  if first_address != lines[3] {
+    return Err(bsms_error(&format!(
+      "BSMS first address {} does not match derived address {}.",
+      lines[3], first_address
+    )));
+  }
+  Ok(BsmsWallet {
+    change_desc: config::change_descriptor(&deposit_desc),
+    deposit_desc,
+    first_address,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::key::master;
+  use bitcoin::network::constants::Network;
+
+  const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+  const MNEMONIC_2: &str = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+  #[test]
+  fn test_pbkdf2() {
+    // BIP39 seed of MNEMONIC with passphrase TREZOR
+    let seed = pbkdf2_sha512(MNEMONIC.as_bytes(), b"mnemonicTREZOR", 2048);
+    assert_eq!(
+      hex::encode(seed),
+      "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+    );
+  }
+
+  #[test]
+  fn test_aes_ctr() {
+    // NIST SP 800-38A F.5.5 CTR-AES256, with the counter block taken from the first 16 bytes of the MAC
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&hex::decode("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").unwrap());
+    let mut record_mac = [0u8; MAC_LENGTH];
+    record_mac[..16].copy_from_slice(&hex::decode("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").unwrap());
+    let mut data = hex::decode("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51").unwrap();
+    aes_ctr(&key, &record_mac, &mut data);
+    assert_eq!(
+      hex::encode(data),
+      "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5"
+    );
+  }
+
+  #[test]
+  fn test_sign_record() {
+    let xprv = ExtendedPrivKey::from_str(&master::import(MNEMONIC, "", Network::Testnet).unwrap().xprv).unwrap();
+    let secp = Secp256k1::new();
+    let message = "BSMS 1.0\n00\n[73c5da0a/48'/1'/0'/2']tpub\nSigner 1 key";
+    let signature = MessageSignature::from_base64(&sign_record(message, &xprv).unwrap()).unwrap();
+    let public_key = bitcoin::PublicKey::new(bitcoin::secp256k1::PublicKey::from_secret_key(&secp, &xprv.private_key.key));
+    let address = bitcoin::Address::p2pkh(&public_key, Network::Testnet);
+    assert!(signature.is_signed_by_address(&secp, &address, signed_msg_hash(message)).unwrap());
+    assert!(!signature.is_signed_by_address(&secp, &address, signed_msg_hash("BSMS 1.0")).unwrap());
+  }
+
+  #[test]
+  fn test_bsms_encryption() {
+    let standard = token("standard").unwrap().token;
+    assert_eq!(standard.len(), 16);
+    assert_eq!(token("extended").unwrap().token.len(), 32);
+    assert_eq!(token("none").unwrap().token, NO_ENCRYPTION);
+    assert!(token("strong").is_err());
+
+    let encrypted = encrypt(&standard, "BSMS 1.0\nrecord").unwrap();
+    assert!(hex::decode(&encrypted).is_ok());
+    assert_eq!(decrypt(&standard, &encrypted).unwrap(), "BSMS 1.0\nrecord");
+    assert!(decrypt("a1b2c3d4e5f6a7b8", &encrypted).is_err());
+    assert_eq!(encrypt(NO_ENCRYPTION, "record").unwrap(), "record");
+    assert!(encrypt("abc", "record").is_err());
+  }
+
+  fn bsms_round_trip(mode: &str) {
+    let signers: Vec<String> = [MNEMONIC, MNEMONIC_2]
+      .iter()
+      .map(|mnemonic| master::import(mnemonic, "", Network::Testnet).unwrap().xprv)
+      .collect();
+    let records: Vec<(BsmsSubmission, String)> = signers
+      .iter()
+      .enumerate()
+      .map(|(index, xprv)| {
+        let token = token(mode).unwrap().token;
+        let key = key_record(xprv, "wsh", "0", &token, &format!("Signer {}", index + 1)).unwrap();
+        (BsmsSubmission { token, record: key.record }, key.xpub)
+      })
+      .collect();
+    let submissions: Vec<BsmsSubmission> = records.iter().map(|(submission, _)| submission.clone()).collect();
+
+    let setup = coordinate(2, "wsh", &submissions).unwrap();
+    assert!(setup.template.starts_with("wsh(sortedmulti(2,[73c5da0a/48'/1'/0'/2']tpub"));
+    assert!(setup.template.contains("/**,["));
+    assert!(setup.deposit_desc.ends_with("/0/*))"));
+    assert!(setup.first_address.starts_with("tb1q"));
+    assert_eq!(setup.records.len(), 2);
+
+    for (index, (submission, xpub)) in records.iter().enumerate() {
+      let wallet = verify_descriptor_record(&setup.records[index], &submission.token, xpub).unwrap();
+      assert_eq!(wallet.deposit_desc, setup.deposit_desc);
+      assert_eq!(wallet.first_address, setup.first_address);
+    }
+  }
+
+  #[test]
+  fn test_bsms_unencrypted() {
+    bsms_round_trip("none");
+
+    let xprv = master::import(MNEMONIC, "", Network::Testnet).unwrap().xprv;
+    let key = key_record(&xprv, "wsh", "0", NO_ENCRYPTION, "Signer 1").unwrap();
+    let record = key.record;
+    let lines: Vec<&str> = record.lines().collect();
+    assert_eq!(lines[0], "BSMS 1.0");
+    assert_eq!(lines[1], "00");
+    assert_eq!(lines[2], format!("[73c5da0a/48'/1'/0'/2']{}", key.xpub));
+    // BIP48 account key, derived independently of child::derive
+    let secp = Secp256k1::new();
+    let root = ExtendedPrivKey::from_str(&xprv).unwrap();
+    let bip48 = root.derive_priv(&secp, &DerivationPath::from_str("m/48'/1'/0'/2'").unwrap()).unwrap();
+    assert_eq!(key.xpub, ExtendedPubKey::from_private(&secp, &bip48).to_string());
+    let nested = key_record(&xprv, "sh-wsh", "0", NO_ENCRYPTION, "Signer 1").unwrap().record;
+    assert!(nested.lines().nth(2).unwrap().starts_with("[73c5da0a/48'/1'/0'/1']tpub"));
+    assert!(key_record(&xprv, "sh", "0", NO_ENCRYPTION, "Signer 1").is_err());
+    assert_eq!(lines[3], "Signer 1");
+
+    let forged = BsmsSubmission {
+      token: NO_ENCRYPTION.to_string(),
+      record: record.replace("Signer 1", "Signer 9"),
+    };
+    assert_eq!(
+      coordinate(1, "wsh", &[forged]).err().unwrap().message,
+      "Invalid signature on the BSMS key record of Signer 9."
+    );
+    let submission = BsmsSubmission { token: NO_ENCRYPTION.to_string(), record };
+    assert!(coordinate(1, "wsh", &[submission.clone(), submission.clone()]).is_err());
+    assert!(coordinate(2, "wsh", std::slice::from_ref(&submission)).is_err());
+    assert!(coordinate(1, "tr", &[submission]).is_err());
+    assert!(key_record(&xprv, "wsh", "0", NO_ENCRYPTION, &"x".repeat(81)).is_err());
+  }
+
+  #[test]
+  fn test_bsms_encrypted() {
+    bsms_round_trip("standard");
+    bsms_round_trip("extended");
+
+    let xprv = master::import(MNEMONIC, "", Network::Testnet).unwrap().xprv;
+    let standard = token("standard").unwrap().token;
+    let key = key_record(&xprv, "sh-wsh", "0", &standard, "Signer 1").unwrap();
+    let (record, xpub) = (key.record, key.xpub);
+    let submission = BsmsSubmission { token: standard.clone(), record };
+    let setup = coordinate(1, "sh-wsh", std::slice::from_ref(&submission)).unwrap();
+    assert!(setup.first_address.starts_with('2'));
+    assert!(verify_descriptor_record(&setup.records[0], "00", &xpub).is_err());
+
+    let wrong_token = BsmsSubmission { token: "a1b2c3d4e5f6a7b8".to_string(), ..submission };
+    assert_eq!(coordinate(1, "wsh", &[wrong_token]).err().unwrap().message, "BSMS record does not match the token.");
+
+    let plain = format!(
+      "BSMS 1.0\n{}\n/0/*,/1/*\ntb1qwrongaddress",
+      setup.template
+    );
+    let tampered = encrypt(&standard, &plain).unwrap();
+    assert!(verify_descriptor_record(&tampered, &standard, &xpub)
+      .err()
+      .unwrap()
+      .message
+      .starts_with("BSMS first address"));
+    let other_xpub = child::derive(&master::import(MNEMONIC_2, "", Network::Testnet).unwrap().xprv, "48", "0").unwrap().xpub;
+    assert!(verify_descriptor_record(&setup.records[0], &standard, &other_xpub).is_err());
+  }
+}
@@ -1,8 +1,12 @@
+use std::ffi::CString;
 use std::fs::File;
+use std::os::raw::c_char;
 
 use serde_derive::{Serialize,Deserialize};
 
 use crate::e::{S5Error,ErrorKind};
+use crate::wallet::address;
+use crate::wallet::export::{self, ScriptType};
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize,Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -64,41 +68,226 @@ impl ColdCardKeys{
 
     
   }
+
+  pub fn from_json(json: &str)->Result<ColdCardKeys,S5Error>{
+    match serde_json::from_str(json){
+      Ok(result)=>Ok(result),
+      Err(_)=>Err(S5Error::new(ErrorKind::Input,"Invalid ColdCard JSON."))
+    }
+  }
 }
 
-// #[cfg(test)]
-
-// mod tests {
-//   use super::*;
-//   use std::path::PathBuf;
-//   use crate::wallet::address;
-//   use crate::config::{WalletConfig, DEFAULT_MAINNET_NODE, BlockchainBackend}; 
-
-//   #[test] #[ignore]
-//   fn test_coldcard_watcher() {
-//     let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-//     path.push("resources/cc.json");
-//     let cckeys = ColdCardKeys::_from_json_file(&path.to_str().unwrap()).unwrap();
-  
-//     let key_source_84 = cckeys.bip84.deriv.replace("m",&cckeys.xfp.to_lowercase());
-//     let bip84_deposit_desc = format!("wpkh([{}]{}/0/*)",key_source_84,cckeys.bip84.xpub);
-//     let config = WalletConfig::new(&bip84_deposit_desc, BlockchainBackend::Electrum,DEFAULT_MAINNET_NODE,None).unwrap();
-//     let bip84_first_address = address::generate(config,0).unwrap();
-//     assert_eq!(bip84_first_address.address,cckeys.bip84.first);
-
-//     let key_source_49 = cckeys.bip49.deriv.replace("m",&cckeys.xfp.to_lowercase());
-//     let bip49_deposit_desc = format!("sh(wpkh([{}]{}/0/*))",key_source_49,cckeys.bip49.xpub);
-//     let config = WalletConfig::new(&bip49_deposit_desc, BlockchainBackend::Electrum, DEFAULT_MAINNET_NODE,None).unwrap();
-
-//     let bip49_first_address = address::generate(config,0).unwrap();
-//     assert_eq!(bip49_first_address.address,cckeys.bip49.first);
-
-//     let key_source_44 = cckeys.bip49.deriv.replace("m",&cckeys.xfp.to_lowercase());
-//     let bip44_deposit_desc = format!("pkh([{}]{}/0/*)",key_source_44,cckeys.bip44.xpub);
-//     let config = WalletConfig::new(&bip44_deposit_desc, BlockchainBackend::Electrum,DEFAULT_MAINNET_NODE,None).unwrap();
-
-//     let bip44_first_address = address::generate(config,0).unwrap();
-//     assert_eq!(bip44_first_address.address,cckeys.bip44.first);
-    
-//   }
-// }
+
+/// FFI Output
+/// Watch-only deposit descriptors for each single signature account of a ColdCard export.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ColdCardDescriptors {
+  pub network: String,
+  pub fingerprint: String,
+  pub bip44: String,
+  pub bip49: String,
+  pub bip84: String,
+}
+
+impl ColdCardDescriptors {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// FFI Output
+/// Contents of a ColdCard multisig setup text file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ColdCardMultisig {
+  pub file: String,
+}
+
+impl ColdCardMultisig {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Longest wallet name a ColdCard accepts.
+pub const MAX_NAME_LENGTH: usize = 20;
+
+/// Receive key expression of an account, eg. [73c5da0a/84'/0'/0']xpub.../0/*
+fn account_key(xfp: &str, deriv: &str, xpub: &str) -> String {
+  format!("[{}]{}/0/*", deriv.replacen('m', &xfp.to_lowercase(), 1), xpub)
+}
+
+/// Checks a deposit descriptor derives the `first` address ColdCard exported for its account.
+fn check_first(deposit_desc: String, first: &str) -> Result<String, S5Error> {
+  let derived = address::derive(&deposit_desc, false, 0)?;
+  if derived.address != first {
+    return Err(S5Error::new(
+      ErrorKind::Input,
+      &format!(
+        "ColdCard first address {} does not match derived address {}.",
+        first, derived.address
+      ),
+    ));
+  }
+  Ok(deposit_desc)
+}
+
+/// Imports the generic JSON export of a ColdCard as bip44, bip49 and bip84 watch-only descriptors.
+/// Each descriptor must derive the first address listed in the export.
+pub fn import(json: &str) -> Result<ColdCardDescriptors, S5Error> {
+  let keys = ColdCardKeys::from_json(json)?;
+  let network = match keys.chain.as_str() {
+    "BTC" => "main",
+    "XTN" => "test",
+    _ => return Err(S5Error::new(ErrorKind::Input, "Unsupported ColdCard chain.")),
+  };
+  let bip44 = account_key(&keys.xfp, &keys.bip44.deriv, &keys.bip44.xpub);
+  let bip49 = account_key(&keys.xfp, &keys.bip49.deriv, &keys.bip49.xpub);
+  let bip84 = account_key(&keys.xfp, &keys.bip84.deriv, &keys.bip84.xpub);
+  Ok(ColdCardDescriptors {
+    network: network.to_string(),
+    fingerprint: keys.xfp.to_lowercase(),
+    bip44: check_first(format!("pkh({})", bip44), &keys.bip44.first)?,
+    bip49: check_first(format!("sh(wpkh({}))", bip49), &keys.bip49.first)?,
+    bip84: check_first(format!("wpkh({})", bip84), &keys.bip84.first)?,
+  })
+}
+
+/// Creates the ColdCard multisig setup file for a sortedmulti deposit descriptor.
+/// Derivation is written once when all cosigners share it, otherwise before each cosigner.
+pub fn multisig_setup(name: &str, deposit_desc: &str) -> Result<ColdCardMultisig, S5Error> {
+  if name.is_empty() || name.len() > MAX_NAME_LENGTH || !name.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+    return Err(S5Error::new(
+      ErrorKind::Input,
+      &format!("ColdCard wallet names must be 1 to {} ASCII characters.", MAX_NAME_LENGTH),
+    ));
+  }
+  let keys = export::account_keys(deposit_desc)?;
+  let format = match keys.script_type {
+    ScriptType::P2sh => "P2SH",
+    ScriptType::P2shP2wsh => "P2SH-P2WSH",
+    ScriptType::P2wsh => "P2WSH",
+    _ => return Err(S5Error::new(ErrorKind::Input, "Only sortedmulti descriptors are supported.")),
+  };
+  let mut cosigners = vec![];
+  for cosigner in &keys.cosigners {
+    match &cosigner.origin {
+      Some((fingerprint, path)) => cosigners.push((fingerprint.to_string(), path.to_string(), cosigner.xpub)),
+      None => return Err(S5Error::new(ErrorKind::Input, "ColdCard multisig keys need a key origin.")),
+    }
+  }
+  let shared_path = cosigners.iter().all(|(_, path, _)| path == &cosigners[0].1);
+
+  let mut file = format!(
+    "# Coldcard Multisig setup file\n#\nName: {}\nPolicy: {} of {}\n",
+    name,
+    keys.threshold,
+    cosigners.len()
+  );
+  if shared_path {
+    file.push_str(&format!("Derivation: {}\n", cosigners[0].1));
+  }
+  file.push_str(&format!("Format: {}\n", format));
+  for (fingerprint, path, xpub) in &cosigners {
+    file.push('\n');
+    if !shared_path {
+      file.push_str(&format!("Derivation: {}\n", path));
+    }
+    file.push_str(&format!("{}: {}\n", fingerprint.to_uppercase(), xpub));
+  }
+  Ok(ColdCardMultisig { file })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Generic JSON export for the BIP84 test vector mnemonic (abandon x11 about).
+  const COLDCARD_JSON: &str = r#"{
+    "chain": "BTC",
+    "xfp": "73C5DA0A",
+    "account": 0,
+    "xpub": "xpub661MyMwAqRbcFkPHucMnrGNzDwb6teAX1RbKQmqtEF8kK3Z7LZ59qafCjB9eCRLiTVG3uxBxgKvRgbubRhqSKXnGGb1aoaqLrpMBDrVxga8",
+    "bip44": {
+      "name": "p2pkh",
+      "xfp": "73C5DA0A",
+      "deriv": "m/44'/0'/0'",
+      "xpub": "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj",
+      "desc": "pkh([73c5da0a/44h/0h/0h]xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj/<0;1>/*)",
+      "first": "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"
+    },
+    "bip49": {
+      "name": "p2sh-p2wpkh",
+      "xfp": "73C5DA0A",
+      "deriv": "m/49'/0'/0'",
+      "xpub": "xpub6C6nQwHaWbSrzs5tZ1q7m5R9cPK9eYpNMFesiXsYrgc1P8bvLLAet9JfHjYXKjToD8cBRswJXXbbFpXgwsswVPAZzKMa1jUp2kVkGVUaJa7",
+      "_pub": "ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP",
+      "first": "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"
+    },
+    "bip84": {
+      "name": "p2wpkh",
+      "xfp": "73C5DA0A",
+      "deriv": "m/84'/0'/0'",
+      "xpub": "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V",
+      "_pub": "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs",
+      "first": "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+    }
+  }"#;
+
+  const MULTISIG_DESC: &str = "wsh(sortedmulti(2,[db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*,[66a0c105/84'/1'/5']tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R/0/*))";
+
+  #[test]
+  fn test_coldcard_import() {
+    let descriptors = import(COLDCARD_JSON).unwrap();
+    assert_eq!(descriptors.network, "main");
+    assert_eq!(descriptors.fingerprint, "73c5da0a");
+    assert_eq!(
+      descriptors.bip84,
+      "wpkh([73c5da0a/84'/0'/0']xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*)"
+    );
+    assert!(descriptors.bip49.starts_with("sh(wpkh([73c5da0a/49'/0'/0']xpub6C6nQ"));
+    assert!(descriptors.bip49.ends_with("/0/*))"));
+    assert!(descriptors.bip44.starts_with("pkh([73c5da0a/44'/0'/0']xpub6Bosf"));
+
+    let tampered = COLDCARD_JSON.replace("1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA", "1JaUQDVNRdhfNsVncGkXedaPSM5Gc54Hso");
+    assert!(import(&tampered).err().unwrap().message.starts_with("ColdCard first address"));
+    assert!(import(&COLDCARD_JSON.replace("\"BTC\"", "\"LTC\"")).is_err());
+    assert_eq!(import("{}").err().unwrap().message, "Invalid ColdCard JSON.");
+  }
+
+  #[test]
+  fn test_coldcard_multisig_setup() {
+    let setup = multisig_setup("Family Vault", MULTISIG_DESC).unwrap();
+    assert_eq!(
+      setup.file,
+      "# Coldcard Multisig setup file\n#\nName: Family Vault\nPolicy: 2 of 2\nFormat: P2WSH\n\n\
+       Derivation: m/84'/1'/6'\nDB7D25B5: tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe\n\n\
+       Derivation: m/84'/1'/5'\n66A0C105: tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R\n"
+    );
+
+    let nested = MULTISIG_DESC.replace("/84'/1'/5'", "/84'/1'/6'").replacen("wsh(", "sh(wsh(", 1) + ")";
+    let setup = multisig_setup("Vault", &nested).unwrap();
+    assert!(setup.file.contains("Derivation: m/84'/1'/6'\nFormat: P2SH-P2WSH\n\nDB7D25B5: "));
+
+    assert!(multisig_setup("A name that is far too long", MULTISIG_DESC).is_err());
+    assert!(multisig_setup("Vault", &MULTISIG_DESC.replace("sortedmulti", "multi")).is_err());
+    assert!(multisig_setup("Vault", &MULTISIG_DESC.replace("/0/*", "/1/*")).is_err());
+  }
+}
@@ -0,0 +1,223 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+use serde::{Deserialize, Serialize};
+
+use bdk::descriptor::checksum::get_checksum;
+use bdk::miniscript::descriptor::{Descriptor, DescriptorPublicKey, DescriptorSecretKey, ShInner};
+use bdk::miniscript::ForEachKey;
+
+use bitcoin::network::constants::Network;
+use bitcoin::secp256k1::Secp256k1;
+
+use crate::e::{ErrorKind, S5Error};
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DescriptorChecksum {
+  pub descriptor: String,
+  pub checksum: String,
+}
+
+impl DescriptorChecksum {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// fingerprint and path are None for keys without an origin.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KeyOrigin {
+  pub fingerprint: Option<String>,
+  pub path: Option<String>,
+  pub is_private: bool,
+}
+
+/// FFI Output
+/// network is None when the descriptor only has single keys, which are valid on any network.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DescriptorInfo {
+  pub descriptor: String,
+  pub checksum: String,
+  pub network: Option<String>,
+  pub script_type: String,
+  pub key_origins: Vec<KeyOrigin>,
+  pub has_private_keys: bool,
+  pub is_ranged: bool,
+}
+
+impl DescriptorInfo {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Removes the checksum of a descriptor after checking it. Descriptors without a checksum are returned as they are.
+pub fn strip_checksum(descriptor: &str) -> Result<&str, S5Error> {
+  match descriptor.split_once('#') {
+    Some((body, checksum)) => match get_checksum(body) {
+      Ok(expected) if expected == checksum => Ok(body),
+      _ => Err(S5Error::new(ErrorKind::Input, "Invalid Descriptor Checksum.")),
+    },
+    None => Ok(descriptor),
+  }
+}
+
+/// Computes the checksum of a descriptor, replacing any checksum it already has. The descriptor is not parsed.
+pub fn checksum(descriptor: &str) -> Result<DescriptorChecksum, S5Error> {
+  let body = descriptor.trim().split('#').next().unwrap_or_default();
+  match get_checksum(body) {
+    Ok(checksum) => Ok(DescriptorChecksum {
+      descriptor: format!("{}#{}", body, checksum),
+      checksum,
+    }),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "Invalid character in descriptor.")),
+  }
+}
+
+fn script_type(descriptor: &Descriptor<DescriptorPublicKey>) -> &'static str {
+  match descriptor {
+    Descriptor::Bare(_) => "bare",
+    Descriptor::Pkh(_) => "pkh",
+    Descriptor::Wpkh(_) => "wpkh",
+    Descriptor::Wsh(_) => "wsh",
+    Descriptor::Sh(sh) => match sh.as_inner() {
+      ShInner::Wsh(_) => "sh-wsh",
+      ShInner::Wpkh(_) => "sh-wpkh",
+      _ => "sh",
+    },
+  }
+}
+
+/// Parses a descriptor with miniscript, checking its checksum if it has one, and describes it.
+pub fn validate(descriptor: &str) -> Result<DescriptorInfo, S5Error> {
+  let body = strip_checksum(descriptor.trim())?;
+  let secp = Secp256k1::new();
+  let (public_desc, key_map) = match Descriptor::<DescriptorPublicKey>::parse_descriptor(&secp, body) {
+    Ok(result) => result,
+    Err(e) => return Err(S5Error::new(ErrorKind::Input, &format!("Invalid Descriptor: {}", e))),
+  };
+
+  let mut networks = vec![];
+  let mut key_origins = vec![];
+  public_desc.for_each_key(|key| {
+    let key = key.as_key();
+    let origin = match key {
+      DescriptorPublicKey::XPub(xpub) => {
+        networks.push(xpub.xkey.network);
+        &xpub.origin
+      }
+      DescriptorPublicKey::SinglePub(single) => &single.origin,
+    };
+    match key_map.get(key) {
+      Some(DescriptorSecretKey::XPrv(xprv)) => networks.push(xprv.xkey.network),
+      Some(DescriptorSecretKey::SinglePriv(single)) => networks.push(single.key.network),
+      None => (),
+    }
+    key_origins.push(KeyOrigin {
+      fingerprint: origin.as_ref().map(|(fingerprint, _)| fingerprint.to_string()),
+      path: origin.as_ref().map(|(_, path)| path.to_string()),
+      is_private: key_map.contains_key(key),
+    });
+    true
+  });
+  let network = match networks.first() {
+    Some(first) if networks.iter().any(|network| network != first) => {
+      return Err(S5Error::new(ErrorKind::Input, "Descriptor mixes keys for different networks."))
+    }
+    Some(Network::Bitcoin) => Some("main".to_string()),
+    Some(_) => Some("test".to_string()),
+    None => None,
+  };
+
+  let checksum = checksum(body)?;
+  Ok(DescriptorInfo {
+    descriptor: checksum.descriptor,
+    checksum: checksum.checksum,
+    network,
+    script_type: script_type(&public_desc).to_string(),
+    key_origins,
+    has_private_keys: !key_map.is_empty(),
+    is_ranged: public_desc.is_deriveable(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DEPOSIT_DESC: &str = "wpkh([db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)";
+
+  #[test]
+  fn test_descriptor_checksum() {
+    // Bitcoin Core descriptors.md example
+    let desc = "sh(multi(2,[00000000/111'/222]xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc,xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L/0))#ggrsrxfy";
+    assert_eq!(checksum(desc).unwrap().checksum, "ggrsrxfy");
+    assert_eq!(strip_checksum(desc).unwrap(), desc.split('#').next().unwrap());
+    assert_eq!(strip_checksum(DEPOSIT_DESC).unwrap(), DEPOSIT_DESC);
+    assert_eq!(
+      strip_checksum(&desc.replace("#ggrsrxfy", "#ggrsrxfx")).err().unwrap().message,
+      "Invalid Descriptor Checksum."
+    );
+
+    let checksummed = checksum(DEPOSIT_DESC).unwrap();
+    assert_eq!(checksummed.descriptor, format!("{}#{}", DEPOSIT_DESC, checksummed.checksum));
+    assert_eq!(checksum(&checksummed.descriptor).unwrap().descriptor, checksummed.descriptor);
+  }
+
+  #[test]
+  fn test_validate_descriptor() {
+    let info = validate(DEPOSIT_DESC).unwrap();
+    assert_eq!(info.network, Some("test".to_string()));
+    assert_eq!(info.script_type, "wpkh");
+    assert!(info.is_ranged);
+    assert!(!info.has_private_keys);
+    assert_eq!(
+      info.key_origins,
+      vec![KeyOrigin {
+        fingerprint: Some("db7d25b5".to_string()),
+        path: Some("m/84'/1'/6'".to_string()),
+        is_private: false,
+      }]
+    );
+    assert!(validate(&info.descriptor).is_ok());
+
+    let private = DEPOSIT_DESC.replace(
+      "tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe",
+      "tprv8fWev2sCuSkVWYoNUUSEuqLkmmfiZaVtgxosS5jRE9fw5ejL2odsajv1QyiLrPri3ppgyta6dsFaoDVCF4ZdEAR6qqY4tnaosujsPzLxB49",
+    );
+    let info = validate(&private).unwrap();
+    assert!(info.has_private_keys);
+    assert!(info.key_origins[0].is_private);
+
+    let desc = "sh(wsh(multi(1,03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd,[66a0c105/84'/1'/5']tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R/0/7)))";
+    let info = validate(desc).unwrap();
+    assert_eq!(info.script_type, "sh-wsh");
+    assert!(!info.is_ranged);
+    assert_eq!(info.key_origins[0].fingerprint, None);
+    assert_eq!(info.key_origins[1].path, Some("m/84'/1'/5'".to_string()));
+
+    let mixed = "wsh(multi(1,xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V/0/*,tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R/0/*))";
+    assert_eq!(validate(mixed).err().unwrap().message, "Descriptor mixes keys for different networks.");
+    assert!(validate("wpkh(notakey)").err().unwrap().message.starts_with("Invalid Descriptor"));
+    assert!(validate(&format!("{}#aaaaaaaa", DEPOSIT_DESC)).is_err());
+  }
+}
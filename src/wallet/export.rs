@@ -0,0 +1,674 @@
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use bdk::miniscript::descriptor::{
+  Descriptor, DescriptorPublicKey, ShInner, SortedMultiVec, Wildcard, WshInner,
+};
+use bdk::miniscript::ScriptContext;
+
+use bitcoin::network::constants::Network;
+use bitcoin::util::base58;
+use bitcoin::util::bip32::{DerivationPath, ExtendedPubKey, Fingerprint};
+
+use crate::config;
+use crate::e::{ErrorKind, S5Error};
+use crate::wallet::address;
+use crate::wallet::descriptor;
+
+/// Last index of the range Bitcoin Core keeps derived for each exported descriptor.
+pub const CORE_RANGE_END: u32 = 999;
+/// Wallet file version Electrum upgrades from on first open.
+const ELECTRUM_SEED_VERSION: u32 = 17;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WalletFormat {
+  Core,
+  Specter,
+  Electrum,
+}
+
+impl FromStr for WalletFormat {
+  type Err = S5Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "core" => Ok(WalletFormat::Core),
+      "specter" | "sparrow" => Ok(WalletFormat::Specter),
+      "electrum" => Ok(WalletFormat::Electrum),
+      _ => Err(S5Error::new(ErrorKind::Input, "Invalid Wallet Format.")),
+    }
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WalletExport {
+  pub format: WalletFormat,
+  pub data: String,
+}
+
+impl WalletExport {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportedWallet {
+  pub format: WalletFormat,
+  pub name: Option<String>,
+  pub deposit_desc: String,
+  pub change_desc: String,
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportedWallets {
+  pub wallets: Vec<ImportedWallet>,
+}
+
+impl ImportedWallets {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Script types of the single signature and sortedmulti wallets other apps share.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum ScriptType {
+  P2pkh,
+  P2shP2wpkh,
+  P2wpkh,
+  P2sh,
+  P2shP2wsh,
+  P2wsh,
+}
+
+const SCRIPT_TYPES: [ScriptType; 6] = [
+  ScriptType::P2pkh,
+  ScriptType::P2shP2wpkh,
+  ScriptType::P2wpkh,
+  ScriptType::P2sh,
+  ScriptType::P2shP2wsh,
+  ScriptType::P2wsh,
+];
+
+impl ScriptType {
+  pub fn is_multisig(self) -> bool {
+    matches!(self, ScriptType::P2sh | ScriptType::P2shP2wsh | ScriptType::P2wsh)
+  }
+
+  /// SLIP132 version bytes (xpub, ypub, zpub, Ypub, Zpub and testnet variants) Electrum uses for the script type.
+  fn slip132_version(self, network: Network) -> [u8; 4] {
+    let mainnet = network == Network::Bitcoin;
+    match (self, mainnet) {
+      (ScriptType::P2pkh | ScriptType::P2sh, true) => [0x04, 0x88, 0xb2, 0x1e],
+      (ScriptType::P2pkh | ScriptType::P2sh, false) => [0x04, 0x35, 0x87, 0xcf],
+      (ScriptType::P2shP2wpkh, true) => [0x04, 0x9d, 0x7c, 0xb2],
+      (ScriptType::P2shP2wpkh, false) => [0x04, 0x4a, 0x52, 0x62],
+      (ScriptType::P2wpkh, true) => [0x04, 0xb2, 0x47, 0x46],
+      (ScriptType::P2wpkh, false) => [0x04, 0x5f, 0x1c, 0xf6],
+      (ScriptType::P2shP2wsh, true) => [0x02, 0x95, 0xb4, 0x3f],
+      (ScriptType::P2shP2wsh, false) => [0x02, 0x42, 0x89, 0xef],
+      (ScriptType::P2wsh, true) => [0x02, 0xaa, 0x7e, 0xd3],
+      (ScriptType::P2wsh, false) => [0x02, 0x57, 0x54, 0x83],
+    }
+  }
+}
+
+fn slip132_encode(xpub: &ExtendedPubKey, script_type: ScriptType) -> String {
+  let mut data = xpub.encode();
+  data[0..4].copy_from_slice(&script_type.slip132_version(xpub.network));
+  base58::check_encode_slice(&data)
+}
+
+/// Reads a SLIP132 extended public key, returning it as an xpub or tpub with the script type it was marked with.
+fn slip132_decode(key: &str, multisig: bool) -> Result<(ExtendedPubKey, ScriptType), S5Error> {
+  let invalid = || S5Error::new(ErrorKind::Key, "Invalid Extended Public Key.");
+  let mut data = base58::from_check(key).map_err(|_| invalid())?;
+  if data.len() != 78 {
+    return Err(invalid());
+  }
+  for network in [Network::Bitcoin, Network::Testnet] {
+    for script_type in SCRIPT_TYPES.iter().filter(|script_type| script_type.is_multisig() == multisig) {
+      if data[0..4] == script_type.slip132_version(network) {
+        data[0..4].copy_from_slice(&ScriptType::P2pkh.slip132_version(network));
+        let xpub = ExtendedPubKey::decode(&data).map_err(|_| invalid())?;
+        return Ok((xpub, *script_type));
+      }
+    }
+  }
+  Err(invalid())
+}
+
+/// An account key of a wallet, with its origin when known.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Cosigner {
+  pub origin: Option<(Fingerprint, DerivationPath)>,
+  pub xpub: ExtendedPubKey,
+}
+
+impl Cosigner {
+  /// Receive key expression, eg. [db7d25b5/84'/1'/6']tpub.../0/*
+  fn key(&self) -> String {
+    match &self.origin {
+      Some((fingerprint, path)) => {
+        format!("[{}{}]{}/0/*", fingerprint, &path.to_string()[1..], self.xpub)
+      }
+      None => format!("{}/0/*", self.xpub),
+    }
+  }
+}
+
+/// Keys of a single signature or sortedmulti wallet. threshold is 1 for single signature wallets.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AccountKeys {
+  pub script_type: ScriptType,
+  pub threshold: usize,
+  pub cosigners: Vec<Cosigner>,
+}
+
+impl AccountKeys {
+  pub fn deposit_desc(&self) -> String {
+    let keys: Vec<String> = self.cosigners.iter().map(Cosigner::key).collect();
+    let multi = format!("sortedmulti({},{})", self.threshold, keys.join(","));
+    match self.script_type {
+      ScriptType::P2pkh => format!("pkh({})", keys[0]),
+      ScriptType::P2shP2wpkh => format!("sh(wpkh({}))", keys[0]),
+      ScriptType::P2wpkh => format!("wpkh({})", keys[0]),
+      ScriptType::P2sh => format!("sh({})", multi),
+      ScriptType::P2shP2wsh => format!("sh(wsh({}))", multi),
+      ScriptType::P2wsh => format!("wsh({})", multi),
+    }
+  }
+}
+
+fn cosigner(key: &DescriptorPublicKey) -> Result<Cosigner, S5Error> {
+  match key {
+    DescriptorPublicKey::XPub(xkey)
+      if xkey.wildcard == Wildcard::Unhardened && xkey.derivation_path.to_string() == "m/0" =>
+    {
+      Ok(Cosigner {
+        origin: xkey.origin.clone(),
+        xpub: xkey.xkey,
+      })
+    }
+    _ => Err(S5Error::new(
+      ErrorKind::Input,
+      "Keys must be extended public keys with a /0/* receive path.",
+    )),
+  }
+}
+
+fn sorted_multi<Ctx: ScriptContext>(
+  script_type: ScriptType,
+  multi: &SortedMultiVec<DescriptorPublicKey, Ctx>,
+) -> Result<AccountKeys, S5Error> {
+  Ok(AccountKeys {
+    script_type,
+    threshold: multi.k,
+    cosigners: multi.pks.iter().map(cosigner).collect::<Result<Vec<Cosigner>, S5Error>>()?,
+  })
+}
+
+/// Reads the keys of a pkh, sh(wpkh), wpkh or sortedmulti deposit descriptor.
+pub(crate) fn account_keys(deposit_desc: &str) -> Result<AccountKeys, S5Error> {
+  let unsupported = || {
+    S5Error::new(
+      ErrorKind::Input,
+      "Only single signature and sortedmulti descriptors are supported.",
+    )
+  };
+  let single = |script_type: ScriptType, key: &DescriptorPublicKey| -> Result<AccountKeys, S5Error> {
+    Ok(AccountKeys {
+      script_type,
+      threshold: 1,
+      cosigners: vec![cosigner(key)?],
+    })
+  };
+  match address::public_descriptor(deposit_desc)? {
+    Descriptor::Pkh(pkh) => single(ScriptType::P2pkh, pkh.as_inner()),
+    Descriptor::Wpkh(wpkh) => single(ScriptType::P2wpkh, wpkh.as_inner()),
+    Descriptor::Wsh(wsh) => match wsh.as_inner() {
+      WshInner::SortedMulti(multi) => sorted_multi(ScriptType::P2wsh, multi),
+      _ => Err(unsupported()),
+    },
+    Descriptor::Sh(sh) => match sh.as_inner() {
+      ShInner::Wpkh(wpkh) => single(ScriptType::P2shP2wpkh, wpkh.as_inner()),
+      ShInner::Wsh(wsh) => match wsh.as_inner() {
+        WshInner::SortedMulti(multi) => sorted_multi(ScriptType::P2shP2wsh, multi),
+        _ => Err(unsupported()),
+      },
+      ShInner::SortedMulti(multi) => sorted_multi(ScriptType::P2sh, multi),
+      _ => Err(unsupported()),
+    },
+    _ => Err(unsupported()),
+  }
+}
+
+/// Checksummed public form of a descriptor. Exports never contain private keys.
+fn watch_only(desc: &str) -> Result<String, S5Error> {
+  Ok(address::public_descriptor(desc)?.to_string())
+}
+
+/// An entry of Bitcoin Core importdescriptors, also found in listdescriptors output.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CoreDescriptor {
+  desc: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  timestamp: Option<serde_json::Value>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  active: Option<bool>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  internal: Option<bool>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  range: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CoreDescriptorList {
+  wallet_name: Option<String>,
+  descriptors: Vec<CoreDescriptor>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SpecterWallet {
+  #[serde(default)]
+  label: String,
+  #[serde(default)]
+  blockheight: u32,
+  descriptor: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ElectrumKeystore {
+  #[serde(rename = "type")]
+  keystore_type: String,
+  xpub: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  derivation: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  root_fingerprint: Option<String>,
+}
+
+/// Electrum wallet file. Multisig keystores are stored as "x1/", "x2/"... and kept in cosigners with any other fields.
+#[derive(Serialize, Deserialize, Debug)]
+struct ElectrumWallet {
+  wallet_type: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  keystore: Option<ElectrumKeystore>,
+  #[serde(default)]
+  use_encryption: bool,
+  #[serde(default)]
+  seed_version: u32,
+  #[serde(flatten)]
+  cosigners: BTreeMap<String, serde_json::Value>,
+}
+
+fn to_core(desc: &str, timestamp: u64, internal: bool) -> Result<CoreDescriptor, S5Error> {
+  let ranged = address::public_descriptor(desc)?.is_deriveable();
+  Ok(CoreDescriptor {
+    desc: watch_only(desc)?,
+    timestamp: Some(timestamp.into()),
+    active: if ranged { Some(true) } else { None },
+    internal: Some(internal),
+    range: if ranged {
+      Some(vec![0, CORE_RANGE_END].into())
+    } else {
+      None
+    },
+  })
+}
+
+/// importdescriptors request for the deposit and change descriptors.
+fn export_core(deposit_desc: &str, timestamp: u64) -> Result<String, S5Error> {
+  let mut descriptors = vec![to_core(deposit_desc, timestamp, false)?];
+  let change_desc = config::change_descriptor(deposit_desc);
+  if change_desc != deposit_desc {
+    descriptors.push(to_core(&change_desc, timestamp, true)?);
+  }
+  match serde_json::to_string(&descriptors) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Internal, "Wallet-Export")),
+  }
+}
+
+fn export_specter(deposit_desc: &str, name: &str, blockheight: u32) -> Result<String, S5Error> {
+  let wallet = SpecterWallet {
+    label: name.to_string(),
+    blockheight,
+    descriptor: watch_only(deposit_desc)?,
+  };
+  match serde_json::to_string(&wallet) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Internal, "Wallet-Export")),
+  }
+}
+
+fn export_electrum(deposit_desc: &str) -> Result<String, S5Error> {
+  let keys = account_keys(deposit_desc)?;
+  let mut keystores: Vec<ElectrumKeystore> = keys
+    .cosigners
+    .iter()
+    .map(|cosigner| ElectrumKeystore {
+      keystore_type: "bip32".to_string(),
+      xpub: slip132_encode(&cosigner.xpub, keys.script_type),
+      derivation: cosigner.origin.as_ref().map(|(_, path)| path.to_string()),
+      root_fingerprint: cosigner.origin.as_ref().map(|(fingerprint, _)| fingerprint.to_string()),
+    })
+    .collect();
+  let wallet = if keys.script_type.is_multisig() {
+    let mut cosigners = BTreeMap::new();
+    for (index, keystore) in keystores.iter().enumerate() {
+      match serde_json::to_value(keystore) {
+        Ok(value) => cosigners.insert(format!("x{}/", index + 1), value),
+        Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Export")),
+      };
+    }
+    ElectrumWallet {
+      wallet_type: format!("{}of{}", keys.threshold, keys.cosigners.len()),
+      keystore: None,
+      use_encryption: false,
+      seed_version: ELECTRUM_SEED_VERSION,
+      cosigners,
+    }
+  } else {
+    ElectrumWallet {
+      wallet_type: "standard".to_string(),
+      keystore: keystores.pop(),
+      use_encryption: false,
+      seed_version: ELECTRUM_SEED_VERSION,
+      cosigners: BTreeMap::new(),
+    }
+  };
+  match serde_json::to_string(&wallet) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Internal, "Wallet-Export")),
+  }
+}
+
+/// Exports the deposit and change descriptors as a watch-only wallet for another app.
+/// timestamp (unix seconds) is the Core rescan start and blockheight the Specter rescan start. 0 rescans everything.
+/// name is only used by Specter and Sparrow.
+pub fn export(
+  deposit_desc: &str,
+  format: WalletFormat,
+  name: &str,
+  timestamp: u64,
+  blockheight: u32,
+) -> Result<WalletExport, S5Error> {
+  let data = match format {
+    WalletFormat::Core => export_core(deposit_desc, timestamp)?,
+    WalletFormat::Specter => export_specter(deposit_desc, name, blockheight)?,
+    WalletFormat::Electrum => export_electrum(deposit_desc)?,
+  };
+  Ok(WalletExport { format, data })
+}
+
+/// Turns a descriptor from another app into a Stackmate deposit descriptor: checksum checked and removed,
+/// and a <0;1> multipath receive/change step written as /0.
+fn deposit_descriptor(desc: &str) -> Result<String, S5Error> {
+  let deposit_desc = descriptor::strip_checksum(desc.trim())?.replace("/<0;1>/", "/0/");
+  address::public_descriptor(&deposit_desc)?;
+  Ok(deposit_desc)
+}
+
+fn imported(format: WalletFormat, name: Option<String>, deposit_desc: String) -> ImportedWallet {
+  ImportedWallet {
+    format,
+    name,
+    change_desc: config::change_descriptor(&deposit_desc),
+    deposit_desc,
+  }
+}
+
+/// Active receive descriptors of a Core wallet. Descriptors this library cannot use (eg. tr) are skipped.
+fn import_core(name: Option<String>, descriptors: Vec<CoreDescriptor>) -> Result<Vec<ImportedWallet>, S5Error> {
+  let mut deposit_descs = vec![];
+  for descriptor in descriptors {
+    if descriptor.internal.unwrap_or(false) || !descriptor.active.unwrap_or(true) {
+      continue;
+    }
+    descriptor::strip_checksum(&descriptor.desc)?;
+    if let Ok(deposit_desc) = deposit_descriptor(&descriptor.desc) {
+      deposit_descs.push(deposit_desc);
+    }
+  }
+  // importdescriptors files may leave out internal; their change descriptors pair with a deposit descriptor.
+  let change_descs: Vec<String> = deposit_descs
+    .iter()
+    .map(|desc| config::change_descriptor(desc))
+    .filter(|change_desc| !deposit_descs.contains(change_desc))
+    .collect();
+  let wallets: Vec<ImportedWallet> = deposit_descs
+    .into_iter()
+    .filter(|desc| !change_descs.contains(desc))
+    .map(|desc| imported(WalletFormat::Core, name.clone(), desc))
+    .collect();
+  if wallets.is_empty() {
+    return Err(S5Error::new(ErrorKind::Input, "No supported descriptors in wallet file."));
+  }
+  Ok(wallets)
+}
+
+fn import_electrum(wallet: ElectrumWallet) -> Result<ImportedWallet, S5Error> {
+  let invalid = || S5Error::new(ErrorKind::Input, "Invalid Electrum Wallet.");
+  let multisig = wallet.wallet_type != "standard";
+  let (threshold, keystores) = if multisig {
+    let (threshold, count) = match wallet.wallet_type.split_once("of") {
+      Some((threshold, count)) => (
+        threshold.parse::<usize>().map_err(|_| invalid())?,
+        count.parse::<usize>().map_err(|_| invalid())?,
+      ),
+      None => return Err(invalid()),
+    };
+    let mut keystores = vec![];
+    for index in 1..=count {
+      let keystore = wallet.cosigners.get(&format!("x{}/", index)).ok_or_else(invalid)?;
+      keystores.push(serde_json::from_value::<ElectrumKeystore>(keystore.clone()).map_err(|_| invalid())?);
+    }
+    (threshold, keystores)
+  } else {
+    (1, vec![wallet.keystore.ok_or_else(invalid)?])
+  };
+  if keystores.is_empty() || threshold == 0 || threshold > keystores.len() {
+    return Err(invalid());
+  }
+
+  let mut script_type = None;
+  let mut cosigners = vec![];
+  for keystore in keystores {
+    let (xpub, key_script_type) = slip132_decode(&keystore.xpub, multisig)?;
+    if script_type.is_some() && script_type != Some(key_script_type) {
+      return Err(S5Error::new(ErrorKind::Input, "Electrum keystores have different script types."));
+    }
+    script_type = Some(key_script_type);
+    let origin = match (keystore.root_fingerprint, keystore.derivation) {
+      (Some(fingerprint), Some(path)) => Some((
+        Fingerprint::from_str(&fingerprint).map_err(|_| invalid())?,
+        DerivationPath::from_str(&path).map_err(|_| invalid())?,
+      )),
+      _ => None,
+    };
+    cosigners.push(Cosigner { origin, xpub });
+  }
+  let keys = AccountKeys {
+    script_type: script_type.ok_or_else(invalid)?,
+    threshold,
+    cosigners,
+  };
+  Ok(imported(WalletFormat::Electrum, None, keys.deposit_desc()))
+}
+
+/// Reads a wallet exported by another app: Core importdescriptors or listdescriptors JSON,
+/// Specter or Sparrow wallet JSON, or an unencrypted Electrum wallet file.
+pub fn import(data: &str) -> Result<ImportedWallets, S5Error> {
+  let unrecognized = || S5Error::new(ErrorKind::Input, "Unrecognized Wallet File.");
+  let value: serde_json::Value = serde_json::from_str(data.trim()).map_err(|_| unrecognized())?;
+  let wallets = if value.is_array() {
+    let descriptors: Vec<CoreDescriptor> = serde_json::from_value(value).map_err(|_| unrecognized())?;
+    import_core(None, descriptors)?
+  } else if value.get("descriptors").is_some() {
+    let list: CoreDescriptorList = serde_json::from_value(value).map_err(|_| unrecognized())?;
+    import_core(list.wallet_name, list.descriptors)?
+  } else if value.get("descriptor").is_some() {
+    let wallet: SpecterWallet = serde_json::from_value(value).map_err(|_| unrecognized())?;
+    let name = if wallet.label.is_empty() {
+      None
+    } else {
+      Some(wallet.label)
+    };
+    vec![imported(WalletFormat::Specter, name, deposit_descriptor(&wallet.descriptor)?)]
+  } else if value.get("wallet_type").is_some() {
+    let wallet: ElectrumWallet = serde_json::from_value(value).map_err(|_| unrecognized())?;
+    vec![import_electrum(wallet)?]
+  } else {
+    return Err(unrecognized());
+  };
+  Ok(ImportedWallets { wallets })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bdk::descriptor::checksum::get_checksum;
+
+  const DEPOSIT_DESC: &str = "wpkh([db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)";
+  const MULTISIG_DESC: &str = "wsh(sortedmulti(2,[db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*,[66a0c105/84'/1'/5']tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R/0/*))";
+  // BIP84 test vector account 0 (abandon x11 about)
+  const BIP84_XPUB: &str = "xpub6CatWdiZiodmUeTDp8LT5or8nmbKNcuyvz7WyksVFkKB4RHwCD3XyuvPEbvqAQY3rAPshWcMLoP2fMFMKHPJ4ZeZXYVUhLv1VMrjPC7PW6V";
+  const BIP84_ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+  fn round_trip(deposit_desc: &str, format: WalletFormat) -> ImportedWallet {
+    let exported = export(deposit_desc, format, "Savings", 1_600_000_000, 2_100_000).unwrap();
+    let mut wallets = import(&exported.data).unwrap().wallets;
+    assert_eq!(wallets.len(), 1);
+    wallets.remove(0)
+  }
+
+  #[test]
+  fn test_export_core() {
+    let exported = export(DEPOSIT_DESC, WalletFormat::Core, "", 1_600_000_000, 0).unwrap();
+    let descriptors: Vec<CoreDescriptor> = serde_json::from_str(&exported.data).unwrap();
+    assert_eq!(descriptors.len(), 2);
+    assert_eq!(descriptors[0].internal, Some(false));
+    assert_eq!(descriptors[1].internal, Some(true));
+    assert_eq!(descriptors[1].timestamp, Some(1_600_000_000.into()));
+    assert_eq!(descriptors[1].range, Some(vec![0, CORE_RANGE_END].into()));
+    let (body, checksum) = descriptors[0].desc.split_once('#').unwrap();
+    assert_eq!(body, DEPOSIT_DESC);
+    assert_eq!(get_checksum(body).unwrap(), checksum);
+    assert!(descriptors[1].desc.contains("/1/*)#"));
+
+    let wallet = round_trip(DEPOSIT_DESC, WalletFormat::Core);
+    assert_eq!(wallet.deposit_desc, DEPOSIT_DESC);
+    assert_eq!(wallet.change_desc, config::change_descriptor(DEPOSIT_DESC));
+
+    let private = DEPOSIT_DESC.replace(
+      "tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe",
+      "tprv8fWev2sCuSkVWYoNUUSEuqLkmmfiZaVtgxosS5jRE9fw5ejL2odsajv1QyiLrPri3ppgyta6dsFaoDVCF4ZdEAR6qqY4tnaosujsPzLxB49",
+    );
+    assert_eq!(export(&private, WalletFormat::Core, "", 0, 0).unwrap().data, export(DEPOSIT_DESC, WalletFormat::Core, "", 0, 0).unwrap().data);
+  }
+
+  #[test]
+  fn test_import_core_list() {
+    let checksummed = |desc: &str| format!("{}#{}", desc, get_checksum(desc).unwrap());
+    let deposit = DEPOSIT_DESC.replace('\'', "h");
+    let change = config::change_descriptor(&deposit);
+    let list = format!(
+      r#"{{"wallet_name":"core","descriptors":[
+        {{"desc":"{}","timestamp":1600000000,"active":true,"internal":false,"range":[0,999],"next":3}},
+        {{"desc":"{}","timestamp":1600000000,"active":true,"internal":true,"range":[0,999],"next":0}},
+        {{"desc":"{}","timestamp":1600000000,"active":true,"internal":false,"range":[0,999],"next":0}},
+        {{"desc":"{}","timestamp":1500000000,"active":false,"internal":false,"range":[0,999],"next":0}}
+      ]}}"#,
+      checksummed(&deposit),
+      checksummed(&change),
+      checksummed("tr([db7d25b5/86h/1h/0h]tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)"),
+      checksummed(MULTISIG_DESC),
+    );
+    let wallets = import(&list).unwrap().wallets;
+    assert_eq!(wallets.len(), 1);
+    assert_eq!(wallets[0].format, WalletFormat::Core);
+    assert_eq!(wallets[0].name, Some("core".to_string()));
+    assert_eq!(wallets[0].deposit_desc, deposit);
+    assert_eq!(wallets[0].change_desc, change);
+
+    let tampered = list.replacen("#", "#x", 1);
+    assert_eq!(import(&tampered).err().unwrap().message, "Invalid Descriptor Checksum.");
+    assert_eq!(import(r#"{"something":"else"}"#).err().unwrap().message, "Unrecognized Wallet File.");
+  }
+
+  #[test]
+  fn test_export_specter() {
+    let exported = export(MULTISIG_DESC, WalletFormat::Specter, "Savings", 0, 2_100_000).unwrap();
+    let wallet: SpecterWallet = serde_json::from_str(&exported.data).unwrap();
+    assert_eq!(wallet.label, "Savings");
+    assert_eq!(wallet.blockheight, 2_100_000);
+    assert!(wallet.descriptor.starts_with(MULTISIG_DESC));
+
+    let wallet = round_trip(MULTISIG_DESC, WalletFormat::Specter);
+    assert_eq!(wallet.name, Some("Savings".to_string()));
+    assert_eq!(wallet.deposit_desc, MULTISIG_DESC);
+
+    let sparrow = format!(r#"{{"label":"Sparrow","blockheight":0,"descriptor":"{}"}}"#, DEPOSIT_DESC.replace("/0/*", "/<0;1>/*"));
+    assert_eq!(import(&sparrow).unwrap().wallets[0].deposit_desc, DEPOSIT_DESC);
+    assert_eq!(WalletFormat::from_str("sparrow").unwrap(), WalletFormat::Specter);
+  }
+
+  #[test]
+  fn test_export_electrum() {
+    let exported = export(DEPOSIT_DESC, WalletFormat::Electrum, "", 0, 0).unwrap();
+    let wallet: ElectrumWallet = serde_json::from_str(&exported.data).unwrap();
+    assert_eq!(wallet.wallet_type, "standard");
+    let keystore = wallet.keystore.unwrap();
+    assert!(keystore.xpub.starts_with("vpub"));
+    assert_eq!(keystore.derivation, Some("m/84'/1'/6'".to_string()));
+    assert_eq!(keystore.root_fingerprint, Some("db7d25b5".to_string()));
+    assert_eq!(round_trip(DEPOSIT_DESC, WalletFormat::Electrum).deposit_desc, DEPOSIT_DESC);
+
+    let exported = export(MULTISIG_DESC, WalletFormat::Electrum, "", 0, 0).unwrap();
+    let wallet: ElectrumWallet = serde_json::from_str(&exported.data).unwrap();
+    assert_eq!(wallet.wallet_type, "2of2");
+    assert!(wallet.cosigners["x2/"]["xpub"].as_str().unwrap().starts_with("Vpub"));
+    assert_eq!(round_trip(MULTISIG_DESC, WalletFormat::Electrum).deposit_desc, MULTISIG_DESC);
+
+    let electrum = format!(
+      r#"{{"addr_history":{{}},"keystore":{{"type":"bip32","xpub":"{}","xprv":null,"derivation":"m/84h/0h/0h","root_fingerprint":"73c5da0a","label":""}},"seed_version":52,"use_encryption":false,"wallet_type":"standard"}}"#,
+      BIP84_ZPUB
+    );
+    let wallet = import(&electrum).unwrap().wallets.remove(0);
+    assert_eq!(wallet.deposit_desc, format!("wpkh([73c5da0a/84'/0'/0']{}/0/*)", BIP84_XPUB));
+    assert_eq!(
+      address::derive(&wallet.deposit_desc, false, 0).unwrap().address,
+      "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+    );
+    assert_eq!(slip132_encode(&ExtendedPubKey::from_str(BIP84_XPUB).unwrap(), ScriptType::P2wpkh), BIP84_ZPUB);
+
+    assert!(export("wsh(multi(2,[db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*,[66a0c105/84'/1'/5']tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R/0/*))", WalletFormat::Electrum, "", 0, 0).is_err());
+  }
+}
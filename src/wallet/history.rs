@@ -3,15 +3,23 @@ use std::os::raw::c_char;
 
 use serde::{Deserialize, Serialize};
 
-use bdk::blockchain::noop_progress;
+#[cfg(feature = "online")]
+use bdk::blockchain::{noop_progress, Blockchain};
+use bdk::database::BatchDatabase;
+#[cfg(feature = "online")]
 use bdk::database::MemoryDatabase;
-use bdk::TransactionDetails;
-use bdk::Wallet;
+use bdk::{KeychainKind, TransactionDetails, Wallet};
 
+use bitcoin::util::address::Address;
+
+#[cfg(feature = "online")]
 use crate::config::WalletConfig;
+#[cfg(feature = "online")]
 use crate::e::{ErrorKind, S5Error};
+use crate::wallet::labels::LabelType;
+use crate::wallet::store::WalletStore;
 
-/**
+/*
 *   "fees": 153,
    "height": 2062130,
    "received": 100000,
@@ -21,22 +29,70 @@ use crate::e::{ErrorKind, S5Error};
    "txid"
 */
 
+/// Confirmation status of a transaction.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+  Pending,
+  Confirmed,
+}
+
+/// Classification of a transaction from the perspective of the wallet.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TxDirection {
+  Incoming,
+  Outgoing,
+  SelfTransfer,
+  Consolidation,
+}
+
+/// value and address are None when the previous output is unknown to the wallet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TxInput {
+  pub previous_outpoint: String,
+  pub address: Option<String>,
+  pub value: Option<u64>,
+  pub sequence: u32,
+  pub is_mine: bool,
+}
+
+/// keychain is "external" or "internal" for outputs belonging to the wallet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TxOutput {
+  pub vout: u32,
+  pub address: String,
+  pub value: u64,
+  pub is_mine: bool,
+  pub keychain: Option<String>,
+}
+
 /// FFI Output
+/// height and timestamp are 0 while status is pending.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Transaction {
   pub timestamp: u64,
   pub height: u32,
+  pub status: TxStatus,
+  pub confirmations: u32,
   pub verified: bool,
   pub txid: String,
   pub received: u64,
   pub sent: u64,
   pub fee: u64,
+  pub vsize: usize,
+  pub fee_rate: f32,
+  pub rbf: bool,
+  pub direction: TxDirection,
+  pub inputs: Vec<TxInput>,
+  pub outputs: Vec<TxOutput>,
+  pub label: Option<String>,
 }
 
 /// FFI Output
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WalletHistory {
-  history: Vec<Transaction>,
+  pub history: Vec<Transaction>,
 }
 
 impl WalletHistory {
@@ -54,27 +110,131 @@ impl WalletHistory {
   }
 }
 
+/// A sweep of several wallet utxos into a single wallet output is a consolidation.
+pub fn classify(sent: u64, received: u64, input_count: usize, outputs: &[TxOutput]) -> TxDirection {
+  if sent == 0 {
+    TxDirection::Incoming
+  } else if outputs.iter().all(|output| output.is_mine) {
+    if input_count > 1 && outputs.len() == 1 {
+      TxDirection::Consolidation
+    } else {
+      TxDirection::SelfTransfer
+    }
+  } else if received > sent {
+    TxDirection::Incoming
+  } else {
+    TxDirection::Outgoing
+  }
+}
+
 impl Transaction {
-  pub fn from_txdetail(txdetail: TransactionDetails) -> Self {
+  /// txdetail must include the raw transaction for inputs and outputs to be populated.
+  pub fn from_txdetail<B, D: BatchDatabase>(
+    txdetail: TransactionDetails,
+    wallet: &Wallet<B, D>,
+    tip: u32,
+    store: &WalletStore,
+  ) -> Self {
+    let network = wallet.network();
+    let height = match txdetail.confirmation_time.clone() {
+      Some(time) => time.height,
+      None => 0,
+    };
+
+    let mut inputs: Vec<TxInput> = vec![];
+    let mut outputs: Vec<TxOutput> = vec![];
+    let mut vsize = 0;
+    let mut rbf = false;
+
+    if let Some(tx) = txdetail.transaction.clone() {
+      vsize = tx.get_weight().div_ceil(4);
+      rbf = tx.input.iter().any(|input| input.sequence < 0xffff_fffe);
+
+      for input in tx.input.iter() {
+        let previous_output = match wallet.database().get_raw_tx(&input.previous_output.txid) {
+          Ok(Some(previous_tx)) => previous_tx
+            .output
+            .get(input.previous_output.vout as usize)
+            .cloned(),
+          _ => None,
+        };
+        inputs.push(TxInput {
+          previous_outpoint: input.previous_output.to_string(),
+          address: previous_output
+            .clone()
+            .and_then(|txout| Address::from_script(&txout.script_pubkey, network))
+            .map(|address| address.to_string()),
+          value: previous_output.clone().map(|txout| txout.value),
+          sequence: input.sequence,
+          is_mine: match previous_output {
+            Some(txout) => wallet.is_mine(&txout.script_pubkey).unwrap_or(false),
+            None => false,
+          },
+        });
+      }
+
+      for (vout, output) in tx.output.iter().enumerate() {
+        let keychain = match wallet
+          .database()
+          .get_path_from_script_pubkey(&output.script_pubkey)
+        {
+          Ok(Some((KeychainKind::External, _))) => Some("external".to_string()),
+          Ok(Some((KeychainKind::Internal, _))) => Some("internal".to_string()),
+          _ => None,
+        };
+        outputs.push(TxOutput {
+          vout: vout as u32,
+          address: match Address::from_script(&output.script_pubkey, network) {
+            Some(address) => address.to_string(),
+            None => "None".to_string(),
+          },
+          value: output.value,
+          is_mine: keychain.is_some(),
+          keychain,
+        });
+      }
+    }
+
+    let fee = txdetail.fee.unwrap_or(0);
+
     Transaction {
       timestamp: match txdetail.confirmation_time.clone() {
         Some(time) => time.timestamp,
         None => 0,
       },
-      height: match txdetail.confirmation_time {
-        Some(time) => time.height,
-        None => 0,
+      height,
+      status: if txdetail.confirmation_time.is_some() {
+        TxStatus::Confirmed
+      } else {
+        TxStatus::Pending
+      },
+      confirmations: if txdetail.confirmation_time.is_some() && tip >= height {
+        tip - height + 1
+      } else {
+        0
       },
       verified: txdetail.verified,
       txid: txdetail.txid.to_string(),
       received: txdetail.received,
       sent: txdetail.sent,
-      fee: txdetail.fee.unwrap_or(0),
+      fee,
+      vsize,
+      fee_rate: if vsize > 0 {
+        fee as f32 / vsize as f32
+      } else {
+        0.0
+      },
+      rbf,
+      direction: classify(txdetail.sent, txdetail.received, inputs.len(), &outputs),
+      inputs,
+      outputs,
+      label: store.get_label(LabelType::Tx, &txdetail.txid.to_string()),
     }
   }
 }
 
-pub async fn sync_history(config: WalletConfig) -> Result<WalletHistory, S5Error> {
+#[cfg(feature = "online")]
+pub async fn sync_history(config: WalletConfig, store: &WalletStore) -> Result<WalletHistory, S5Error> {
   let wallet = match Wallet::new(
     &config.deposit_desc,
     Some(&config.change_desc),
@@ -94,20 +254,29 @@ pub async fn sync_history(config: WalletConfig) -> Result<WalletHistory, S5Error
     Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Sync")),
   };
 
-  match wallet.list_transactions(false) {
+  let tip = match wallet.client().get_height().await {
+    Ok(result) => result,
+    Err(e) => return Err(S5Error::new(ErrorKind::Network, &e.to_string())),
+  };
+
+  match wallet.list_transactions(true) {
     Ok(history) => Ok(WalletHistory {
       history: history
         .iter()
-        .map(|txdetail| Transaction::from_txdetail(txdetail.clone()))
+        .map(|txdetail| Transaction::from_txdetail(txdetail.clone(), &wallet, tip, store))
         .collect(),
     }),
     Err(e) => Err(S5Error::new(ErrorKind::Internal, &e.to_string())),
   }
 }
 
+/// FFI Output
+/// balance is the total of spendable and frozen.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WalletBalance {
   pub balance: u64,
+  pub spendable: u64,
+  pub frozen: u64,
 }
 impl WalletBalance {
   pub fn c_stringify(&self) -> *mut c_char {
@@ -124,7 +293,8 @@ impl WalletBalance {
   }
 }
 
-pub async fn sync_balance(config: WalletConfig) -> Result<WalletBalance, S5Error> {
+#[cfg(feature = "online")]
+pub async fn sync_balance(config: WalletConfig, store: &WalletStore) -> Result<WalletBalance, S5Error> {
   let wallet = match Wallet::new(
     &config.deposit_desc,
     Some(&config.change_desc),
@@ -144,9 +314,146 @@ pub async fn sync_balance(config: WalletConfig) -> Result<WalletBalance, S5Error
     Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Sync")),
   };
 
-  match wallet.get_balance() {
-    Ok(balance) => Ok(WalletBalance { balance }),
-    Err(e) => Err(S5Error::new(ErrorKind::Internal, &e.to_string())),
+  let utxos = match wallet.list_unspent() {
+    Ok(result) => result,
+    Err(e) => return Err(S5Error::new(ErrorKind::Internal, &e.to_string())),
+  };
+
+  let balance: u64 = utxos.iter().map(|utxo| utxo.txout.value).sum();
+  let frozen: u64 = utxos
+    .iter()
+    .filter(|utxo| store.is_frozen(&utxo.outpoint))
+    .map(|utxo| utxo.txout.value)
+    .sum();
+
+  Ok(WalletBalance {
+    balance,
+    spendable: balance - frozen,
+    frozen,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::str::FromStr;
+
+  use bdk::database::{BatchOperations, MemoryDatabase};
+  use bdk::wallet::AddressIndex::New;
+  use bdk::BlockTime;
+  use bitcoin::blockdata::script::Script;
+  use bitcoin::blockdata::transaction::{OutPoint, Transaction as BitcoinTx, TxIn, TxOut};
+  use bitcoin::network::constants::Network;
+
+  fn tx(inputs: Vec<(OutPoint, u32)>, outputs: Vec<(Script, u64)>) -> BitcoinTx {
+    BitcoinTx {
+      version: 2,
+      lock_time: 0,
+      input: inputs
+        .into_iter()
+        .map(|(previous_output, sequence)| TxIn {
+          previous_output,
+          script_sig: Script::new(),
+          sequence,
+          witness: vec![],
+        })
+        .collect(),
+      output: outputs
+        .into_iter()
+        .map(|(script_pubkey, value)| TxOut {
+          value,
+          script_pubkey,
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn test_history_records() {
+    let deposit_desc = "wpkh([db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)";
+    let change_desc = deposit_desc.replace("/0/*", "/1/*");
+    let foreign = Address::from_str("mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt").unwrap().script_pubkey();
+    let foreign_outpoint = OutPoint::from_str("f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:3").unwrap();
+
+    let probe = Wallet::new_offline(deposit_desc, Some(&change_desc), Network::Testnet, MemoryDatabase::default()).unwrap();
+    let ours = probe.get_address(New).unwrap().script_pubkey();
+
+    let incoming = tx(vec![(foreign_outpoint, 0xffff_ffff)], vec![(ours.clone(), 100_000)]);
+    let outgoing = tx(
+      vec![(OutPoint::new(incoming.txid(), 0), 0xffff_fffd)],
+      vec![(foreign, 50_000), (ours, 49_000)],
+    );
+
+    let mut database = MemoryDatabase::default();
+    database.set_raw_tx(&incoming).unwrap();
+    let wallet = Wallet::new_offline(deposit_desc, Some(&change_desc), Network::Testnet, database).unwrap();
+    wallet.get_address(New).unwrap();
+
+    let store = WalletStore::default();
+    let record = Transaction::from_txdetail(
+      TransactionDetails {
+        transaction: Some(incoming.clone()),
+        txid: incoming.txid(),
+        received: 100_000,
+        sent: 0,
+        fee: None,
+        confirmation_time: Some(BlockTime {
+          height: 100,
+          timestamp: 1_640_000_000,
+        }),
+        verified: false,
+      },
+      &wallet,
+      105,
+      &store,
+    );
+    assert_eq!(record.status, TxStatus::Confirmed);
+    assert_eq!(record.confirmations, 6);
+    assert_eq!(record.direction, TxDirection::Incoming);
+    assert!(!record.rbf);
+    assert_eq!(record.inputs[0].value, None);
+    assert!(record.outputs[0].is_mine);
+    assert_eq!(record.outputs[0].keychain, Some("external".to_string()));
+
+    let record = Transaction::from_txdetail(
+      TransactionDetails {
+        transaction: Some(outgoing.clone()),
+        txid: outgoing.txid(),
+        received: 49_000,
+        sent: 100_000,
+        fee: Some(1_000),
+        confirmation_time: None,
+        verified: false,
+      },
+      &wallet,
+      105,
+      &store,
+    );
+    assert_eq!(record.status, TxStatus::Pending);
+    assert_eq!(record.height, 0);
+    assert_eq!(record.confirmations, 0);
+    assert_eq!(record.direction, TxDirection::Outgoing);
+    assert!(record.rbf);
+    assert_eq!(record.inputs[0].value, Some(100_000));
+    assert!(record.inputs[0].is_mine);
+    assert!(!record.outputs[0].is_mine);
+    assert_eq!(record.vsize, outgoing.get_weight().div_ceil(4));
+    assert_eq!(record.fee_rate, 1_000.0 / record.vsize as f32);
+  }
+
+  #[test]
+  fn test_classify() {
+    let output = |is_mine: bool| TxOutput {
+      vout: 0,
+      address: "None".to_string(),
+      value: 1_000,
+      is_mine,
+      keychain: None,
+    };
+    assert_eq!(classify(0, 1_000, 1, &[output(true)]), TxDirection::Incoming);
+    assert_eq!(classify(3_000, 2_800, 3, &[output(true)]), TxDirection::Consolidation);
+    assert_eq!(classify(3_000, 2_800, 1, &[output(true), output(true)]), TxDirection::SelfTransfer);
+    assert_eq!(classify(3_000, 1_000, 1, &[output(false), output(true)]), TxDirection::Outgoing);
   }
 }
 
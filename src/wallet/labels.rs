@@ -0,0 +1,240 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use bitcoin::blockdata::transaction::OutPoint;
+use bitcoin::util::address::Address;
+use bitcoin::util::bip32::ExtendedPubKey;
+use bitcoin::{PublicKey, Txid};
+
+use crate::e::{ErrorKind, S5Error};
+use crate::wallet::store::WalletStore;
+
+/// BIP329 label types
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelType {
+  Tx,
+  Addr,
+  Pubkey,
+  Input,
+  Output,
+  Xpub,
+}
+
+impl FromStr for LabelType {
+  type Err = S5Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "tx" => Ok(LabelType::Tx),
+      "addr" => Ok(LabelType::Addr),
+      "pubkey" => Ok(LabelType::Pubkey),
+      "input" => Ok(LabelType::Input),
+      "output" => Ok(LabelType::Output),
+      "xpub" => Ok(LabelType::Xpub),
+      _ => Err(S5Error::new(ErrorKind::Input, "Invalid Label Type.")),
+    }
+  }
+}
+
+/// A single BIP329 record.
+/// spendable is only used with output labels and mirrors the frozen status of the utxo.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Label {
+  #[serde(rename = "type")]
+  pub label_type: LabelType,
+  #[serde(rename = "ref")]
+  pub reference: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub label: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub origin: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub spendable: Option<bool>,
+}
+
+impl Label {
+  /// Checks that ref is well formed for the label type.
+  pub fn validate(&self) -> Result<(), S5Error> {
+    let valid = match self.label_type {
+      LabelType::Tx => Txid::from_str(&self.reference).is_ok(),
+      LabelType::Addr => Address::from_str(&self.reference).is_ok(),
+      LabelType::Pubkey => PublicKey::from_str(&self.reference).is_ok(),
+      LabelType::Input | LabelType::Output => OutPoint::from_str(&self.reference).is_ok(),
+      LabelType::Xpub => ExtendedPubKey::from_str(&self.reference).is_ok(),
+    };
+    if valid {
+      Ok(())
+    } else {
+      Err(S5Error::new(ErrorKind::Input, "Invalid Label Reference."))
+    }
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WalletLabels {
+  pub count: usize,
+  pub bip329: String,
+}
+
+impl WalletLabels {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Parses BIP329 JSON Lines. Empty lines are ignored.
+pub fn from_jsonl(jsonl: &str) -> Result<Vec<Label>, S5Error> {
+  let mut labels = vec![];
+  for (index, line) in jsonl.lines().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let label: Label = match serde_json::from_str(line) {
+      Ok(result) => result,
+      Err(_) => {
+        return Err(S5Error::new(
+          ErrorKind::Input,
+          &format!("Invalid BIP329 record at line {}.", index + 1),
+        ))
+      }
+    };
+    if label.validate().is_err() {
+      return Err(S5Error::new(
+        ErrorKind::Input,
+        &format!("Invalid BIP329 ref at line {}.", index + 1),
+      ));
+    }
+    labels.push(label);
+  }
+  Ok(labels)
+}
+
+pub fn to_jsonl(labels: &[Label]) -> Result<String, S5Error> {
+  let mut lines = vec![];
+  for label in labels {
+    match serde_json::to_string(label) {
+      Ok(line) => lines.push(line),
+      Err(_) => return Err(S5Error::new(ErrorKind::Internal, "BIP329-Stringify")),
+    }
+  }
+  Ok(lines.join("\n"))
+}
+
+/// Merges BIP329 records into the wallet store. Imported records replace existing ones with the same type and ref.
+/// Output records with spendable set freeze or unfreeze the utxo.
+pub fn import(db_path: &str, deposit_desc: &str, jsonl: &str) -> Result<WalletLabels, S5Error> {
+  let labels = from_jsonl(jsonl)?;
+  let mut store = WalletStore::load(db_path, deposit_desc)?;
+  for label in labels.iter() {
+    store.set_label(label.clone());
+  }
+  store.save(db_path, deposit_desc)?;
+
+  Ok(WalletLabels {
+    count: labels.len(),
+    bip329: to_jsonl(&labels)?,
+  })
+}
+
+/// Exports every label in the wallet store as BIP329 JSON Lines.
+/// Frozen utxos without a label are exported as unspendable output records.
+pub fn export(db_path: &str, deposit_desc: &str) -> Result<WalletLabels, S5Error> {
+  let store = WalletStore::load(db_path, deposit_desc)?;
+  let labels = store.bip329_labels();
+  Ok(WalletLabels {
+    count: labels.len(),
+    bip329: to_jsonl(&labels)?,
+  })
+}
+
+/// Sets a single label. An empty label removes the existing one.
+pub fn set(
+  db_path: &str,
+  deposit_desc: &str,
+  label_type: LabelType,
+  reference: &str,
+  label: &str,
+) -> Result<WalletLabels, S5Error> {
+  let mut store = WalletStore::load(db_path, deposit_desc)?;
+  let record = Label {
+    label_type,
+    reference: reference.to_string(),
+    label: if label.is_empty() {
+      None
+    } else {
+      Some(label.to_string())
+    },
+    origin: None,
+    spendable: None,
+  };
+  record.validate()?;
+  store.set_label(record.clone());
+  store.save(db_path, deposit_desc)?;
+
+  Ok(WalletLabels {
+    count: 1,
+    bip329: to_jsonl(&[record])?,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+
+  #[test]
+  fn test_bip329_round_trip() {
+    let deposit_desc = "wpkh([db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)";
+    let db_path = std::env::temp_dir().join("stackmate-test-labels");
+    fs::create_dir_all(&db_path).unwrap();
+    let db_path = db_path.to_str().unwrap();
+    let _ = fs::remove_file(format!("{}/{}.json", db_path, crate::wallet::store::wallet_id(deposit_desc).unwrap()));
+
+    let jsonl = r#"{"type":"tx","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd","label":"Transaction","origin":"wpkh([d34db33f/84'/0'/0'])"}
+{"type":"addr","ref":"bc1q34aq5drpuwy3wgl9lhup9892qp6svr8ldzyy7c","label":"Address"}
+{"type":"pubkey","ref":"0283409659355b6d1cc3c32decd5d561abaac86c37a353b52895a5e6c196d6f448","label":"Public Key"}
+
+{"type":"input","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0","label":"Input"}
+{"type":"output","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:1","label":"Output","spendable":false}
+{"type":"xpub","ref":"xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8","label":"Extended Public Key"}"#;
+
+    let imported = import(db_path, deposit_desc, jsonl).unwrap();
+    assert_eq!(imported.count, 6);
+
+    let outpoint = OutPoint::from_str("f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:1").unwrap();
+    let store = WalletStore::load(db_path, deposit_desc).unwrap();
+    assert!(store.is_frozen(&outpoint));
+    assert_eq!(store.utxo_label(&outpoint), Some("Output".to_string()));
+
+    let exported = export(db_path, deposit_desc).unwrap();
+    assert_eq!(exported.count, 6);
+    assert_eq!(from_jsonl(&exported.bip329).unwrap(), from_jsonl(jsonl).unwrap());
+
+    set(db_path, deposit_desc, LabelType::Tx, "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd", "").unwrap();
+    assert_eq!(export(db_path, deposit_desc).unwrap().count, 5);
+  }
+
+  #[test]
+  fn test_bip329_errors() {
+    let bad_type = r#"{"type":"note","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd","label":"x"}"#;
+    assert_eq!(from_jsonl(bad_type).err().unwrap().message, "Invalid BIP329 record at line 1.");
+    let bad_ref = "{\"type\":\"tx\",\"ref\":\"f91d\",\"label\":\"x\"}\n{\"type\":\"output\",\"ref\":\"f91d\",\"label\":\"x\"}";
+    assert_eq!(from_jsonl(bad_ref).err().unwrap().message, "Invalid BIP329 ref at line 1.");
+    assert!(LabelType::from_str("output").is_ok());
+    assert!(LabelType::from_str("outputs").is_err());
+  }
+}
@@ -1,8 +1,19 @@
 pub mod address;
+pub mod bip21;
+pub mod bsms;
 pub mod history;
 pub mod coldcard;
+pub mod descriptor;
+pub mod export;
+pub mod labels;
 pub mod policy;
+pub mod precheck;
+pub mod progress;
 pub mod psbt;
+pub mod psbtv2;
+pub mod report;
+pub mod store;
+pub mod utxo;
 
 
 
@@ -11,13 +11,18 @@ use bdk::miniscript::policy::Concrete;
 
 use bdk::descriptor::policy::SatisfiableItem;
 
+#[cfg(feature = "online")]
 use bdk::blockchain::noop_progress;
+#[cfg(feature = "online")]
 use bdk::database::MemoryDatabase;
 
+#[cfg(feature = "online")]
 use bdk::{KeychainKind, Wallet};
 // use bdk::Error;
+#[cfg(feature = "online")]
 use crate::config::WalletConfig;
 use crate::e::{ErrorKind, S5Error};
+use crate::wallet::descriptor;
 
 /// FFI Output
 #[derive(Serialize, Deserialize, Debug)]
@@ -69,7 +74,7 @@ pub fn compile(policy: &str, script_type: &str) -> Result<WalletPolicy, S5Error>
 
   Ok(WalletPolicy {
     policy: policy.to_string(),
-    descriptor: descriptor.split('#').collect::<Vec<&str>>()[0].to_string(),
+    descriptor: descriptor::strip_checksum(&descriptor)?.to_string(),
   })
 }
 
@@ -85,6 +90,7 @@ pub struct RaftMemberPolicyPaths {
   pub secondary: SpendingPolicyPaths,
 }
 
+#[cfg(feature = "online")]
 pub async fn raft_policy_paths(config: WalletConfig) -> Result<RaftMemberPolicyPaths, S5Error> {
   let wallet = match Wallet::new(
     &config.deposit_desc,
@@ -0,0 +1,585 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+#[cfg(feature = "online")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "online")]
+use bdk::blockchain::{noop_progress, Blockchain};
+#[cfg(feature = "online")]
+use bdk::database::BatchDatabase;
+#[cfg(feature = "online")]
+use bdk::database::MemoryDatabase;
+#[cfg(feature = "online")]
+use bdk::Wallet;
+use bdk::TransactionDetails;
+
+use bitcoin::blockdata::script::Script;
+use bitcoin::blockdata::transaction::Transaction;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+
+#[cfg(feature = "online")]
+use crate::config::WalletConfig;
+use crate::e::{ErrorKind, S5Error};
+use crate::wallet::psbt;
+
+/// Nodes do not relay transactions paying less than 1 sat/vB.
+pub const MIN_RELAY_FEE_RATE: f32 = 1.0;
+/// Fee rates above this are almost certainly a mistake in the fee or an output amount.
+pub const MAX_FEE_RATE: f32 = 1000.0;
+const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+const MAX_OP_RETURN_SIZE: usize = 83;
+const DUST_RELAY_FEE_RATE: u64 = 3;
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+const SEQUENCE_FINAL: u32 = 0xFFFFFFFF;
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000FFFF;
+const SEQUENCE_LOCKTIME_GRANULARITY: u64 = 512;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  /// The transaction would be rejected by nodes, or is almost certainly a mistake. Broadcasting is refused.
+  Error,
+  Warning,
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TxIssue {
+  pub code: String,
+  pub severity: Severity,
+  pub message: String,
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BroadcastReport {
+  pub txid: String,
+  pub fee: Option<u64>,
+  pub vsize: usize,
+  pub fee_rate: Option<f32>,
+  pub tip_height: u32,
+  pub can_broadcast: bool,
+  pub issues: Vec<TxIssue>,
+}
+
+impl BroadcastReport {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+
+  fn add(&mut self, code: &str, severity: Severity, message: String) {
+    if severity == Severity::Error {
+      self.can_broadcast = false;
+    }
+    self.issues.push(TxIssue {
+      code: code.to_string(),
+      severity,
+      message,
+    });
+  }
+
+  /// Error returned by broadcast when a hard failure was found and force was not set.
+  pub fn refusal(&self) -> S5Error {
+    let messages: Vec<String> = self
+      .issues
+      .iter()
+      .filter(|issue| issue.severity == Severity::Error)
+      .map(|issue| issue.message.clone())
+      .collect();
+    S5Error::new(
+      ErrorKind::Input,
+      &format!("Broadcast refused: {} Set force to broadcast anyway.", messages.join(" ")),
+    )
+  }
+}
+
+/// Dust threshold as computed by Bitcoin Core with the default dust relay fee of 3 sat/vB:
+/// the cost of creating and later spending the output.
+fn dust_threshold(script: &Script) -> u64 {
+  let output_size = 8 + bitcoin::VarInt(script.len() as u64).len() + script.len();
+  let spend_size = if script.is_witness_program() {
+    32 + 4 + 1 + 107 / 4 + 4
+  } else {
+    32 + 4 + 1 + 107 + 4
+  };
+  (output_size + spend_size) as u64 * DUST_RELAY_FEE_RATE
+}
+
+fn is_standard_script(script: &Script) -> bool {
+  if script.is_witness_program() {
+    // witness v0 must be exactly p2wpkh or p2wsh
+    return script.as_bytes()[0] != 0 || script.is_v0_p2wpkh() || script.is_v0_p2wsh();
+  }
+  script.is_p2pk()
+    || script.is_p2pkh()
+    || script.is_p2sh()
+    || (script.is_op_return() && script.len() <= MAX_OP_RETURN_SIZE)
+}
+
+/// m-of-n bare multisig with 1 to 3 keys: OP_m <pubkeys> OP_n OP_CHECKMULTISIG.
+/// Bitcoin Core relays these unless run with -permitbaremultisig=0.
+fn is_bare_multisig(script: &Script) -> bool {
+  let bytes = script.as_bytes();
+  // OP_1 to OP_3
+  let small_int = |op: u8| if (0x51..=0x53).contains(&op) { Some(op - 0x50) } else { None };
+  if bytes.len() < 3 || bytes[bytes.len() - 1] != 0xae {
+    return false;
+  }
+  let n = match (small_int(bytes[0]), small_int(bytes[bytes.len() - 2])) {
+    (Some(m), Some(n)) if m <= n => n,
+    _ => return false,
+  };
+  let mut keys = &bytes[1..bytes.len() - 2];
+  let mut count = 0;
+  while let Some(&len) = keys.first() {
+    if (len != 33 && len != 65) || keys.len() <= len as usize {
+      return false;
+    }
+    keys = &keys[1 + len as usize..];
+    count += 1;
+  }
+  count == n
+}
+
+#[cfg(feature = "online")]
+fn now() -> u64 {
+  match SystemTime::now().duration_since(UNIX_EPOCH) {
+    Ok(duration) => duration.as_secs(),
+    Err(_) => 0,
+  }
+}
+
+fn check_outputs(tx: &Transaction, report: &mut BroadcastReport) {
+  let mut op_returns = 0;
+  for (vout, txout) in tx.output.iter().enumerate() {
+    let script = &txout.script_pubkey;
+    if script.is_op_return() {
+      op_returns += 1;
+    }
+    let bare_multisig = is_bare_multisig(script);
+    if bare_multisig {
+      report.add(
+        "bare_multisig",
+        Severity::Warning,
+        format!("Output {} is bare multisig, which some nodes do not relay.", vout),
+      );
+    }
+    if !bare_multisig && !is_standard_script(script) {
+      report.add(
+        "non_standard_script",
+        Severity::Error,
+        format!("Output {} has a non-standard script.", vout),
+      );
+    } else if !script.is_op_return() && txout.value < dust_threshold(script) {
+      report.add(
+        "dust_output",
+        Severity::Error,
+        format!(
+          "Output {} of {} sats is below the dust limit of {} sats.",
+          vout,
+          txout.value,
+          dust_threshold(script)
+        ),
+      );
+    }
+  }
+  if op_returns > 1 {
+    report.add(
+      "non_standard_script",
+      Severity::Error,
+      "Only one OP_RETURN output is standard.".to_string(),
+    );
+  }
+}
+
+fn check_timelocks(tx: &Transaction, tip: u32, now: u64, known: &[TransactionDetails], report: &mut BroadcastReport) {
+  let locktime_enabled = tx.input.iter().any(|txin| txin.sequence != SEQUENCE_FINAL);
+  if tx.lock_time > 0 && locktime_enabled {
+    if tx.lock_time < LOCKTIME_THRESHOLD {
+      if tx.lock_time > tip {
+        report.add(
+          "locktime_not_reached",
+          Severity::Error,
+          format!("Locktime is block {} but the tip is {}.", tx.lock_time, tip),
+        );
+      }
+    } else if tx.lock_time as u64 > now {
+      report.add(
+        "locktime_not_reached",
+        Severity::Error,
+        format!("Locktime is timestamp {}, which has not passed.", tx.lock_time),
+      );
+    }
+  }
+
+  if tx.version < 2 {
+    return;
+  }
+  for (index, txin) in tx.input.iter().enumerate() {
+    if txin.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+      continue;
+    }
+    let value = txin.sequence & SEQUENCE_LOCKTIME_MASK;
+    if value == 0 {
+      continue;
+    }
+    let previous = known.iter().find(|detail| detail.txid == txin.previous_output.txid);
+    let confirmation = match previous {
+      Some(detail) => detail.confirmation_time.clone(),
+      None => {
+        report.add(
+          "sequence_lock_unknown",
+          Severity::Warning,
+          format!("Input {} has a relative timelock on a transaction unknown to the wallet.", index),
+        );
+        continue;
+      }
+    };
+    let satisfied = match confirmation {
+      None => false,
+      Some(block) if txin.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 => {
+        block.timestamp + value as u64 * SEQUENCE_LOCKTIME_GRANULARITY <= now
+      }
+      Some(block) => tip + 1 >= block.height + value,
+    };
+    if !satisfied {
+      report.add(
+        "sequence_lock_not_reached",
+        Severity::Error,
+        format!("Input {} has a relative timelock which is not yet satisfied.", index),
+      );
+    }
+  }
+}
+
+fn check_conflicts(tx: &Transaction, known: &[TransactionDetails], report: &mut BroadcastReport) {
+  let txid = tx.txid();
+  for detail in known.iter().filter(|detail| detail.txid != txid) {
+    let other = match &detail.transaction {
+      Some(other) => other,
+      None => continue,
+    };
+    // an unconfirmed BIP125 transaction can be replaced, eg. by a fee bump
+    let replaceable = detail.confirmation_time.is_none()
+      && other.input.iter().any(|other_in| other_in.sequence < SEQUENCE_FINAL - 1);
+    for (index, txin) in tx.input.iter().enumerate() {
+      if !other.input.iter().any(|other_in| other_in.previous_output == txin.previous_output) {
+        continue;
+      }
+      if replaceable {
+        report.add(
+          "replaces",
+          Severity::Warning,
+          format!("Input {} replaces mempool transaction {}.", index, detail.txid),
+        );
+      } else {
+        report.add(
+          "input_spent",
+          Severity::Error,
+          format!(
+            "Input {} is already spent by {} transaction {}.",
+            index,
+            if detail.confirmation_time.is_some() { "confirmed" } else { "mempool" },
+            detail.txid
+          ),
+        );
+      }
+    }
+  }
+}
+
+/// Checks a finalized PSBT before broadcast.
+/// known is the wallet's transaction history, used for mempool conflicts and relative timelocks.
+pub fn check(
+  psbt_struct: &PartiallySignedTransaction,
+  tip: u32,
+  now: u64,
+  known: &[TransactionDetails],
+) -> BroadcastReport {
+  let tx = psbt_struct.clone().extract_tx();
+  let weight = tx.get_weight();
+  let mut report = BroadcastReport {
+    txid: tx.txid().to_string(),
+    fee: None,
+    vsize: weight.div_ceil(4),
+    fee_rate: None,
+    tip_height: tip,
+    can_broadcast: true,
+    issues: vec![],
+  };
+
+  if tx.input.iter().any(|txin| txin.script_sig.is_empty() && txin.witness.is_empty()) {
+    report.add(
+      "not_finalized",
+      Severity::Error,
+      "The transaction is not fully signed.".to_string(),
+    );
+  }
+
+  // None when an input has no utxo, Some(None) when the values overflow
+  let input_total: Option<Option<u64>> = (0..tx.input.len())
+    .map(|index| psbt::input_utxo(psbt_struct, index).ok().flatten().map(|utxo| utxo.value))
+    .collect::<Option<Vec<u64>>>()
+    .map(|values| values.iter().try_fold(0u64, |total, value| total.checked_add(*value)));
+  let output_total: Option<u64> = tx.output.iter().try_fold(0u64, |total, txout| total.checked_add(txout.value));
+  match (input_total, output_total) {
+    (Some(None), _) | (_, None) => report.add(
+      "value_overflow",
+      Severity::Error,
+      "Input or output values add up to more than 2^64 sats.".to_string(),
+    ),
+    (Some(Some(input_total)), Some(output_total)) if input_total < output_total => report.add(
+      "fee_negative",
+      Severity::Error,
+      "Outputs spend more than the inputs.".to_string(),
+    ),
+    (Some(Some(input_total)), Some(output_total)) => {
+      let fee = input_total - output_total;
+      let fee_rate = fee as f32 / report.vsize as f32;
+      report.fee = Some(fee);
+      report.fee_rate = Some(fee_rate);
+      if fee_rate < MIN_RELAY_FEE_RATE {
+        report.add(
+          "fee_below_min_relay",
+          Severity::Error,
+          format!("Fee rate of {:.2} sat/vB is below the minimum relay fee rate.", fee_rate),
+        );
+      } else if fee_rate > MAX_FEE_RATE {
+        report.add(
+          "fee_absurd",
+          Severity::Error,
+          format!("Fee rate of {:.0} sat/vB ({} sats) is absurdly high.", fee_rate, fee),
+        );
+      }
+    }
+    (None, Some(_)) => report.add(
+      "fee_unknown",
+      Severity::Warning,
+      "The fee cannot be checked because an input has no utxo in the PSBT.".to_string(),
+    ),
+  }
+
+  if weight > MAX_STANDARD_TX_WEIGHT {
+    report.add(
+      "oversize",
+      Severity::Error,
+      format!("Weight of {} is above the standard limit of {}.", weight, MAX_STANDARD_TX_WEIGHT),
+    );
+  }
+
+  check_outputs(&tx, &mut report);
+  check_timelocks(&tx, tip, now, known, &mut report);
+  check_conflicts(&tx, known, &mut report);
+  report
+}
+
+/// Checks a PSBT against a synced wallet's history and the current tip.
+#[cfg(feature = "online")]
+pub async fn check_with_wallet<B: Blockchain, D: BatchDatabase>(
+  wallet: &Wallet<B, D>,
+  psbt_struct: &PartiallySignedTransaction,
+) -> Result<BroadcastReport, S5Error> {
+  let tip = match wallet.client().get_height().await {
+    Ok(result) => result,
+    Err(e) => return Err(S5Error::new(ErrorKind::Network, &e.to_string())),
+  };
+  let known = match wallet.list_transactions(true) {
+    Ok(result) => result,
+    Err(e) => return Err(S5Error::new(ErrorKind::Internal, &e.to_string())),
+  };
+  Ok(check(psbt_struct, tip, now(), &known))
+}
+
+#[cfg(feature = "online")]
+pub async fn check_broadcast(config: WalletConfig, psbt: &str) -> Result<BroadcastReport, S5Error> {
+  let wallet = match Wallet::new(
+    &config.deposit_desc,
+    Some(&config.change_desc),
+    config.network,
+    MemoryDatabase::default(),
+    config.client,
+  )
+  .await
+  {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Initialization")),
+  };
+
+  match wallet.sync(noop_progress(), None).await {
+    Ok(_) => (),
+    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Sync")),
+  };
+
+  check_with_wallet(&wallet, &psbt::parse_psbt(psbt)?).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bdk::BlockTime;
+  use bitcoin::blockdata::transaction::{OutPoint, TxIn, TxOut};
+  use bitcoin::hashes::hex::FromHex;
+  use std::str::FromStr;
+
+  fn p2wpkh() -> Script {
+    Script::from_hex("0014135c032cff542be67ea90d52bd3b5e4e0d9fd409").unwrap()
+  }
+
+  fn signed_tx(previous: OutPoint, sequence: u32, lock_time: u32, outputs: Vec<TxOut>) -> Transaction {
+    Transaction {
+      version: 2,
+      lock_time,
+      input: vec![TxIn {
+        previous_output: previous,
+        script_sig: Script::new(),
+        sequence,
+        witness: vec![vec![0x30; 72], vec![0x02; 33]],
+      }],
+      output: outputs,
+    }
+  }
+
+  fn to_psbt(tx: &Transaction, input_value: u64) -> PartiallySignedTransaction {
+    let mut unsigned = tx.clone();
+    unsigned.input[0].witness = vec![];
+    let mut psbt_struct = PartiallySignedTransaction::from_unsigned_tx(unsigned).unwrap();
+    psbt_struct.inputs[0].witness_utxo = Some(TxOut {
+      value: input_value,
+      script_pubkey: p2wpkh(),
+    });
+    psbt_struct.inputs[0].final_script_witness = Some(tx.input[0].witness.clone());
+    psbt_struct
+  }
+
+  fn codes(report: &BroadcastReport) -> Vec<String> {
+    report.issues.iter().map(|issue| issue.code.clone()).collect()
+  }
+
+  #[test]
+  fn test_broadcast_checks() {
+    let previous =
+      OutPoint::from_str("69ec8f72a3e601e807adb5d778ad0ad27cf5f14dcab59f5fbadf3754442cdcfd:0").unwrap();
+    let payment = TxOut {
+      value: 90_000,
+      script_pubkey: p2wpkh(),
+    };
+
+    let tx = signed_tx(previous, 0xFFFFFFFD, 0, vec![payment.clone()]);
+    let report = check(&to_psbt(&tx, 100_000), 700_000, 1_600_000_000, &[]);
+    assert!(report.can_broadcast);
+    assert!(report.issues.is_empty());
+    assert_eq!(report.fee, Some(10_000));
+    assert_eq!(report.vsize, 110);
+
+    let report = check(&to_psbt(&tx, 90_050), 700_000, 1_600_000_000, &[]);
+    assert_eq!(codes(&report), vec!["fee_below_min_relay"]);
+    assert!(!report.can_broadcast);
+    assert!(report.refusal().message.contains("minimum relay fee rate"));
+    let report = check(&to_psbt(&tx, 1_000_000), 700_000, 1_600_000_000, &[]);
+    assert_eq!(codes(&report), vec!["fee_absurd"]);
+    let report = check(&to_psbt(&tx, u64::MAX), 700_000, 1_600_000_000, &[]);
+    assert_eq!(codes(&report), vec!["fee_absurd"]);
+    let huge = TxOut {
+      value: u64::MAX,
+      script_pubkey: p2wpkh(),
+    };
+    let overflowing = signed_tx(previous, 0xFFFFFFFD, 0, vec![payment.clone(), huge]);
+    let report = check(&to_psbt(&overflowing, 100_000), 700_000, 1_600_000_000, &[]);
+    assert_eq!(codes(&report), vec!["value_overflow"]);
+    assert!(!report.can_broadcast);
+
+    let dust = TxOut {
+      value: 293,
+      script_pubkey: p2wpkh(),
+    };
+    let bare = TxOut {
+      value: 10_000,
+      script_pubkey: Script::from_hex("51").unwrap(),
+    };
+    let tx = signed_tx(previous, 0xFFFFFFFD, 700_001, vec![payment.clone(), dust, bare]);
+    let report = check(&to_psbt(&tx, 110_000), 700_000, 1_600_000_000, &[]);
+    assert_eq!(
+      codes(&report),
+      vec!["dust_output", "non_standard_script", "locktime_not_reached"]
+    );
+    let tx = signed_tx(previous, SEQUENCE_FINAL, 700_001, vec![payment.clone()]);
+    assert!(check(&to_psbt(&tx, 100_000), 700_000, 1_600_000_000, &[]).can_broadcast);
+
+    let key = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    let p2pk = TxOut {
+      value: 10_000,
+      script_pubkey: Script::from_hex(&format!("21{}ac", key)).unwrap(),
+    };
+    let bare_multisig = TxOut {
+      value: 10_000,
+      script_pubkey: Script::from_hex(&format!("5121{}21{}52ae", key, key)).unwrap(),
+    };
+    let tx = signed_tx(previous, 0xFFFFFFFD, 0, vec![payment.clone(), p2pk, bare_multisig]);
+    let report = check(&to_psbt(&tx, 120_000), 700_000, 1_600_000_000, &[]);
+    assert_eq!(codes(&report), vec!["bare_multisig"]);
+    assert!(report.can_broadcast);
+    let four_keys = Script::from_hex(&format!("51{}54ae", format!("21{}", key).repeat(4))).unwrap();
+    assert!(!is_bare_multisig(&four_keys));
+
+    // 10 block relative timelock on an output confirmed at 699_995
+    let tx = signed_tx(previous, 10, 0, vec![payment.clone()]);
+    let mut parent = TransactionDetails {
+      transaction: None,
+      txid: previous.txid,
+      received: 100_000,
+      sent: 0,
+      fee: None,
+      confirmation_time: Some(BlockTime {
+        height: 699_995,
+        timestamp: 1_599_990_000,
+      }),
+      verified: true,
+    };
+    let report = check(&to_psbt(&tx, 100_000), 700_000, 1_600_000_000, &[parent.clone()]);
+    assert_eq!(codes(&report), vec!["sequence_lock_not_reached"]);
+    assert!(check(&to_psbt(&tx, 100_000), 700_004, 1_600_000_000, &[parent.clone()]).can_broadcast);
+    let report = check(&to_psbt(&tx, 100_000), 700_004, 1_600_000_000, &[]);
+    assert_eq!(codes(&report), vec!["sequence_lock_unknown"]);
+    assert!(report.can_broadcast);
+
+    let tx = signed_tx(previous, 0xFFFFFFFD, 0, vec![payment.clone()]);
+    let conflict = signed_tx(previous, 0xFFFFFFFD, 0, vec![]);
+    parent.txid = conflict.txid();
+    parent.transaction = Some(conflict);
+    parent.confirmation_time = None;
+    let report = check(&to_psbt(&tx, 100_000), 700_000, 1_600_000_000, &[parent.clone()]);
+    assert_eq!(codes(&report), vec!["replaces"]);
+    assert!(report.can_broadcast);
+    let mut confirmed = parent.clone();
+    confirmed.confirmation_time = Some(BlockTime {
+      height: 699_995,
+      timestamp: 1_599_990_000,
+    });
+    let report = check(&to_psbt(&tx, 100_000), 700_000, 1_600_000_000, &[confirmed]);
+    assert_eq!(codes(&report), vec!["input_spent"]);
+    assert!(report.issues[0].message.contains("confirmed"));
+    let conflict = signed_tx(previous, 0xFFFFFFFE, 0, vec![]);
+    parent.txid = conflict.txid();
+    parent.transaction = Some(conflict);
+    let report = check(&to_psbt(&tx, 100_000), 700_000, 1_600_000_000, &[parent]);
+    assert_eq!(codes(&report), vec!["input_spent"]);
+    assert!(report.issues[0].message.contains("mempool"));
+
+    let mut unsigned = to_psbt(&tx, 100_000);
+    unsigned.inputs[0].final_script_witness = None;
+    assert_eq!(codes(&check(&unsigned, 700_000, 1_600_000_000, &[]))[0], "not_finalized");
+  }
+}
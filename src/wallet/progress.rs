@@ -0,0 +1,390 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+use serde::{Deserialize, Serialize};
+
+use bdk::miniscript::descriptor::DescriptorPublicKey;
+use bdk::miniscript::policy::{Liftable, Semantic};
+
+use bitcoin::blockdata::transaction::Transaction;
+use bitcoin::util::psbt::{Input, PartiallySignedTransaction};
+
+use crate::e::{ErrorKind, S5Error};
+use crate::wallet::address;
+use crate::wallet::psbt::parse_psbt;
+
+/// Locktimes below this are block heights, above are unix timestamps.
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+const SEQUENCE_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_MASK: u32 = 0x0000ffff;
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InputProgress {
+  pub index: usize,
+  pub complete: bool,
+  pub signed_by: Vec<String>,
+  pub branch: String,
+  pub missing_signatures: usize,
+  pub waiting_on: Vec<String>,
+  pub timelocks: Vec<String>,
+  pub preimages: Vec<String>,
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SigningProgress {
+  pub complete: bool,
+  pub signed_by: Vec<String>,
+  pub missing_signatures: usize,
+  pub waiting_on: Vec<String>,
+  pub inputs: Vec<InputProgress>,
+}
+
+impl SigningProgress {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Fingerprint of the key origin, or the key itself for single keys without an origin.
+fn key_id(key: &DescriptorPublicKey) -> String {
+  match key {
+    DescriptorPublicKey::SinglePub(single) if single.origin.is_none() => single.key.to_string(),
+    _ => key.master_fingerprint().to_string(),
+  }
+}
+
+/// Keys with a partial signature on an input, as fingerprints where the PSBT has the key origin.
+fn input_signers(input: &Input) -> Vec<String> {
+  input
+    .partial_sigs
+    .keys()
+    .map(|key| match input.bip32_derivation.get(key) {
+      Some((fingerprint, _)) => fingerprint.to_string(),
+      None => key.to_string(),
+    })
+    .collect()
+}
+
+/// What is still needed to satisfy one branch of the policy.
+#[derive(Debug, Clone, Default)]
+struct Branch {
+  description: String,
+  signatures: usize,
+  missing_signatures: usize,
+  waiting_on: BTreeSet<String>,
+  timelocks: Vec<String>,
+  preimages: Vec<String>,
+}
+
+impl Branch {
+  fn done(description: String) -> Self {
+    Branch {
+      description,
+      ..Default::default()
+    }
+  }
+
+  fn cost(&self) -> usize {
+    self.missing_signatures + self.timelocks.len() + self.preimages.len()
+  }
+}
+
+struct InputContext<'a> {
+  tx: &'a Transaction,
+  index: usize,
+  input: &'a Input,
+  signers: BTreeSet<String>,
+}
+
+impl<'a> InputContext<'a> {
+  fn after_met(&self, value: u32) -> bool {
+    let locktime = self.tx.lock_time;
+    (locktime < LOCKTIME_THRESHOLD) == (value < LOCKTIME_THRESHOLD)
+      && locktime >= value
+      && self.tx.input[self.index].sequence != 0xffffffff
+  }
+
+  fn older_met(&self, value: u32) -> bool {
+    let sequence = self.tx.input[self.index].sequence;
+    self.tx.version >= 2
+      && sequence & SEQUENCE_DISABLE_FLAG == 0
+      && sequence & SEQUENCE_TYPE_FLAG == value & SEQUENCE_TYPE_FLAG
+      && sequence & SEQUENCE_MASK >= value & SEQUENCE_MASK
+  }
+
+  /// Cheapest way to satisfy a policy node given what is already in the PSBT. None if unsatisfiable.
+  fn evaluate(&self, policy: &Semantic<DescriptorPublicKey>) -> Option<Branch> {
+    let preimage = |description: String, present: bool| {
+      if present {
+        Some(Branch::done(description))
+      } else {
+        Some(Branch {
+          preimages: vec![description.clone()],
+          ..Branch::done(description)
+        })
+      }
+    };
+
+    match policy {
+      Semantic::Unsatisfiable => None,
+      Semantic::Trivial => Some(Branch::done("true".to_string())),
+      Semantic::KeyHash(key) => {
+        let id = key_id(key);
+        let description = format!("pk({})", id);
+        if self.signers.contains(&id) {
+          Some(Branch {
+            signatures: 1,
+            ..Branch::done(description)
+          })
+        } else {
+          let mut waiting_on = BTreeSet::new();
+          waiting_on.insert(id);
+          Some(Branch {
+            missing_signatures: 1,
+            waiting_on,
+            ..Branch::done(description)
+          })
+        }
+      }
+      Semantic::After(value) => {
+        let description = format!("after({})", value);
+        if self.after_met(*value) {
+          Some(Branch::done(description))
+        } else {
+          Some(Branch {
+            timelocks: vec![description.clone()],
+            ..Branch::done(description)
+          })
+        }
+      }
+      Semantic::Older(value) => {
+        let description = format!("older({})", value);
+        if self.older_met(*value) {
+          Some(Branch::done(description))
+        } else {
+          Some(Branch {
+            timelocks: vec![description.clone()],
+            ..Branch::done(description)
+          })
+        }
+      }
+      Semantic::Sha256(hash) => preimage(
+        format!("sha256({})", hash),
+        self.input.sha256_preimages.contains_key(hash),
+      ),
+      Semantic::Hash256(hash) => preimage(
+        format!("hash256({})", hash),
+        self.input.hash256_preimages.contains_key(hash),
+      ),
+      Semantic::Ripemd160(hash) => preimage(
+        format!("ripemd160({})", hash),
+        self.input.ripemd160_preimages.contains_key(hash),
+      ),
+      Semantic::Hash160(hash) => preimage(
+        format!("hash160({})", hash),
+        self.input.hash160_preimages.contains_key(hash),
+      ),
+      Semantic::Threshold(k, subs) => {
+        if *k == 0 {
+          return Some(Branch::done("true".to_string()));
+        }
+        let mut branches: Vec<Branch> = subs.iter().filter_map(|sub| self.evaluate(sub)).collect();
+        if branches.len() < *k {
+          return None;
+        }
+        // on equal cost prefer the branch cosigners have already started signing
+        branches.sort_by_key(|branch| (branch.cost(), Reverse(branch.signatures)));
+        let alternatives = branches.split_off(*k);
+        let highest_cost = branches[*k - 1].cost();
+
+        let descriptions: Vec<String> = branches.iter().map(|branch| branch.description.clone()).collect();
+        let mut combined = Branch::done(if *k == 1 {
+          descriptions[0].clone()
+        } else if *k == subs.len() {
+          format!("and({})", descriptions.join(","))
+        } else {
+          format!("thresh({},{})", k, descriptions.join(","))
+        });
+        for branch in branches {
+          combined.signatures += branch.signatures;
+          combined.missing_signatures += branch.missing_signatures;
+          combined.waiting_on.extend(branch.waiting_on);
+          combined.timelocks.extend(branch.timelocks);
+          combined.preimages.extend(branch.preimages);
+        }
+        // any unused branch as cheap as the costliest chosen one could be signed instead, eg. the other cosigners of a multisig
+        for alternative in alternatives {
+          if highest_cost > 0 && alternative.cost() == highest_cost {
+            combined.waiting_on.extend(alternative.waiting_on);
+          }
+        }
+        Some(combined)
+      }
+    }
+  }
+}
+
+/// Reports which keys have signed each input and what the cheapest remaining branch of the descriptor policy still needs.
+pub fn progress(deposit_desc: &str, psbt: &str) -> Result<SigningProgress, S5Error> {
+  let descriptor = address::public_descriptor(deposit_desc)?;
+  let policy = match descriptor.lift() {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Input, "Descriptor-Policy")),
+  };
+  let psbt_struct: PartiallySignedTransaction = parse_psbt(psbt)?;
+  let tx = &psbt_struct.global.unsigned_tx;
+
+  let mut inputs = vec![];
+  for (index, input) in psbt_struct.inputs.iter().enumerate() {
+    let signed_by = input_signers(input);
+    if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+      inputs.push(InputProgress {
+        index,
+        complete: true,
+        signed_by,
+        branch: "finalized".to_string(),
+        missing_signatures: 0,
+        waiting_on: vec![],
+        timelocks: vec![],
+        preimages: vec![],
+      });
+      continue;
+    }
+
+    let context = InputContext {
+      tx,
+      index,
+      input,
+      signers: signed_by.iter().cloned().collect(),
+    };
+    let branch = match context.evaluate(&policy) {
+      Some(result) => result,
+      None => return Err(S5Error::new(ErrorKind::Input, "Policy-Unsatisfiable")),
+    };
+    inputs.push(InputProgress {
+      index,
+      complete: branch.cost() == 0,
+      signed_by,
+      branch: branch.description,
+      missing_signatures: branch.missing_signatures,
+      waiting_on: branch.waiting_on.into_iter().collect(),
+      timelocks: branch.timelocks,
+      preimages: branch.preimages,
+    });
+  }
+
+  let signed_by: BTreeSet<String> = inputs.iter().flat_map(|input| input.signed_by.clone()).collect();
+  let waiting_on: BTreeSet<String> = inputs.iter().flat_map(|input| input.waiting_on.clone()).collect();
+  Ok(SigningProgress {
+    complete: inputs.iter().all(|input| input.complete),
+    signed_by: signed_by.into_iter().collect(),
+    missing_signatures: inputs
+      .iter()
+      .map(|input| input.missing_signatures)
+      .max()
+      .unwrap_or(0),
+    waiting_on: waiting_on.into_iter().collect(),
+    inputs,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::str::FromStr;
+
+  use bitcoin::blockdata::transaction::{OutPoint, TxIn, TxOut};
+  use bitcoin::secp256k1::Secp256k1;
+  use bitcoin::util::bip32::{DerivationPath, ExtendedPubKey, Fingerprint};
+  use bitcoin::util::psbt::PartiallySignedTransaction;
+
+  const USER: &str = "[db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe";
+  const CUSTODIAN: &str = "[66a0c105/84'/1'/5']tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R";
+  const BACKUP: &str = "[05232dee/84h/1h/0h]tpubDCLDXhTEBD9usoa7td6k94WhnA8G8gLPnEkZeauvTqyB2NgV9hZkVbWeQmmSbDxYWuvcsiqg2DY688NiXzjZwt3TZAxYs33RDXvpqPNSdPM";
+
+  fn unsigned_psbt(lock_time: u32) -> PartiallySignedTransaction {
+    let tx = Transaction {
+      version: 2,
+      lock_time,
+      input: vec![TxIn {
+        previous_output: OutPoint::from_str("69ec8f72a3e601e807adb5d778ad0ad27cf5f14dcab59f5fbadf3754442cdcfd:0").unwrap(),
+        sequence: 0xfffffffd,
+        ..Default::default()
+      }],
+      output: vec![TxOut::default()],
+    };
+    PartiallySignedTransaction::from_unsigned_tx(tx).unwrap()
+  }
+
+  /// Adds a placeholder signature from the key at .../0/0
+  fn add_signature(psbt: &mut PartiallySignedTransaction, xkey: &str) {
+    let secp = Secp256k1::new();
+    let fingerprint = Fingerprint::from_str(&xkey[1..9]).unwrap();
+    let xpub = ExtendedPubKey::from_str(xkey.split(']').collect::<Vec<&str>>()[1]).unwrap();
+    let path = DerivationPath::from_str("m/0/0").unwrap();
+    let key = xpub.derive_pub(&secp, &path).unwrap().public_key;
+    psbt.inputs[0].partial_sigs.insert(key, vec![0x30, 0x01]);
+    psbt.inputs[0].bip32_derivation.insert(key, (fingerprint, path));
+  }
+
+  #[test]
+  fn test_raft_progress() {
+    let desc = format!(
+      "wsh(thresh(1,pk({}/0/*),snj:and_v(v:pk({}/0/*),after(2105103))))",
+      USER, CUSTODIAN
+    );
+
+    let psbt = unsigned_psbt(0);
+    let report = progress(&desc, &psbt.to_string()).unwrap();
+    assert!(!report.complete);
+    assert_eq!(report.missing_signatures, 1);
+    assert_eq!(report.waiting_on, vec!["db7d25b5"]);
+    assert_eq!(report.inputs[0].branch, "pk(db7d25b5)");
+
+    let mut psbt = unsigned_psbt(2105103);
+    add_signature(&mut psbt, CUSTODIAN);
+    let report = progress(&desc, &psbt.to_string()).unwrap();
+    assert!(report.complete);
+    assert_eq!(report.signed_by, vec!["66a0c105"]);
+    assert_eq!(report.inputs[0].branch, "and(pk(66a0c105),after(2105103))");
+
+    let mut psbt = unsigned_psbt(0);
+    add_signature(&mut psbt, CUSTODIAN);
+    let report = progress(&desc, &psbt.to_string()).unwrap();
+    assert!(!report.complete);
+    assert_eq!(report.inputs[0].branch, "and(pk(66a0c105),after(2105103))");
+    assert_eq!(report.inputs[0].timelocks, vec!["after(2105103)"]);
+    assert_eq!(report.missing_signatures, 0);
+  }
+
+  #[test]
+  fn test_multisig_progress() {
+    let desc = format!("wsh(multi(2,{}/0/*,{}/0/*,{}/0/*))", USER, CUSTODIAN, BACKUP);
+    let mut psbt = unsigned_psbt(0);
+    add_signature(&mut psbt, USER);
+    let report = progress(&desc, &psbt.to_string()).unwrap();
+    assert!(!report.complete);
+    assert_eq!(report.signed_by, vec!["db7d25b5"]);
+    assert_eq!(report.missing_signatures, 1);
+    assert_eq!(report.inputs[0].waiting_on, vec!["05232dee", "66a0c105"]);
+
+    add_signature(&mut psbt, BACKUP);
+    let report = progress(&desc, &psbt.to_string()).unwrap();
+    assert!(report.complete);
+    assert_eq!(report.inputs[0].branch, "thresh(2,pk(db7d25b5),pk(05232dee))");
+  }
+}
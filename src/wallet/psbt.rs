@@ -1,28 +1,49 @@
+use std::cell::RefCell;
+use std::collections::btree_map::BTreeMap;
 use std::ffi::CString;
+use std::fs;
 use std::os::raw::c_char;
 use std::str::FromStr;
-use std::collections::btree_map::BTreeMap;
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "online")]
 use bdk::blockchain::noop_progress;
+#[cfg(feature = "online")]
+use bdk::blockchain::Blockchain;
 use bdk::database::MemoryDatabase;
 
+use bdk::signer::SignerError;
 use bdk::{SignOptions, Wallet, KeychainKind};
 
 use bitcoin::base64;
-use bitcoin::blockdata::transaction::Transaction;
+use bitcoin::blockdata::script::Script;
+use bitcoin::blockdata::transaction::{SigHashType, Transaction, TxOut};
+use bitcoin::consensus::encode::{serialize, serialize_hex};
 use bitcoin::consensus::deserialize;
 use bitcoin::network::constants::Network;
+use bitcoin::secp256k1::{Secp256k1, VerifyOnly};
 use bitcoin::util::address::Address;
-use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::util::bip32::{ChildNumber, KeySource};
+use bitcoin::PublicKey;
+use bitcoin::util::psbt::{Input, PartiallySignedTransaction};
 use bdk::descriptor::{Descriptor};
+use bdk::miniscript::descriptor::DescriptorPublicKey;
 use bdk::miniscript::DescriptorTrait;
 
+use crate::config;
+#[cfg(feature = "online")]
 use crate::config::WalletConfig;
 use crate::e::{ErrorKind, S5Error};
 
+use crate::wallet::address;
+use crate::wallet::psbtv2;
+#[cfg(feature = "online")]
 use crate::wallet::policy::{SpendingPolicyPaths};
+#[cfg(feature = "online")]
+use crate::wallet::precheck;
+#[cfg(feature = "online")]
+use crate::wallet::store::WalletStore;
 
 /// FFI Output
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -47,14 +68,18 @@ impl WalletPSBT {
 }
 
 
+#[cfg(feature = "online")]
 pub async fn build(
   config: WalletConfig,
   to: &str,
   amount: Option<u64>,
   fee_absolute: u64,
   sweep: bool,
-  policy_paths: Option<SpendingPolicyPaths>
+  policy_paths: Option<SpendingPolicyPaths>,
+  store: &WalletStore,
 ) -> Result<WalletPSBT, S5Error> {
+  let send_to = address::parse(to, config.network)?;
+
   let wallet = match Wallet::new(
     &config.deposit_desc,
     Some(&config.change_desc),
@@ -71,11 +96,6 @@ pub async fn build(
     Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Sync")),
   };
 
-  let send_to = match Address::from_str(to) {
-    Ok(result) => result,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Address-Parse")),
-  };
-
   let (psbt, _) = {
     let mut builder = wallet.build_tx();
     if sweep && amount.is_none() {
@@ -87,6 +107,7 @@ pub async fn build(
     }
 
     builder.fee_absolute(fee_absolute);
+    builder.unspendable(store.frozen_outpoints());
 
     if policy_paths.is_some(){
       builder.policy_path(policy_paths.clone().unwrap().external, KeychainKind::External);
@@ -108,17 +129,41 @@ pub async fn build(
   })
 }
 
-#[derive(Serialize, Debug, Clone)]
-pub struct DecodedTxIO {
-  value: u64,
-  to: String,
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DecodedInput {
+  pub previous_outpoint: String,
+  pub value: Option<u64>,
+  pub address: Option<String>,
+  pub is_mine: bool,
+  pub sequence: u32,
+  pub sighash_type: String,
+  pub signed_by: Vec<String>,
+  pub finalized: bool,
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DecodedOutput {
+  pub vout: u32,
+  pub value: u64,
+  pub address: String,
+  pub is_mine: bool,
+  pub is_change: bool,
 }
 
-#[derive(Serialize, Debug, Clone)]
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DecodedTx {
-  pub outputs: Vec<DecodedTxIO>,
-  // pub weight: usize,
-  // pub satisfaction_weight: usize
+  pub txid: String,
+  pub version: i32,
+  pub locktime: u32,
+  pub rbf: bool,
+  pub inputs: Vec<DecodedInput>,
+  pub outputs: Vec<DecodedOutput>,
+  pub fee: Option<u64>,
+  pub vsize: Option<usize>,
+  pub fee_rate: Option<f32>,
 }
 
 impl DecodedTx {
@@ -136,59 +181,309 @@ impl DecodedTx {
   }
 }
 
-pub fn decode(network: Network, psbt: &str) -> Result<DecodedTx, S5Error> {
-  let decoded_psbt = match base64::decode(psbt) {
-    Ok(psbt) => psbt,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Basae64-Decode")),
-  };
+/// Number of addresses per keychain searched when a script has no bip32 derivation hint.
+const DECODE_SCAN_DEPTH: u32 = 100;
+
+/// Matches scripts against the deposit and change keychains of a descriptor.
+struct WalletScripts {
+  external: Descriptor<DescriptorPublicKey>,
+  internal: Option<Descriptor<DescriptorPublicKey>>,
+  scanned: RefCell<Option<Vec<(Script, KeychainKind)>>>,
+  secp: Secp256k1<VerifyOnly>,
+}
+
+impl WalletScripts {
+  fn new(deposit_desc: &str) -> Result<Self, S5Error> {
+    let change_desc = deposit_desc.replace("/0/*", "/1/*");
+    Ok(WalletScripts {
+      external: address::public_descriptor(deposit_desc)?,
+      internal: if change_desc == deposit_desc {
+        None
+      } else {
+        Some(address::public_descriptor(&change_desc)?)
+      },
+      scanned: RefCell::new(None),
+      secp: Secp256k1::verification_only(),
+    })
+  }
+
+  fn keychains(&self) -> Vec<(&Descriptor<DescriptorPublicKey>, KeychainKind)> {
+    let mut keychains = vec![(&self.external, KeychainKind::External)];
+    if let Some(internal) = &self.internal {
+      keychains.push((internal, KeychainKind::Internal));
+    }
+    keychains
+  }
+
+  /// Finds the keychain of a script using the last step of any bip32 derivation hints,
+  /// falling back to a scan of the first DECODE_SCAN_DEPTH addresses.
+  fn keychain_of(&self, script: &Script, hints: &BTreeMap<PublicKey, KeySource>) -> Option<KeychainKind> {
+    for (_, (_, path)) in hints.iter() {
+      let index = match path.into_iter().last() {
+        Some(ChildNumber::Normal { index }) => *index,
+        _ => continue,
+      };
+      for (descriptor, keychain) in self.keychains() {
+        if address::derive_script(descriptor, index, &self.secp).ok().as_ref() == Some(script) {
+          return Some(keychain);
+        }
+      }
+    }
+
+    let mut scanned = self.scanned.borrow_mut();
+    let scanned = scanned.get_or_insert_with(|| {
+      let mut scripts = vec![];
+      for (descriptor, keychain) in self.keychains() {
+        for index in 0..DECODE_SCAN_DEPTH {
+          if let Ok(derived) = address::derive_script(descriptor, index, &self.secp) {
+            scripts.push((derived, keychain));
+          }
+        }
+      }
+      scripts
+    });
+    scanned
+      .iter()
+      .find(|(derived, _)| derived == script)
+      .map(|(_, keychain)| *keychain)
+  }
+}
 
-  let psbt_struct: PartiallySignedTransaction = match deserialize(&decoded_psbt) {
-    Ok(psbt) => psbt,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Deserialize-Error")),
+/// Previous output of an input, from either witness_utxo or non_witness_utxo.
+/// A non_witness_utxo must be the transaction being spent, and a witness_utxo given with it must be the spent output.
+pub(crate) fn input_utxo(psbt: &PartiallySignedTransaction, index: usize) -> Result<Option<TxOut>, S5Error> {
+  let input = &psbt.inputs[index];
+  let previous_output = psbt.global.unsigned_tx.input[index].previous_output;
+  let mismatch = || {
+    S5Error::new(
+      ErrorKind::Input,
+      &format!("Input {} has a utxo which does not match its previous outpoint.", index),
+    )
   };
+  let non_witness_utxo = match &input.non_witness_utxo {
+    Some(tx) if tx.txid() != previous_output.txid => return Err(mismatch()),
+    Some(tx) => match tx.output.get(previous_output.vout as usize) {
+      Some(utxo) => Some(utxo.clone()),
+      None => return Err(mismatch()),
+    },
+    None => None,
+  };
+  match (&input.witness_utxo, non_witness_utxo) {
+    (Some(utxo), Some(spent)) if *utxo != spent => Err(mismatch()),
+    (Some(utxo), _) => Ok(Some(utxo.clone())),
+    (None, spent) => Ok(spent),
+  }
+}
+
+fn value_overflow() -> S5Error {
+  S5Error::new(ErrorKind::Input, "PSBT values add up to more than 2^64 sats.")
+}
 
-  let outputs = &psbt_struct.global.unsigned_tx.output;
-  // println!("{:#?}", Address::from_script(&outputs[0].clone().script_pubkey,network_enum));
-  let inputs = &psbt_struct.inputs;
+/// Decodes a PSBT for review before signing.
+/// deposit_desc is optional and is used to flag inputs and outputs belonging to the wallet and to estimate the final size.
+pub fn decode(network: Network, psbt: &str, deposit_desc: Option<&str>) -> Result<DecodedTx, S5Error> {
+  let psbt_struct = parse_psbt(psbt)?;
 
-  // let transaction: Transaction = psbt_struct.clone().extract_tx();
+  let wallet_scripts = match deposit_desc {
+    Some(desc) => Some(WalletScripts::new(desc)?),
+    None => None,
+  };
 
-  let mut decoded_outputs: Vec<DecodedTxIO> = vec![];
+  let unsigned_tx = &psbt_struct.global.unsigned_tx;
+  if unsigned_tx.input.len() != psbt_struct.inputs.len()
+    || unsigned_tx.output.len() != psbt_struct.outputs.len()
+  {
+    return Err(S5Error::new(ErrorKind::Input, "PSBT-Inconsistent"));
+  }
 
-  let mut total_out_value = 0;
-  let mut total_in_value = 0;
+  let mut inputs: Vec<DecodedInput> = vec![];
+  let mut total_in_value: Option<u64> = Some(0);
+  for (index, (txin, input)) in unsigned_tx.input.iter().zip(psbt_struct.inputs.iter()).enumerate() {
+    let utxo = input_utxo(&psbt_struct, index)?;
+    total_in_value = match (total_in_value, &utxo) {
+      (Some(total), Some(utxo)) => match total.checked_add(utxo.value) {
+        Some(total) => Some(total),
+        None => return Err(value_overflow()),
+      },
+      _ => None,
+    };
+    let keychain = match (&wallet_scripts, &utxo) {
+      (Some(scripts), Some(utxo)) => scripts.keychain_of(&utxo.script_pubkey, &input.bip32_derivation),
+      _ => None,
+    };
+    inputs.push(DecodedInput {
+      previous_outpoint: txin.previous_output.to_string(),
+      value: utxo.as_ref().map(|utxo| utxo.value),
+      address: utxo
+        .as_ref()
+        .and_then(|utxo| Address::from_script(&utxo.script_pubkey, network))
+        .map(|address| address.to_string()),
+      is_mine: keychain.is_some(),
+      sequence: txin.sequence,
+      sighash_type: input.sighash_type.unwrap_or(SigHashType::All).to_string(),
+      signed_by: input
+        .partial_sigs
+        .keys()
+        .map(|key| match input.bip32_derivation.get(key) {
+          Some((fingerprint, _)) => fingerprint.to_string(),
+          None => key.to_string(),
+        })
+        .collect(),
+      finalized: input.final_script_sig.is_some() || input.final_script_witness.is_some(),
+    });
+  }
 
-  for output in outputs {
-    total_out_value += output.value;
-    decoded_outputs.push(DecodedTxIO {
-      value: output.value,
-      to: match Address::from_script(&output.script_pubkey, network) {
+  let mut outputs: Vec<DecodedOutput> = vec![];
+  let mut total_out_value: u64 = 0;
+  for (vout, (txout, output)) in unsigned_tx.output.iter().zip(psbt_struct.outputs.iter()).enumerate() {
+    total_out_value = match total_out_value.checked_add(txout.value) {
+      Some(total) => total,
+      None => return Err(value_overflow()),
+    };
+    let keychain = match &wallet_scripts {
+      Some(scripts) => scripts.keychain_of(&txout.script_pubkey, &output.bip32_derivation),
+      None => None,
+    };
+    outputs.push(DecodedOutput {
+      vout: vout as u32,
+      value: txout.value,
+      address: match Address::from_script(&txout.script_pubkey, network) {
         Some(address) => address.to_string(),
         None => "None".to_string(),
       },
+      is_mine: keychain.is_some(),
+      is_change: keychain == Some(KeychainKind::Internal),
     });
   }
 
-  for input in inputs {
-    // let witness_utxo = input.witness_utxo.clone();
-    total_in_value += input.witness_utxo.clone().unwrap().value;
-    // decoded_inputs.push(DecodedTxIO {
-    //   value: input.witness_utxo.clone().unwrap().value,
-    //   to: match Address::from_script(&input.witness_script.clone().unwrap(), network) {
-    //     Some(address) => address.to_string(),
-    //     None => "None".to_string(),
-    //   },
-    // });
-  }
+  let fee = total_in_value.and_then(|total| total.checked_sub(total_out_value));
 
-  decoded_outputs.push(DecodedTxIO {
-    value: total_in_value - total_out_value,
-    to: "miner".to_string(),
-  });
+  let weight = if inputs.iter().all(|input| input.finalized) {
+    Some(psbt_struct.clone().extract_tx().get_weight())
+  } else {
+    wallet_scripts
+      .as_ref()
+      .and_then(|scripts| scripts.external.max_satisfaction_weight().ok())
+      .map(|satisfaction_weight| unsigned_tx.get_weight() + unsigned_tx.input.len() * satisfaction_weight)
+  };
+  let vsize = weight.map(|weight| weight.div_ceil(4));
+  let fee_rate = match (fee, vsize) {
+    (Some(fee), Some(vsize)) if vsize > 0 => Some(fee as f32 / vsize as f32),
+    _ => None,
+  };
 
   Ok(DecodedTx {
-    outputs: decoded_outputs,
-    // weight: weight + outputs.len() * 76,
+    txid: unsigned_tx.txid().to_string(),
+    version: unsigned_tx.version,
+    locktime: unsigned_tx.lock_time,
+    rbf: unsigned_tx.input.iter().any(|txin| signals_rbf(txin.sequence)),
+    inputs,
+    outputs,
+    fee,
+    vsize,
+    fee_rate,
+  })
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RawTxInput {
+  pub previous_outpoint: String,
+  pub sequence: u32,
+  pub rbf: bool,
+  pub witness_items: usize,
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RawTxOutput {
+  pub vout: u32,
+  pub value: u64,
+  pub address: Option<String>,
+  pub script_pubkey: String,
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DecodedRawTx {
+  pub txid: String,
+  pub wtxid: String,
+  pub version: i32,
+  pub locktime: u32,
+  pub rbf: bool,
+  pub size: usize,
+  pub weight: usize,
+  pub vsize: usize,
+  pub inputs: Vec<RawTxInput>,
+  pub outputs: Vec<RawTxOutput>,
+}
+
+impl DecodedRawTx {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Sequences below 0xfffffffe signal opt-in replace-by-fee (BIP125).
+fn signals_rbf(sequence: u32) -> bool {
+  sequence < 0xFFFFFFFE
+}
+
+pub(crate) fn parse_raw_tx(tx_hex: &str) -> Result<Transaction, S5Error> {
+  let bytes = match hex::decode(tx_hex.trim()) {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Input, "Transaction-Hex")),
+  };
+  match deserialize(&bytes) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "Transaction-Deserialize")),
+  }
+}
+
+/// Decodes a raw transaction hex without any wallet context.
+/// Input values are not part of a raw transaction, so unlike decode there is no fee.
+pub fn decode_raw_tx(network: Network, tx_hex: &str) -> Result<DecodedRawTx, S5Error> {
+  let tx = parse_raw_tx(tx_hex)?;
+  let weight = tx.get_weight();
+  Ok(DecodedRawTx {
+    txid: tx.txid().to_string(),
+    wtxid: tx.wtxid().to_string(),
+    version: tx.version,
+    locktime: tx.lock_time,
+    rbf: tx.input.iter().any(|txin| signals_rbf(txin.sequence)),
+    size: tx.get_size(),
+    weight,
+    vsize: weight.div_ceil(4),
+    inputs: tx
+      .input
+      .iter()
+      .map(|txin| RawTxInput {
+        previous_outpoint: txin.previous_output.to_string(),
+        sequence: txin.sequence,
+        rbf: signals_rbf(txin.sequence),
+        witness_items: txin.witness.len(),
+      })
+      .collect(),
+    outputs: tx
+      .output
+      .iter()
+      .enumerate()
+      .map(|(vout, txout)| RawTxOutput {
+        vout: vout as u32,
+        value: txout.value,
+        address: Address::from_script(&txout.script_pubkey, network).map(|address| address.to_string()),
+        script_pubkey: hex::encode(txout.script_pubkey.as_bytes()),
+      })
+      .collect(),
   })
 }
 
@@ -218,15 +513,7 @@ pub fn get_weight(
   deposit_desc: &str,
   psbt: &str
 )->Result<TransactionWeight, S5Error>{
-  let decoded_psbt = match base64::decode(psbt) {
-    Ok(psbt) => psbt,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Base64-Decode")),
-  };
-
-  let psbt_struct: PartiallySignedTransaction = match deserialize(&decoded_psbt) {
-    Ok(psbt) => psbt,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Deserialize-Error")),
-  };
+  let psbt_struct = parse_psbt(psbt)?;
 
   let transaction: Transaction = psbt_struct.extract_tx();
   let desc = Descriptor::<String>::from_str(deposit_desc).unwrap();
@@ -238,30 +525,373 @@ pub fn get_weight(
  
 }
 
-pub fn sign(config: WalletConfig, psbt: &str) -> Result<WalletPSBT, S5Error> {
-  let wallet = match Wallet::new_offline(
-    &config.deposit_desc,
-    Some(&config.change_desc),
-    config.network,
+/// Wallet for a descriptor with no blockchain backend, used to sign and finalize without a network connection.
+fn offline_wallet(deposit_desc: &str) -> Result<Wallet<(), MemoryDatabase>, S5Error> {
+  let change_desc = config::change_descriptor(deposit_desc);
+  match Wallet::new_offline(
+    deposit_desc,
+    Some(&change_desc),
+    config::descriptor_network(deposit_desc),
     MemoryDatabase::default(),
   ) {
-    Ok(result) => result,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Initialization")),
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Internal, "Wallet-Initialization")),
+  }
+}
+
+/// Signer behaviour, passed as JSON. Missing fields take the defaults below.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct WalletSignOptions {
+  /// Sign inputs that only carry a witness_utxo. Defaults to false (non_witness_utxo required).
+  pub trust_witness_utxo: bool,
+  /// Height assumed reached when finalizing, to use timelocked branches early.
+  pub assume_height: Option<u32>,
+  /// Sign with the sighash set in the PSBT, even if it is not SIGHASH_ALL.
+  pub allow_all_sighashes: bool,
+  /// Drop partial signatures from inputs once they are finalized.
+  pub remove_partial_sigs: bool,
+  /// Finalize inputs which have enough signatures after signing.
+  pub try_finalize: bool,
+}
+
+impl Default for WalletSignOptions {
+  fn default() -> Self {
+    WalletSignOptions {
+      trust_witness_utxo: false,
+      assume_height: None,
+      allow_all_sighashes: false,
+      remove_partial_sigs: false,
+      try_finalize: true,
+    }
+  }
+}
+
+impl WalletSignOptions {
+  fn bdk_options(&self) -> SignOptions {
+    SignOptions {
+      trust_witness_utxo: self.trust_witness_utxo,
+      assume_height: self.assume_height,
+      allow_all_sighashes: self.allow_all_sighashes,
+    }
+  }
+}
+
+fn sign_error(error: bdk::Error) -> S5Error {
+  match error {
+    bdk::Error::Signer(e) => match e {
+      SignerError::MissingKey => S5Error::new(ErrorKind::Key, "Missing private key for a required public key."),
+      SignerError::InvalidKey => S5Error::new(ErrorKind::Key, "Private key does not derive the expected public key."),
+      SignerError::UserCanceled => S5Error::new(ErrorKind::Internal, "Signing cancelled."),
+      SignerError::InputIndexOutOfRange => S5Error::new(ErrorKind::Input, "PSBT-Inconsistent"),
+      SignerError::MissingNonWitnessUtxo => S5Error::new(
+        ErrorKind::Input,
+        "Missing non_witness_utxo. Set trust_witness_utxo to sign with only the witness_utxo.",
+      ),
+      SignerError::InvalidNonWitnessUtxo => S5Error::new(ErrorKind::Input, "Invalid non_witness_utxo."),
+      SignerError::MissingWitnessUtxo => S5Error::new(ErrorKind::Input, "Missing witness_utxo."),
+      SignerError::MissingWitnessScript => S5Error::new(ErrorKind::Input, "Missing witness_script."),
+      SignerError::MissingHdKeypath => S5Error::new(ErrorKind::Input, "Missing key origin in PSBT input."),
+      SignerError::NonStandardSighash => S5Error::new(
+        ErrorKind::Input,
+        "Non-standard sighash. Set allow_all_sighashes to sign it.",
+      ),
+    },
+    e => S5Error::new(ErrorKind::Wallet, &e.to_string()),
+  }
+}
+
+fn signature_count(psbt: &PartiallySignedTransaction) -> usize {
+  psbt.inputs.iter().map(|input| input.partial_sigs.len()).sum()
+}
+
+/// Signs a PSBT with the private keys in a descriptor. Does not require a network connection.
+pub fn sign(deposit_desc: &str, psbt: &str, options: WalletSignOptions) -> Result<WalletPSBT, S5Error> {
+  let wallet = offline_wallet(deposit_desc)?;
+  let (original, version) = parse_psbt_versioned(psbt)?;
+  let mut final_psbt = original.clone();
+
+  if let Err(e) = wallet.sign(&mut final_psbt, options.bdk_options()) {
+    return Err(sign_error(e));
+  }
+
+  if !options.try_finalize {
+    // bdk always finalizes; restore inputs that were not finalized before signing
+    for (input, before) in final_psbt.inputs.iter_mut().zip(original.inputs.iter()) {
+      input.final_script_sig = before.final_script_sig.clone();
+      input.final_script_witness = before.final_script_witness.clone();
+    }
+  }
+
+  let finalized_count = |psbt: &PartiallySignedTransaction| psbt.inputs.iter().filter(|input| is_input_finalized(input)).count();
+  let unchanged = signature_count(&final_psbt) == signature_count(&original)
+    && finalized_count(&final_psbt) == finalized_count(&original);
+  if finalized_count(&original) < original.inputs.len() && unchanged {
+    // nothing changed: either none of our keys are in the PSBT, or they already signed it
+    let mut probe = original.clone();
+    for input in probe.inputs.iter_mut() {
+      input.partial_sigs.clear();
+      input.final_script_sig = None;
+      input.final_script_witness = None;
+    }
+    let probe_options = WalletSignOptions { try_finalize: false, ..options.clone() };
+    if wallet.sign(&mut probe, probe_options.bdk_options()).is_err() || signature_count(&probe) == 0 {
+      return Err(S5Error::new(ErrorKind::Key, "No key in the descriptor can sign this PSBT."));
+    }
+  }
+
+  if options.remove_partial_sigs {
+    for input in final_psbt.inputs.iter_mut().filter(|input| is_input_finalized(input)) {
+      input.partial_sigs.clear();
+    }
+  }
+
+  Ok(WalletPSBT {
+    is_finalized: is_finalized(&final_psbt),
+    psbt: encode_psbt(&final_psbt, version)?,
+  })
+}
+
+/// Encodings a PSBT can be read from and written to.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PSBTEncoding {
+  Base64,
+  Hex,
+  Binary,
+}
+
+impl FromStr for PSBTEncoding {
+  type Err = S5Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "base64" => Ok(PSBTEncoding::Base64),
+      "hex" => Ok(PSBTEncoding::Hex),
+      "binary" => Ok(PSBTEncoding::Binary),
+      _ => Err(S5Error::new(ErrorKind::Input, "Invalid PSBT Encoding.")),
+    }
+  }
+}
+
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+const PSBT_MAGIC_HEX: &str = "70736274ff";
+
+/// Returns the serialized PSBT from raw binary, hex or base64 data, detecting which one it is.
+fn decode_psbt_data(data: &[u8]) -> Result<Vec<u8>, S5Error> {
+  if data.starts_with(PSBT_MAGIC) {
+    return Ok(data.to_vec());
+  }
+  let text = match std::str::from_utf8(data) {
+    Ok(result) => result.trim(),
+    Err(_) => return Err(S5Error::new(ErrorKind::Input, "PSBT-Decode")),
   };
+  let magic = text.as_bytes().get(..PSBT_MAGIC_HEX.len());
+  if magic.is_some_and(|magic| magic.eq_ignore_ascii_case(PSBT_MAGIC_HEX.as_bytes())) {
+    return match hex::decode(text) {
+      Ok(result) => Ok(result),
+      Err(_) => Err(S5Error::new(ErrorKind::Input, "PSBT-Decode")),
+    };
+  }
+  match base64::decode(text) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "PSBT-Decode")),
+  }
+}
 
-  let mut final_psbt = match deserialize(&base64::decode(psbt).unwrap()) {
-    Ok(psbt) => psbt,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Deserialize-Psbt-Error")),
+/// Parses a PSBT of version 0 or 2 in base64, hex or binary, returning it with its version.
+/// Version 2 PSBTs are converted to version 0, which is what rust-bitcoin and bdk work with.
+fn parse_psbt_data(data: &[u8]) -> Result<(PartiallySignedTransaction, u32), S5Error> {
+  let decoded_psbt = decode_psbt_data(data)?;
+  let version = psbtv2::version(&decoded_psbt)?;
+  let v0_psbt = match version {
+    0 => decoded_psbt,
+    2 => psbtv2::to_v0(&decoded_psbt)?,
+    _ => return Err(S5Error::new(ErrorKind::Input, "Unsupported PSBT Version.")),
   };
+  match deserialize(&v0_psbt) {
+    Ok(result) => Ok((result, version)),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "PSBT-Deserialize")),
+  }
+}
+
+pub(crate) fn parse_psbt_versioned(psbt: &str) -> Result<(PartiallySignedTransaction, u32), S5Error> {
+  parse_psbt_data(psbt.as_bytes())
+}
+
+/// Accepts base64 or hex, and version 0 or 2.
+pub(crate) fn parse_psbt(psbt: &str) -> Result<PartiallySignedTransaction, S5Error> {
+  Ok(parse_psbt_versioned(psbt)?.0)
+}
 
-  let finalized = match wallet.sign(&mut final_psbt, SignOptions::default()) {
+pub(crate) fn serialize_psbt(psbt: &PartiallySignedTransaction, version: u32) -> Result<Vec<u8>, S5Error> {
+  match version {
+    0 => Ok(serialize(psbt)),
+    2 => psbtv2::to_v2(&serialize(psbt)),
+    _ => Err(S5Error::new(ErrorKind::Input, "Unsupported PSBT Version.")),
+  }
+}
+
+/// Base64 encodes a PSBT as the given version (0 or 2).
+pub(crate) fn encode_psbt(psbt: &PartiallySignedTransaction, version: u32) -> Result<String, S5Error> {
+  Ok(base64::encode(&serialize_psbt(psbt, version)?))
+}
+
+/// Reads a PSBT file in base64, hex or binary (eg. a Coldcard SD card .psbt) and returns it in base64.
+pub fn read_file(path: &str) -> Result<WalletPSBT, S5Error> {
+  let data = match fs::read(path) {
     Ok(result) => result,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Sign-Error")),
+    Err(_) => return Err(S5Error::new(ErrorKind::Input, "PSBT-File-Read")),
   };
+  let (psbt_struct, version) = parse_psbt_data(&data)?;
+  Ok(WalletPSBT {
+    psbt: encode_psbt(&psbt_struct, version)?,
+    is_finalized: is_finalized(&psbt_struct),
+  })
+}
 
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PSBTFile {
+  pub path: String,
+  pub encoding: PSBTEncoding,
+  pub size: usize,
+}
+
+impl PSBTFile {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Writes a PSBT to a file in the given encoding, keeping its PSBT version.
+pub fn write_file(psbt: &str, path: &str, encoding: PSBTEncoding) -> Result<PSBTFile, S5Error> {
+  let (psbt_struct, version) = parse_psbt_versioned(psbt)?;
+  let serialized = serialize_psbt(&psbt_struct, version)?;
+  let data = match encoding {
+    PSBTEncoding::Base64 => base64::encode(&serialized).into_bytes(),
+    PSBTEncoding::Hex => hex::encode(&serialized).into_bytes(),
+    PSBTEncoding::Binary => serialized,
+  };
+  match fs::write(path, &data) {
+    Ok(_) => Ok(PSBTFile {
+      path: path.to_string(),
+      encoding,
+      size: data.len(),
+    }),
+    Err(e) => Err(S5Error::new(ErrorKind::Internal, &e.to_string())),
+  }
+}
+
+/// Converts a PSBT between version 0 and version 2 (BIP370).
+pub fn convert(psbt: &str, version: u32) -> Result<WalletPSBT, S5Error> {
+  let psbt_struct = parse_psbt(psbt)?;
   Ok(WalletPSBT {
-    psbt: final_psbt.to_string(),
+    psbt: encode_psbt(&psbt_struct, version)?,
+    is_finalized: is_finalized(&psbt_struct),
+  })
+}
+
+fn is_input_finalized(input: &Input) -> bool {
+  input.final_script_sig.is_some() || input.final_script_witness.is_some()
+}
+
+fn is_finalized(psbt: &PartiallySignedTransaction) -> bool {
+  psbt.inputs.iter().all(is_input_finalized)
+}
+
+/// Merges copies of the same PSBT signed by different cosigners.
+/// Every copy must describe the same unsigned transaction. The result has the version of the first copy.
+pub fn combine(psbts: &[String]) -> Result<WalletPSBT, S5Error> {
+  let mut parsed = vec![];
+  for (index, psbt) in psbts.iter().enumerate() {
+    match parse_psbt_versioned(psbt) {
+      Ok(result) => parsed.push(result),
+      Err(_) => {
+        return Err(S5Error::new(
+          ErrorKind::Input,
+          &format!("Invalid PSBT at index {}.", index),
+        ))
+      }
+    }
+  }
+
+  let mut parsed = parsed.into_iter();
+  let (mut combined, version) = match parsed.next() {
+    Some(first) => first,
+    None => return Err(S5Error::new(ErrorKind::Input, "No PSBTs to combine.")),
+  };
+  let txid = combined.global.unsigned_tx.txid();
+
+  for (index, (psbt, _)) in parsed.enumerate() {
+    if psbt.global.unsigned_tx.txid() != txid {
+      return Err(S5Error::new(
+        ErrorKind::Input,
+        &format!("PSBT at index {} is for a different transaction.", index + 1),
+      ));
+    }
+    if let Err(e) = combined.merge(psbt) {
+      return Err(S5Error::new(ErrorKind::Input, &e.to_string()));
+    }
+  }
+
+  Ok(WalletPSBT {
+    is_finalized: is_finalized(&combined),
+    psbt: encode_psbt(&combined, version)?,
+  })
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FinalizedPSBT {
+  pub psbt: String,
+  pub is_finalized: bool,
+  pub tx: Option<String>,
+}
+
+impl FinalizedPSBT {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Runs the miniscript finalizer over every input.
+/// Returns the raw transaction hex once every input is satisfied, otherwise the PSBT with whichever inputs could be finalized.
+pub fn finalize(deposit_desc: &str, psbt: &str) -> Result<FinalizedPSBT, S5Error> {
+  let wallet = offline_wallet(deposit_desc)?;
+
+  let (mut psbt_struct, version) = parse_psbt_versioned(psbt)?;
+  let finalized = match wallet.finalize_psbt(&mut psbt_struct, SignOptions::default()) {
+    Ok(result) => result,
+    Err(e) => return Err(S5Error::new(ErrorKind::Wallet, &e.to_string())),
+  };
+
+  Ok(FinalizedPSBT {
+    psbt: encode_psbt(&psbt_struct, version)?,
     is_finalized: finalized,
+    tx: if finalized {
+      Some(serialize_hex(&psbt_struct.extract_tx()))
+    } else {
+      None
+    },
   })
 }
 
@@ -284,7 +914,9 @@ impl Txid {
   }
 }
 
-pub async fn broadcast(config: WalletConfig, psbt: &str) -> Result<Txid, S5Error> {
+#[cfg(feature = "online")]
+/// Runs precheck::check first and refuses to broadcast on any error unless force is set.
+pub async fn broadcast(config: WalletConfig, psbt: &str, force: bool) -> Result<Txid, S5Error> {
   let wallet = match Wallet::new(
     &config.deposit_desc,
     Some(&config.change_desc),
@@ -301,14 +933,11 @@ pub async fn broadcast(config: WalletConfig, psbt: &str) -> Result<Txid, S5Error
     Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Sync")),
   };
 
-  let decoded_psbt = match base64::decode(&psbt) {
-    Ok(result) => result,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "PSBT-Decode")),
-  };
-  let psbt_struct: PartiallySignedTransaction = match deserialize(&decoded_psbt) {
-    Ok(result) => result,
-    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "PSBT-Deserialize")),
-  };
+  let psbt_struct = parse_psbt(psbt)?;
+  let report = precheck::check_with_wallet(&wallet, &psbt_struct).await?;
+  if !report.can_broadcast && !force {
+    return Err(report.refusal());
+  }
   let tx = psbt_struct.extract_tx();
   let txid = match wallet.broadcast(&tx).await {
     Ok(result) => result,
@@ -320,6 +949,244 @@ pub async fn broadcast(config: WalletConfig, psbt: &str) -> Result<Txid, S5Error
   })
 }
 
+/// Broadcasts a raw transaction hex through the backend, without building or syncing a wallet.
+#[cfg(feature = "online")]
+pub async fn broadcast_raw(config: WalletConfig, tx_hex: &str) -> Result<Txid, S5Error> {
+  let tx = parse_raw_tx(tx_hex)?;
+  match config.client.broadcast(&tx).await {
+    Ok(_) => Ok(Txid {
+      txid: tx.txid().to_string(),
+    }),
+    Err(e) => Err(S5Error::new(ErrorKind::Network, &e.to_string())),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DEPOSIT_DESC: &str = "wpkh([db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)";
+  const PSBT: &str = "cHNidP8BAHQBAAAAAf3cLERUN9+6X5+1yk3x9XzSCq1417WtB+gB5qNyj+xpAAAAAAD9////AnRxAQAAAAAAFgAUVyorkNVSCsiE4/7OspP52IwquzqIEwAAAAAAABl2qRQ0Sg9IyhUOwrkDgXZgubaLE6ZwJoisAAAAAAABAN4CAAAAAAEByvn9X3PvFqemGsrTv8ivAO07IOeRhBz7J0huqXJLfVgBAAAAAP7///8CoIYBAAAAAAAWABQTXAMs/1Qr5n6pDVK9O15ODZ/UCVZWjQAAAAAAFgAUIixaISTPlO8fwyT3hCL+An5+Km4CRzBEAiBFsQJfBur3eQgO5Vw+EvEgr2CagcVGXw9oYw3FOaMSSgIgch0CV+W3oRCKNBwxqiqIK0C5b1TsGk32HvNM+4Z7IksBIQNP/rsBHKbA98977TzmriFrOuO8hQjNg4ON3goI9/Uwjp0BIAABAR+ghgEAAAAAABYAFBNcAyz/VCvmfqkNUr07Xk4Nn9QJIgYD9WhlKKSeNh6567KTmyKrlitDWZOz/+mms7emVsWjGTsY230ltVQAAIABAACABgAAgAAAAAABAAAAACICAgHPrE7CShQkK90ApPF8xdr+8o7T/sHggOlZNOHIUft/GNt9JbVUAACAAQAAgAYAAIABAAAAAQAAAAAA";
+
+  #[test]
+  fn test_decode() {
+    let decoded = decode(Network::Testnet, PSBT, Some(DEPOSIT_DESC)).unwrap();
+    assert_eq!(decoded.inputs.len(), 1);
+    assert_eq!(decoded.inputs[0].value, Some(100_000));
+    assert!(decoded.inputs[0].is_mine);
+    assert_eq!(decoded.inputs[0].sequence, 0xFFFFFFFD);
+    assert_eq!(decoded.inputs[0].sighash_type, "SIGHASH_ALL");
+    assert!(decoded.inputs[0].signed_by.is_empty());
+    assert!(decoded.rbf);
+
+    let change = &decoded.outputs[0];
+    assert!(change.is_mine && change.is_change);
+    let recipient = &decoded.outputs[1];
+    assert_eq!(recipient.address, "mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt");
+    assert_eq!(recipient.value, 5_000);
+    assert!(!recipient.is_mine && !recipient.is_change);
+
+    assert_eq!(decoded.fee, Some(420));
+    assert_eq!(decoded.vsize, Some(144));
+    assert!(decoded.fee_rate.unwrap() > 2.9);
+
+    let without_desc = decode(Network::Testnet, PSBT, None).unwrap();
+    assert_eq!(without_desc.fee, Some(420));
+    assert_eq!(without_desc.fee_rate, None);
+    assert!(!without_desc.outputs[0].is_mine);
+  }
+
+  #[test]
+  fn test_decode_legacy_input() {
+    let mut psbt_struct: PartiallySignedTransaction = deserialize(&base64::decode(PSBT).unwrap()).unwrap();
+    psbt_struct.inputs[0].witness_utxo = None;
+    let non_witness_only = psbt_struct.to_string();
+    let decoded = decode(Network::Testnet, &non_witness_only, None).unwrap();
+    assert_eq!(decoded.inputs[0].value, Some(100_000));
+    assert_eq!(decoded.fee, Some(420));
+
+    // a non_witness_utxo which is not the spent transaction cannot set the fee shown
+    let mut forged = psbt_struct.clone();
+    let mut other_tx = forged.inputs[0].non_witness_utxo.clone().unwrap();
+    other_tx.output[0].value = 1_000_000;
+    forged.inputs[0].non_witness_utxo = Some(other_tx);
+    let error = decode(Network::Testnet, &forged.to_string(), None).err().unwrap();
+    assert_eq!(error.message, "Input 0 has a utxo which does not match its previous outpoint.");
+    let mut both = parse_psbt(PSBT).unwrap();
+    both.inputs[0].witness_utxo.as_mut().unwrap().value = 1_000_000;
+    assert!(decode(Network::Testnet, &both.to_string(), None).is_err());
+
+    let mut overflow = parse_psbt(PSBT).unwrap();
+    overflow.global.unsigned_tx.output[1].value = u64::MAX;
+    let error = decode(Network::Testnet, &overflow.to_string(), None).err().unwrap();
+    assert_eq!(error.message, "PSBT values add up to more than 2^64 sats.");
+
+    psbt_struct.inputs[0].non_witness_utxo = None;
+    let missing_utxo = psbt_struct.to_string();
+    let decoded = decode(Network::Testnet, &missing_utxo, Some(DEPOSIT_DESC)).unwrap();
+    assert_eq!(decoded.inputs[0].value, None);
+    assert!(!decoded.inputs[0].is_mine);
+    assert_eq!(decoded.fee, None);
+  }
+
+  #[test]
+  fn test_combine_and_finalize() {
+    let signing_desc = "wpkh([db7d25b5/84'/1'/6']tprv8fWev2sCuSkVWYoNUUSEuqLkmmfiZaVtgxosS5jRE9fw5ejL2odsajv1QyiLrPri3ppgyta6dsFaoDVCF4ZdEAR6qqY4tnaosujsPzLxB49/0/*)";
+    let signed = sign(signing_desc, PSBT, WalletSignOptions::default()).unwrap();
+    assert!(signed.is_finalized);
+
+    // keep only the partial signature, as a cosigner would send it
+    let mut partial = parse_psbt(&signed.psbt).unwrap();
+    partial.inputs[0].final_script_sig = None;
+    partial.inputs[0].final_script_witness = None;
+    assert_eq!(partial.inputs[0].partial_sigs.len(), 1);
+
+    let combined = combine(&[PSBT.to_string(), partial.to_string()]).unwrap();
+    assert!(!combined.is_finalized);
+    assert_eq!(decode(Network::Testnet, &combined.psbt, None).unwrap().inputs[0].signed_by, vec!["db7d25b5"]);
+
+    let finalized = finalize(DEPOSIT_DESC, &combined.psbt).unwrap();
+    assert!(finalized.is_finalized);
+    let tx: Transaction = deserialize(&hex::decode(finalized.tx.unwrap()).unwrap()).unwrap();
+    assert_eq!(tx.txid(), parse_psbt(PSBT).unwrap().global.unsigned_tx.txid());
+    assert_eq!(tx.input[0].witness.len(), 2);
+
+    let unsigned = finalize(DEPOSIT_DESC, PSBT).unwrap();
+    assert!(!unsigned.is_finalized);
+    assert_eq!(unsigned.tx, None);
+
+    let mut other = parse_psbt(PSBT).unwrap();
+    other.global.unsigned_tx.lock_time = 1;
+    let error = combine(&[PSBT.to_string(), other.to_string()]).err().unwrap();
+    assert_eq!(error.message, "PSBT at index 1 is for a different transaction.");
+    assert_eq!(combine(&[PSBT.to_string(), "cHNidP8".to_string()]).err().unwrap().message, "Invalid PSBT at index 1.");
+    assert_eq!(combine(&[]).err().unwrap().message, "No PSBTs to combine.");
+  }
+
+  #[test]
+  fn test_sign_options() {
+    let signing_desc = "wpkh([db7d25b5/84'/1'/6']tprv8fWev2sCuSkVWYoNUUSEuqLkmmfiZaVtgxosS5jRE9fw5ejL2odsajv1QyiLrPri3ppgyta6dsFaoDVCF4ZdEAR6qqY4tnaosujsPzLxB49/0/*)";
+
+    let options = WalletSignOptions { try_finalize: false, ..Default::default() };
+    let signed = sign(signing_desc, PSBT, options).unwrap();
+    assert!(!signed.is_finalized);
+    assert_eq!(parse_psbt(&signed.psbt).unwrap().inputs[0].partial_sigs.len(), 1);
+
+    let options = WalletSignOptions { remove_partial_sigs: true, ..Default::default() };
+    let signed = sign(signing_desc, PSBT, options).unwrap();
+    assert!(signed.is_finalized);
+    assert!(parse_psbt(&signed.psbt).unwrap().inputs[0].partial_sigs.is_empty());
+
+    let mut witness_only = parse_psbt(PSBT).unwrap();
+    witness_only.inputs[0].non_witness_utxo = None;
+    let error = sign(signing_desc, &witness_only.to_string(), WalletSignOptions::default()).err().unwrap();
+    assert_eq!(error.kind, ErrorKind::Input.to_string());
+    assert!(error.message.starts_with("Missing non_witness_utxo."));
+    let options = WalletSignOptions { trust_witness_utxo: true, ..Default::default() };
+    assert!(sign(signing_desc, &witness_only.to_string(), options).unwrap().is_finalized);
+
+    let mut single = parse_psbt(PSBT).unwrap();
+    single.inputs[0].sighash_type = Some(SigHashType::Single);
+    let error = sign(signing_desc, &single.to_string(), WalletSignOptions::default()).err().unwrap();
+    assert!(error.message.starts_with("Non-standard sighash."));
+    let options = WalletSignOptions { allow_all_sighashes: true, ..Default::default() };
+    assert!(sign(signing_desc, &single.to_string(), options).is_ok());
+
+    let other_key = "wpkh([c0e7f2b1/84'/1'/0']tprv8fWev2sCuSkVWYoNUUSEuqLkmmfiZaVtgxosS5jRE9fw5ejL2odsajv1QyiLrPri3ppgyta6dsFaoDVCF4ZdEAR6qqY4tnaosujsPzLxB49/0/*)";
+    let error = sign(other_key, PSBT, WalletSignOptions::default()).err().unwrap();
+    assert_eq!(error.message, "No key in the descriptor can sign this PSBT.");
+
+    // signing twice with the same key is not an error
+    let options = WalletSignOptions { try_finalize: false, ..Default::default() };
+    let signed = sign(signing_desc, PSBT, options.clone()).unwrap();
+    assert!(sign(signing_desc, &signed.psbt, options).is_ok());
+
+    let parsed: WalletSignOptions = serde_json::from_str("{\"assume_height\":700000}").unwrap();
+    assert_eq!(parsed.assume_height, Some(700000));
+    assert!(parsed.try_finalize);
+  }
+
+  #[test]
+  fn test_psbt_v2() {
+    let v2 = convert(PSBT, 2).unwrap().psbt;
+    assert_ne!(v2, PSBT);
+    assert_eq!(parse_psbt_versioned(&v2).unwrap().1, 2);
+    assert_eq!(convert(&v2, 0).unwrap().psbt, PSBT);
+    assert_eq!(convert(PSBT, 1).err().unwrap().message, "Unsupported PSBT Version.");
+
+    let decoded = decode(Network::Testnet, &v2, Some(DEPOSIT_DESC)).unwrap();
+    assert_eq!(decoded.txid, decode(Network::Testnet, PSBT, None).unwrap().txid);
+    assert_eq!(decoded.fee, Some(420));
+    assert_eq!(get_weight(DEPOSIT_DESC, &v2).unwrap().weight, get_weight(DEPOSIT_DESC, PSBT).unwrap().weight);
+
+    let signing_desc = "wpkh([db7d25b5/84'/1'/6']tprv8fWev2sCuSkVWYoNUUSEuqLkmmfiZaVtgxosS5jRE9fw5ejL2odsajv1QyiLrPri3ppgyta6dsFaoDVCF4ZdEAR6qqY4tnaosujsPzLxB49/0/*)";
+    let signed = sign(signing_desc, &v2, WalletSignOptions::default()).unwrap();
+    assert!(signed.is_finalized);
+    assert_eq!(parse_psbt_versioned(&signed.psbt).unwrap().1, 2);
+
+    let options = WalletSignOptions { try_finalize: false, ..Default::default() };
+    let partial = sign(signing_desc, &v2, options).unwrap();
+    let combined = combine(&[v2.clone(), partial.psbt]).unwrap();
+    assert_eq!(parse_psbt_versioned(&combined.psbt).unwrap().1, 2);
+    assert_eq!(combine(&[PSBT.to_string(), v2.clone()]).unwrap().psbt, PSBT);
+
+    let finalized = finalize(DEPOSIT_DESC, &combined.psbt).unwrap();
+    assert!(finalized.is_finalized);
+    assert_eq!(parse_psbt_versioned(&finalized.psbt).unwrap().1, 2);
+    assert_eq!(finalize(DEPOSIT_DESC, &v2).unwrap().psbt, v2);
+  }
+
+  #[test]
+  fn test_psbt_encodings() {
+    let binary = base64::decode(PSBT).unwrap();
+    let hex_psbt = hex::encode(&binary);
+    assert_eq!(parse_psbt(&hex_psbt).unwrap(), parse_psbt(PSBT).unwrap());
+    assert_eq!(parse_psbt(&format!("{}\n", hex_psbt.to_uppercase())).unwrap(), parse_psbt(PSBT).unwrap());
+    assert_eq!(parse_psbt_data(&binary).unwrap().0, parse_psbt(PSBT).unwrap());
+    assert_eq!(parse_psbt("not a psbt").err().unwrap().message, "PSBT-Decode");
+    assert_eq!(parse_psbt("aéééééééé").err().unwrap().message, "PSBT-Decode");
+    assert_eq!(parse_psbt("70736274ff00").err().unwrap().message, "PSBT-Deserialize");
+
+    let dir = std::env::temp_dir().join("stackmate-psbt-files");
+    fs::create_dir_all(&dir).unwrap();
+    for (encoding, name) in [(PSBTEncoding::Binary, "tx.psbt"), (PSBTEncoding::Hex, "tx.hex"), (PSBTEncoding::Base64, "tx.txt")] {
+      let path = dir.join(name).to_str().unwrap().to_string();
+      let written = write_file(PSBT, &path, encoding).unwrap();
+      assert_eq!(written.encoding, encoding);
+      assert_eq!(read_file(&path).unwrap().psbt, PSBT);
+    }
+    assert_eq!(fs::read(dir.join("tx.psbt")).unwrap(), binary);
+
+    let v2 = convert(PSBT, 2).unwrap().psbt;
+    let path = dir.join("v2.psbt").to_str().unwrap().to_string();
+    write_file(&v2, &path, PSBTEncoding::Binary).unwrap();
+    assert_eq!(read_file(&path).unwrap().psbt, v2);
+    assert_eq!(read_file("/nonexistent/tx.psbt").err().unwrap().message, "PSBT-File-Read");
+  }
+
+  #[test]
+  fn test_decode_raw_tx() {
+    let psbt = parse_psbt(PSBT).unwrap();
+    let previous_tx = psbt.inputs[0].non_witness_utxo.clone().unwrap();
+    let decoded = decode_raw_tx(Network::Testnet, &serialize_hex(&previous_tx)).unwrap();
+    assert_eq!(decoded.txid, psbt.global.unsigned_tx.input[0].previous_output.txid.to_string());
+    assert_ne!(decoded.wtxid, decoded.txid);
+    assert!(!decoded.rbf);
+    assert_eq!(decoded.inputs[0].witness_items, 2);
+    assert_eq!(decoded.outputs[0].value, 100_000);
+    assert_eq!(decoded.outputs[0].address, Some("tb1qzdwqxt8l2s47vl4fp4ft6w67fcxel4qf5j96ld".to_string()));
+    assert_eq!(decoded.weight, previous_tx.get_weight());
+    assert!(decoded.vsize < decoded.size);
+
+    let unsigned = decode_raw_tx(Network::Testnet, &serialize_hex(&psbt.global.unsigned_tx)).unwrap();
+    assert!(unsigned.rbf);
+    assert!(unsigned.inputs[0].rbf);
+    assert_eq!(unsigned.outputs.len(), 2);
+    assert_eq!(decode_raw_tx(Network::Testnet, "zz").err().unwrap().message, "Transaction-Hex");
+    assert_eq!(decode_raw_tx(Network::Testnet, "0200").err().unwrap().message, "Transaction-Deserialize");
+  }
+}
+
 // #[cfg(test)]
 // mod tests {
 //   use super::*;
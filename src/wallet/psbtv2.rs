@@ -0,0 +1,381 @@
+// PSBT version 2 (BIP370) support.
+// rust-bitcoin only reads version 0, so v2 PSBTs are converted at the key-value map level:
+// the per-input and per-output transaction fields are moved into (or out of) a global unsigned tx.
+// Every other field is copied through unchanged.
+use std::io::Cursor;
+
+use bitcoin::blockdata::script::Script;
+use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxIn, TxOut};
+use bitcoin::consensus::encode::{deserialize, serialize, Decodable, Encodable, VarInt};
+use bitcoin::hashes::Hash;
+use bitcoin::Txid;
+
+use crate::e::{ErrorKind, S5Error};
+
+const PSBT_MAGIC: &[u8; 5] = b"psbt\xff";
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_GLOBAL_TX_VERSION: u8 = 0x02;
+const PSBT_GLOBAL_FALLBACK_LOCKTIME: u8 = 0x03;
+const PSBT_GLOBAL_INPUT_COUNT: u8 = 0x04;
+const PSBT_GLOBAL_OUTPUT_COUNT: u8 = 0x05;
+const PSBT_GLOBAL_TX_MODIFIABLE: u8 = 0x06;
+const PSBT_GLOBAL_VERSION: u8 = 0xFB;
+
+const PSBT_IN_PREVIOUS_TXID: u8 = 0x0e;
+const PSBT_IN_OUTPUT_INDEX: u8 = 0x0f;
+const PSBT_IN_SEQUENCE: u8 = 0x10;
+const PSBT_IN_REQUIRED_TIME_LOCKTIME: u8 = 0x11;
+const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u8 = 0x12;
+
+const PSBT_OUT_AMOUNT: u8 = 0x03;
+const PSBT_OUT_SCRIPT: u8 = 0x04;
+
+/// Lowest locktime value interpreted as a unix timestamp rather than a block height.
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// A single key-value pair. key holds the type byte followed by the key data.
+#[derive(Debug, Clone, PartialEq)]
+struct Pair {
+  key: Vec<u8>,
+  value: Vec<u8>,
+}
+
+impl Pair {
+  fn new(key_type: u8, value: Vec<u8>) -> Self {
+    Pair {
+      key: vec![key_type],
+      value,
+    }
+  }
+  fn key_type(&self) -> u8 {
+    self.key[0]
+  }
+}
+
+type Map = Vec<Pair>;
+
+fn format_error() -> S5Error {
+  S5Error::new(ErrorKind::Input, "PSBT-Deserialize")
+}
+
+fn read_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Vec<u8>, S5Error> {
+  let len = match VarInt::consensus_decode(&mut *cursor) {
+    Ok(result) => result.0 as usize,
+    Err(_) => return Err(format_error()),
+  };
+  let start = cursor.position() as usize;
+  let data: &[u8] = cursor.get_ref();
+  let end = match start.checked_add(len).filter(|end| *end <= data.len()) {
+    Some(end) => end,
+    None => return Err(format_error()),
+  };
+  let bytes = data[start..end].to_vec();
+  cursor.set_position(end as u64);
+  Ok(bytes)
+}
+
+fn read_map(cursor: &mut Cursor<&[u8]>) -> Result<Map, S5Error> {
+  let mut map = vec![];
+  loop {
+    let key = read_bytes(cursor)?;
+    if key.is_empty() {
+      return Ok(map);
+    }
+    let value = read_bytes(cursor)?;
+    map.push(Pair { key, value });
+  }
+}
+
+fn write_map(out: &mut Vec<u8>, map: &[Pair]) {
+  for pair in map {
+    VarInt(pair.key.len() as u64).consensus_encode(&mut *out).unwrap();
+    out.extend_from_slice(&pair.key);
+    VarInt(pair.value.len() as u64).consensus_encode(&mut *out).unwrap();
+    out.extend_from_slice(&pair.value);
+  }
+  out.push(0x00);
+}
+
+fn find(map: &[Pair], key_type: u8) -> Option<&[u8]> {
+  map
+    .iter()
+    .find(|pair| pair.key == [key_type])
+    .map(|pair| pair.value.as_slice())
+}
+
+fn find_u32(map: &[Pair], key_type: u8) -> Result<Option<u32>, S5Error> {
+  match find(map, key_type) {
+    Some(value) => match deserialize::<u32>(value) {
+      Ok(result) => Ok(Some(result)),
+      Err(_) => Err(format_error()),
+    },
+    None => Ok(None),
+  }
+}
+
+fn find_count(map: &[Pair], key_type: u8) -> Result<usize, S5Error> {
+  match find(map, key_type) {
+    Some(value) => match deserialize::<VarInt>(value) {
+      Ok(result) => Ok(result.0 as usize),
+      Err(_) => Err(format_error()),
+    },
+    None => Err(format_error()),
+  }
+}
+
+/// Reads the PSBT_GLOBAL_VERSION of a serialized PSBT. Absent means version 0.
+pub fn version(psbt: &[u8]) -> Result<u32, S5Error> {
+  if !psbt.starts_with(PSBT_MAGIC) {
+    return Err(format_error());
+  }
+  let mut cursor = Cursor::new(&psbt[PSBT_MAGIC.len()..]);
+  let global = read_map(&mut cursor)?;
+  Ok(find_u32(&global, PSBT_GLOBAL_VERSION)?.unwrap_or(0))
+}
+
+/// BIP370 locktime: the max of the required locktimes of a type every locking input supports,
+/// preferring height; otherwise the fallback locktime.
+fn locktime(global: &[Pair], inputs: &[Map]) -> Result<u32, S5Error> {
+  let mut heights = vec![];
+  let mut times = vec![];
+  let mut all_height = true;
+  let mut all_time = true;
+  let mut any = false;
+  for input in inputs {
+    let height = find_u32(input, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME)?;
+    let time = find_u32(input, PSBT_IN_REQUIRED_TIME_LOCKTIME)?;
+    if height.is_none() && time.is_none() {
+      continue;
+    }
+    any = true;
+    match height {
+      Some(value) => heights.push(value),
+      None => all_height = false,
+    }
+    match time {
+      Some(value) => times.push(value),
+      None => all_time = false,
+    }
+  }
+  if !any {
+    return Ok(find_u32(global, PSBT_GLOBAL_FALLBACK_LOCKTIME)?.unwrap_or(0));
+  }
+  if all_height {
+    Ok(heights.into_iter().max().unwrap_or(0))
+  } else if all_time {
+    Ok(times.into_iter().max().unwrap_or(0))
+  } else {
+    Err(S5Error::new(ErrorKind::Input, "PSBT inputs require incompatible locktimes."))
+  }
+}
+
+/// Converts a serialized version 2 PSBT into version 0.
+pub fn to_v0(psbt: &[u8]) -> Result<Vec<u8>, S5Error> {
+  if version(psbt)? != 2 {
+    return Err(S5Error::new(ErrorKind::Input, "PSBT is not version 2."));
+  }
+  let mut cursor = Cursor::new(&psbt[PSBT_MAGIC.len()..]);
+  let global = read_map(&mut cursor)?;
+  let input_count = find_count(&global, PSBT_GLOBAL_INPUT_COUNT)?;
+  let output_count = find_count(&global, PSBT_GLOBAL_OUTPUT_COUNT)?;
+  let inputs = (0..input_count)
+    .map(|_| read_map(&mut cursor))
+    .collect::<Result<Vec<Map>, S5Error>>()?;
+  let outputs = (0..output_count)
+    .map(|_| read_map(&mut cursor))
+    .collect::<Result<Vec<Map>, S5Error>>()?;
+
+  let version = match find(&global, PSBT_GLOBAL_TX_VERSION) {
+    Some(value) => match deserialize::<i32>(value) {
+      Ok(result) => result,
+      Err(_) => return Err(format_error()),
+    },
+    None => return Err(format_error()),
+  };
+
+  let mut tx_inputs = vec![];
+  for input in inputs.iter() {
+    let txid = match find(input, PSBT_IN_PREVIOUS_TXID).map(Txid::from_slice) {
+      Some(Ok(result)) => result,
+      _ => return Err(format_error()),
+    };
+    let vout = match find_u32(input, PSBT_IN_OUTPUT_INDEX)? {
+      Some(result) => result,
+      None => return Err(format_error()),
+    };
+    tx_inputs.push(TxIn {
+      previous_output: OutPoint { txid, vout },
+      script_sig: Script::new(),
+      sequence: find_u32(input, PSBT_IN_SEQUENCE)?.unwrap_or(0xFFFFFFFF),
+      witness: vec![],
+    });
+  }
+
+  let mut tx_outputs = vec![];
+  for output in outputs.iter() {
+    let value = match find(output, PSBT_OUT_AMOUNT).map(deserialize::<u64>) {
+      Some(Ok(result)) => result,
+      _ => return Err(format_error()),
+    };
+    let script_pubkey = match find(output, PSBT_OUT_SCRIPT) {
+      Some(script) => Script::from(script.to_vec()),
+      None => return Err(format_error()),
+    };
+    tx_outputs.push(TxOut { value, script_pubkey });
+  }
+
+  let unsigned_tx = Transaction {
+    version,
+    lock_time: locktime(&global, &inputs)?,
+    input: tx_inputs,
+    output: tx_outputs,
+  };
+
+  let v2_global = [
+    PSBT_GLOBAL_TX_VERSION,
+    PSBT_GLOBAL_FALLBACK_LOCKTIME,
+    PSBT_GLOBAL_INPUT_COUNT,
+    PSBT_GLOBAL_OUTPUT_COUNT,
+    PSBT_GLOBAL_TX_MODIFIABLE,
+    PSBT_GLOBAL_VERSION,
+  ];
+  let v2_input = [
+    PSBT_IN_PREVIOUS_TXID,
+    PSBT_IN_OUTPUT_INDEX,
+    PSBT_IN_SEQUENCE,
+    PSBT_IN_REQUIRED_TIME_LOCKTIME,
+    PSBT_IN_REQUIRED_HEIGHT_LOCKTIME,
+  ];
+  let v2_output = [PSBT_OUT_AMOUNT, PSBT_OUT_SCRIPT];
+
+  let mut out = PSBT_MAGIC.to_vec();
+  let mut v0_global = vec![Pair::new(PSBT_GLOBAL_UNSIGNED_TX, serialize(&unsigned_tx))];
+  v0_global.extend(global.into_iter().filter(|pair| !v2_global.contains(&pair.key_type())));
+  write_map(&mut out, &v0_global);
+  for input in inputs {
+    let kept: Map = input.into_iter().filter(|pair| !v2_input.contains(&pair.key_type())).collect();
+    write_map(&mut out, &kept);
+  }
+  for output in outputs {
+    let kept: Map = output.into_iter().filter(|pair| !v2_output.contains(&pair.key_type())).collect();
+    write_map(&mut out, &kept);
+  }
+  Ok(out)
+}
+
+/// Converts a serialized version 0 PSBT into version 2.
+pub fn to_v2(psbt: &[u8]) -> Result<Vec<u8>, S5Error> {
+  if version(psbt)? != 0 {
+    return Err(S5Error::new(ErrorKind::Input, "PSBT is not version 0."));
+  }
+  let mut cursor = Cursor::new(&psbt[PSBT_MAGIC.len()..]);
+  let global = read_map(&mut cursor)?;
+  let unsigned_tx: Transaction = match find(&global, PSBT_GLOBAL_UNSIGNED_TX).map(deserialize) {
+    Some(Ok(result)) => result,
+    _ => return Err(format_error()),
+  };
+  let inputs = (0..unsigned_tx.input.len())
+    .map(|_| read_map(&mut cursor))
+    .collect::<Result<Vec<Map>, S5Error>>()?;
+  let outputs = (0..unsigned_tx.output.len())
+    .map(|_| read_map(&mut cursor))
+    .collect::<Result<Vec<Map>, S5Error>>()?;
+
+  let mut out = PSBT_MAGIC.to_vec();
+  let mut v2_global = vec![
+    Pair::new(PSBT_GLOBAL_TX_VERSION, serialize(&unsigned_tx.version)),
+    Pair::new(PSBT_GLOBAL_FALLBACK_LOCKTIME, serialize(&unsigned_tx.lock_time)),
+    Pair::new(PSBT_GLOBAL_INPUT_COUNT, serialize(&VarInt(unsigned_tx.input.len() as u64))),
+    Pair::new(PSBT_GLOBAL_OUTPUT_COUNT, serialize(&VarInt(unsigned_tx.output.len() as u64))),
+  ];
+  v2_global.extend(
+    global
+      .into_iter()
+      .filter(|pair| pair.key_type() != PSBT_GLOBAL_UNSIGNED_TX && pair.key_type() != PSBT_GLOBAL_VERSION),
+  );
+  v2_global.push(Pair::new(PSBT_GLOBAL_VERSION, serialize(&2u32)));
+  write_map(&mut out, &v2_global);
+
+  for (txin, input) in unsigned_tx.input.iter().zip(inputs) {
+    let mut map = vec![
+      Pair::new(PSBT_IN_PREVIOUS_TXID, txin.previous_output.txid.to_vec()),
+      Pair::new(PSBT_IN_OUTPUT_INDEX, serialize(&txin.previous_output.vout)),
+      Pair::new(PSBT_IN_SEQUENCE, serialize(&txin.sequence)),
+    ];
+    if unsigned_tx.lock_time > 0 {
+      let required = if unsigned_tx.lock_time < LOCKTIME_THRESHOLD {
+        PSBT_IN_REQUIRED_HEIGHT_LOCKTIME
+      } else {
+        PSBT_IN_REQUIRED_TIME_LOCKTIME
+      };
+      map.push(Pair::new(required, serialize(&unsigned_tx.lock_time)));
+    }
+    map.extend(input);
+    write_map(&mut out, &map);
+  }
+  for (txout, output) in unsigned_tx.output.iter().zip(outputs) {
+    let mut map = vec![
+      Pair::new(PSBT_OUT_AMOUNT, serialize(&txout.value)),
+      Pair::new(PSBT_OUT_SCRIPT, txout.script_pubkey.to_bytes()),
+    ];
+    map.extend(output);
+    write_map(&mut out, &map);
+  }
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bitcoin::base64;
+  use bitcoin::util::psbt::PartiallySignedTransaction;
+
+  const PSBT: &str = "cHNidP8BAHQBAAAAAf3cLERUN9+6X5+1yk3x9XzSCq1417WtB+gB5qNyj+xpAAAAAAD9////AnRxAQAAAAAAFgAUVyorkNVSCsiE4/7OspP52IwquzqIEwAAAAAAABl2qRQ0Sg9IyhUOwrkDgXZgubaLE6ZwJoisAAAAAAABAN4CAAAAAAEByvn9X3PvFqemGsrTv8ivAO07IOeRhBz7J0huqXJLfVgBAAAAAP7///8CoIYBAAAAAAAWABQTXAMs/1Qr5n6pDVK9O15ODZ/UCVZWjQAAAAAAFgAUIixaISTPlO8fwyT3hCL+An5+Km4CRzBEAiBFsQJfBur3eQgO5Vw+EvEgr2CagcVGXw9oYw3FOaMSSgIgch0CV+W3oRCKNBwxqiqIK0C5b1TsGk32HvNM+4Z7IksBIQNP/rsBHKbA98977TzmriFrOuO8hQjNg4ON3goI9/Uwjp0BIAABAR+ghgEAAAAAABYAFBNcAyz/VCvmfqkNUr07Xk4Nn9QJIgYD9WhlKKSeNh6567KTmyKrlitDWZOz/+mms7emVsWjGTsY230ltVQAAIABAACABgAAgAAAAAABAAAAACICAgHPrE7CShQkK90ApPF8xdr+8o7T/sHggOlZNOHIUft/GNt9JbVUAACAAQAAgAYAAIABAAAAAQAAAAAA";
+
+  #[test]
+  fn test_round_trip() {
+    let v0 = base64::decode(PSBT).unwrap();
+    assert_eq!(version(&v0).unwrap(), 0);
+
+    let v2 = to_v2(&v0).unwrap();
+    assert_eq!(version(&v2).unwrap(), 2);
+    // rust-bitcoin cannot read v2 directly
+    assert!(deserialize::<PartiallySignedTransaction>(&v2).is_err());
+
+    let back = to_v0(&v2).unwrap();
+    assert_eq!(back, v0);
+    assert_eq!(to_v0(&v0).err().unwrap().message, "PSBT is not version 2.");
+    assert_eq!(to_v2(&v2).err().unwrap().message, "PSBT is not version 0.");
+  }
+
+  #[test]
+  fn test_oversized_length() {
+    // version key followed by a value length of u64::MAX
+    let psbt = b"psbt\xff\x01\xfb\xff\xff\xff\xff\xff\xff\xff\xff";
+    assert_eq!(version(psbt).err().unwrap().message, "PSBT-Deserialize");
+    assert!(to_v0(psbt).is_err());
+    assert!(to_v2(psbt).is_err());
+  }
+
+  #[test]
+  fn test_locktime() {
+    let mut v0: PartiallySignedTransaction = deserialize(&base64::decode(PSBT).unwrap()).unwrap();
+    v0.global.unsigned_tx.lock_time = 700_000;
+    let v2 = to_v2(&serialize(&v0)).unwrap();
+    let back: PartiallySignedTransaction = deserialize(&to_v0(&v2).unwrap()).unwrap();
+    assert_eq!(back.global.unsigned_tx.lock_time, 700_000);
+
+    let mut cursor = Cursor::new(&v2[PSBT_MAGIC.len()..]);
+    let global = read_map(&mut cursor).unwrap();
+    let mut input = read_map(&mut cursor).unwrap();
+    assert_eq!(locktime(&global, &[input.clone()]).unwrap(), 700_000);
+
+    input.retain(|pair| pair.key_type() != PSBT_IN_REQUIRED_HEIGHT_LOCKTIME);
+    input.push(Pair::new(PSBT_IN_REQUIRED_TIME_LOCKTIME, serialize(&1_600_000_000u32)));
+    let other = vec![Pair::new(PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, serialize(&1u32))];
+    assert!(locktime(&global, &[input.clone(), other]).is_err());
+    assert_eq!(locktime(&global, &[input]).unwrap(), 1_600_000_000);
+    assert_eq!(locktime(&global, &[vec![]]).unwrap(), 700_000);
+  }
+}
@@ -0,0 +1,424 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "online")]
+use crate::config::WalletConfig;
+use crate::e::{ErrorKind, S5Error};
+#[cfg(feature = "online")]
+use crate::wallet::history;
+use crate::wallet::history::{Transaction, TxDirection, TxStatus, WalletHistory};
+#[cfg(feature = "online")]
+use crate::wallet::store::WalletStore;
+
+const SATS_PER_BTC: u64 = 100_000_000;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+  Csv,
+  Koinly,
+  CoinTracker,
+}
+
+impl FromStr for ReportFormat {
+  type Err = S5Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "csv" => Ok(ReportFormat::Csv),
+      "koinly" => Ok(ReportFormat::Koinly),
+      "cointracker" => Ok(ReportFormat::CoinTracker),
+      _ => Err(S5Error::new(ErrorKind::Input, "Invalid Report Format.")),
+    }
+  }
+}
+
+/// price is the value of 1 BTC in currency at timestamp.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PricePoint {
+  pub timestamp: u64,
+  pub price: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PriceSeries {
+  pub currency: String,
+  pub prices: Vec<PricePoint>,
+}
+
+impl PriceSeries {
+  /// Uses the latest price at or before timestamp, falling back to the earliest price.
+  /// Pending transactions (timestamp 0) use the latest price in the series.
+  pub fn price_at(&self, timestamp: u64) -> Option<f64> {
+    if timestamp == 0 {
+      return self.prices.iter().max_by_key(|point| point.timestamp).map(|point| point.price);
+    }
+    self
+      .prices
+      .iter()
+      .filter(|point| point.timestamp <= timestamp)
+      .max_by_key(|point| point.timestamp)
+      .or_else(|| self.prices.iter().min_by_key(|point| point.timestamp))
+      .map(|point| point.price)
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HistoryReport {
+  pub format: ReportFormat,
+  pub rows: usize,
+  pub data: String,
+}
+
+impl HistoryReport {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+/// Formats sats as a BTC decimal string with 8 places.
+pub fn sats_to_btc(sats: i64) -> String {
+  let sign = if sats < 0 { "-" } else { "" };
+  let sats = sats.unsigned_abs();
+  format!("{}{}.{:08}", sign, sats / SATS_PER_BTC, sats % SATS_PER_BTC)
+}
+
+/// Converts days since the unix epoch to a (year, month, day) civil date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+  (year, month, day)
+}
+
+/// (year, month, day, hour, minute, second) in UTC
+fn utc_parts(timestamp: u64) -> (i64, u32, u32, u64, u64, u64) {
+  let (year, month, day) = civil_from_days((timestamp / 86_400) as i64);
+  let seconds = timestamp % 86_400;
+  (year, month, day, seconds / 3_600, (seconds % 3_600) / 60, seconds % 60)
+}
+
+fn escape(field: &str) -> String {
+  if field.contains(',') || field.contains('"') || field.contains('\n') || field.contains('\r') {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
+
+fn row(fields: &[String]) -> String {
+  fields
+    .iter()
+    .map(|field| escape(field))
+    .collect::<Vec<String>>()
+    .join(",")
+}
+
+fn direction_str(direction: TxDirection) -> &'static str {
+  match direction {
+    TxDirection::Incoming => "incoming",
+    TxDirection::Outgoing => "outgoing",
+    TxDirection::SelfTransfer => "self_transfer",
+    TxDirection::Consolidation => "consolidation",
+  }
+}
+
+/// Sats that left (negative) or entered (positive) the wallet, excluding the fee.
+fn amount_without_fee(tx: &Transaction) -> i64 {
+  let net = tx.received as i64 - tx.sent as i64;
+  match tx.direction {
+    TxDirection::Outgoing => net + tx.fee as i64,
+    TxDirection::SelfTransfer | TxDirection::Consolidation => 0,
+    TxDirection::Incoming => net,
+  }
+}
+
+fn fiat_value(sats: i64, price: Option<f64>) -> String {
+  match price {
+    Some(price) => format!("{:.2}", sats.unsigned_abs() as f64 / SATS_PER_BTC as f64 * price),
+    None => "".to_string(),
+  }
+}
+
+fn csv(history: &[&Transaction], prices: Option<&PriceSeries>) -> String {
+  let mut header = [
+    "Date", "Txid", "Type", "Status", "Amount (BTC)", "Amount (sats)", "Fee (BTC)", "Fee (sats)", "Label",
+  ]
+  .iter()
+  .map(|field| field.to_string())
+  .collect::<Vec<String>>();
+  if let Some(series) = prices {
+    header.push(format!("Value ({})", series.currency));
+  }
+
+  let mut lines = vec![row(&header)];
+  for tx in history {
+    let net = tx.received as i64 - tx.sent as i64;
+    let date = if tx.status == TxStatus::Pending {
+      "".to_string()
+    } else {
+      let (year, month, day, hour, minute, second) = utc_parts(tx.timestamp);
+      format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+    };
+    let mut fields = vec![
+      date,
+      tx.txid.clone(),
+      direction_str(tx.direction).to_string(),
+      match tx.status {
+        TxStatus::Pending => "pending".to_string(),
+        TxStatus::Confirmed => "confirmed".to_string(),
+      },
+      sats_to_btc(net),
+      net.to_string(),
+      sats_to_btc(tx.fee as i64),
+      tx.fee.to_string(),
+      tx.label.clone().unwrap_or_default(),
+    ];
+    if let Some(series) = prices {
+      fields.push(fiat_value(net, series.price_at(tx.timestamp)));
+    }
+    lines.push(row(&fields));
+  }
+  lines.join("\n")
+}
+
+/// Koinly universal format. Fee only transfers are reported as a "cost".
+fn koinly(history: &[&Transaction], prices: Option<&PriceSeries>) -> String {
+  let header = [
+    "Date", "Sent Amount", "Sent Currency", "Received Amount", "Received Currency", "Fee Amount",
+    "Fee Currency", "Net Worth Amount", "Net Worth Currency", "Label", "Description", "TxHash",
+  ]
+  .iter()
+  .map(|field| field.to_string())
+  .collect::<Vec<String>>();
+
+  let mut lines = vec![row(&header)];
+  for tx in history {
+    let (year, month, day, hour, minute, _) = utc_parts(tx.timestamp);
+    let amount = amount_without_fee(tx);
+    let price = prices.and_then(|series| series.price_at(tx.timestamp));
+    let (sent, received, fee, label) = match tx.direction {
+      TxDirection::Incoming => ("".to_string(), sats_to_btc(amount), "".to_string(), ""),
+      TxDirection::Outgoing => (sats_to_btc(-amount), "".to_string(), sats_to_btc(tx.fee as i64), ""),
+      TxDirection::SelfTransfer | TxDirection::Consolidation => {
+        (sats_to_btc(tx.fee as i64), "".to_string(), "".to_string(), "cost")
+      }
+    };
+    let worth = match tx.direction {
+      TxDirection::SelfTransfer | TxDirection::Consolidation => fiat_value(tx.fee as i64, price),
+      _ => fiat_value(amount, price),
+    };
+    lines.push(row(&[
+      format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, hour, minute),
+      sent.clone(),
+      if sent.is_empty() { "".to_string() } else { "BTC".to_string() },
+      received.clone(),
+      if received.is_empty() { "".to_string() } else { "BTC".to_string() },
+      fee.clone(),
+      if fee.is_empty() { "".to_string() } else { "BTC".to_string() },
+      worth.clone(),
+      match (prices, worth.is_empty()) {
+        (Some(series), false) => series.currency.clone(),
+        _ => "".to_string(),
+      },
+      label.to_string(),
+      tx.label.clone().unwrap_or_default(),
+      tx.txid.clone(),
+    ]));
+  }
+  lines.join("\n")
+}
+
+/// CoinTracker import format. CoinTracker fetches fiat values itself.
+fn cointracker(history: &[&Transaction]) -> String {
+  let header = [
+    "Date", "Received Quantity", "Received Currency", "Sent Quantity", "Sent Currency", "Fee Amount",
+    "Fee Currency", "Tag",
+  ]
+  .iter()
+  .map(|field| field.to_string())
+  .collect::<Vec<String>>();
+
+  let mut lines = vec![row(&header)];
+  for tx in history {
+    let (year, month, day, hour, minute, second) = utc_parts(tx.timestamp);
+    let amount = amount_without_fee(tx);
+    let (received, sent, fee) = match tx.direction {
+      TxDirection::Incoming => (sats_to_btc(amount), "".to_string(), "".to_string()),
+      TxDirection::Outgoing => ("".to_string(), sats_to_btc(-amount), sats_to_btc(tx.fee as i64)),
+      TxDirection::SelfTransfer | TxDirection::Consolidation => {
+        ("".to_string(), sats_to_btc(tx.fee as i64), "".to_string())
+      }
+    };
+    lines.push(row(&[
+      format!("{:02}/{:02}/{:04} {:02}:{:02}:{:02}", month, day, year, hour, minute, second),
+      received.clone(),
+      if received.is_empty() { "".to_string() } else { "BTC".to_string() },
+      sent.clone(),
+      if sent.is_empty() { "".to_string() } else { "BTC".to_string() },
+      fee.clone(),
+      if fee.is_empty() { "".to_string() } else { "BTC".to_string() },
+      "".to_string(),
+    ]));
+  }
+  lines.join("\n")
+}
+
+/// Formats wallet history oldest first. Pending transactions are only included in the csv format.
+pub fn format(history: &WalletHistory, report_format: ReportFormat, prices: Option<&PriceSeries>) -> HistoryReport {
+  let mut sorted: Vec<&Transaction> = history
+    .history
+    .iter()
+    .filter(|tx| report_format == ReportFormat::Csv || tx.status == TxStatus::Confirmed)
+    .collect();
+  sorted.sort_by_key(|tx| (tx.status == TxStatus::Pending, tx.timestamp));
+
+  let data = match report_format {
+    ReportFormat::Csv => csv(&sorted, prices),
+    ReportFormat::Koinly => koinly(&sorted, prices),
+    ReportFormat::CoinTracker => cointracker(&sorted),
+  };
+
+  HistoryReport {
+    format: report_format,
+    rows: sorted.len(),
+    data,
+  }
+}
+
+#[cfg(feature = "online")]
+pub async fn sync_report(
+  config: WalletConfig,
+  store: &WalletStore,
+  report_format: ReportFormat,
+  prices: Option<PriceSeries>,
+) -> Result<HistoryReport, S5Error> {
+  let history = history::sync_history(config, store).await?;
+  Ok(format(&history, report_format, prices.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn transaction(
+    txid: &str,
+    timestamp: u64,
+    received: u64,
+    sent: u64,
+    fee: u64,
+    direction: TxDirection,
+    label: Option<&str>,
+  ) -> Transaction {
+    Transaction {
+      timestamp,
+      height: if timestamp == 0 { 0 } else { 700_000 },
+      status: if timestamp == 0 {
+        TxStatus::Pending
+      } else {
+        TxStatus::Confirmed
+      },
+      confirmations: if timestamp == 0 { 0 } else { 1 },
+      verified: false,
+      txid: txid.to_string(),
+      received,
+      sent,
+      fee,
+      vsize: 141,
+      fee_rate: fee as f32 / 141.0,
+      rbf: false,
+      direction,
+      inputs: vec![],
+      outputs: vec![],
+      label: label.map(|label| label.to_string()),
+    }
+  }
+
+  fn history() -> WalletHistory {
+    WalletHistory {
+      history: vec![
+        transaction("bb", 1_640_995_200, 0, 100_000, 1_000, TxDirection::Outgoing, Some("Rent, January")),
+        transaction("aa", 1_609_459_200, 250_000, 0, 0, TxDirection::Incoming, Some("Salary \"Q4\"")),
+        transaction("cc", 0, 148_500, 150_000, 1_500, TxDirection::Consolidation, None),
+      ],
+    }
+  }
+
+  #[test]
+  fn test_dates_and_amounts() {
+    assert_eq!(utc_parts(0), (1970, 1, 1, 0, 0, 0));
+    assert_eq!(utc_parts(1_609_459_200), (2021, 1, 1, 0, 0, 0));
+    assert_eq!(utc_parts(951_827_696), (2000, 2, 29, 12, 34, 56));
+    assert_eq!(sats_to_btc(250_000), "0.00250000");
+    assert_eq!(sats_to_btc(-101_000), "-0.00101000");
+    assert_eq!(sats_to_btc(2_100_000_000_000_000), "21000000.00000000");
+  }
+
+  #[test]
+  fn test_csv_report() {
+    let prices = PriceSeries {
+      currency: "USD".to_string(),
+      prices: vec![
+        PricePoint { timestamp: 1_609_400_000, price: 29_000.0 },
+        PricePoint { timestamp: 1_640_000_000, price: 47_000.0 },
+      ],
+    };
+    let report = format(&history(), ReportFormat::Csv, Some(&prices));
+    let lines: Vec<&str> = report.data.lines().collect();
+    assert_eq!(report.rows, 3);
+    assert_eq!(
+      lines[0],
+      "Date,Txid,Type,Status,Amount (BTC),Amount (sats),Fee (BTC),Fee (sats),Label,Value (USD)"
+    );
+    assert_eq!(
+      lines[1],
+      "2021-01-01T00:00:00Z,aa,incoming,confirmed,0.00250000,250000,0.00000000,0,\"Salary \"\"Q4\"\"\",72.50"
+    );
+    assert_eq!(
+      lines[2],
+      "2022-01-01T00:00:00Z,bb,outgoing,confirmed,-0.00100000,-100000,0.00001000,1000,\"Rent, January\",47.00"
+    );
+    assert_eq!(lines[3], ",cc,consolidation,pending,-0.00001500,-1500,0.00001500,1500,,0.71");
+
+    let report = format(&history(), ReportFormat::Csv, None);
+    assert!(report.data.lines().next().unwrap().ends_with(",Label"));
+  }
+
+  #[test]
+  fn test_accounting_reports() {
+    let report = format(&history(), ReportFormat::Koinly, None);
+    let lines: Vec<&str> = report.data.lines().collect();
+    assert_eq!(report.rows, 2);
+    assert_eq!(lines[1], "2021-01-01 00:00 UTC,,,0.00250000,BTC,,,,,,\"Salary \"\"Q4\"\"\",aa");
+    assert_eq!(
+      lines[2],
+      "2022-01-01 00:00 UTC,0.00099000,BTC,,,0.00001000,BTC,,,,\"Rent, January\",bb"
+    );
+
+    let report = format(&history(), ReportFormat::CoinTracker, None);
+    let lines: Vec<&str> = report.data.lines().collect();
+    assert_eq!(lines[1], "01/01/2021 00:00:00,0.00250000,BTC,,,,,");
+    assert_eq!(lines[2], "01/01/2022 00:00:00,,,0.00099000,BTC,0.00001000,BTC,");
+
+    assert!(ReportFormat::from_str("xls").is_err());
+  }
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::fs::File;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use bdk::descriptor::Descriptor;
+use bdk::miniscript::descriptor::DescriptorPublicKey;
+use bitcoin::blockdata::transaction::OutPoint;
+use bitcoin::secp256k1::Secp256k1;
+
+use crate::e::{ErrorKind, S5Error};
+use crate::wallet::descriptor;
+use crate::wallet::labels::{Label, LabelType};
+
+/// Local wallet metadata which cannot be recovered from the chain.
+/// Persisted as JSON at db_path/<wallet_id>.json
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WalletStore {
+  #[serde(default)]
+  pub frozen: BTreeSet<String>,
+  #[serde(default)]
+  pub labels: Vec<Label>,
+  /// Utxo labels of stores saved before BIP329 labels. Moved into labels on load.
+  #[serde(default, skip_serializing)]
+  utxo_labels: BTreeMap<String, String>,
+  /// Highest receive index given out by get_new_address or get_unused_address.
+  #[serde(default)]
+  pub last_revealed: Option<u32>,
+  /// Highest receive index with a transaction, as of the last address request.
+  #[serde(default)]
+  pub last_used: Option<u32>,
+}
+
+/// Identifies a wallet by its public deposit descriptor.
+/// Private and public versions of the same descriptor share an id.
+pub fn wallet_id(deposit_desc: &str) -> Result<String, S5Error> {
+  let secp = Secp256k1::new();
+  let desc = descriptor::strip_checksum(deposit_desc)?;
+  let public_desc = match Descriptor::<DescriptorPublicKey>::parse_descriptor(&secp, desc) {
+    Ok((public_desc, _)) => public_desc.to_string(),
+    Err(_) => desc.to_string(),
+  };
+  let public_desc = descriptor::strip_checksum(&public_desc)?;
+  Ok(hex::encode(&Sha1::digest(public_desc.as_bytes())[..8]))
+}
+
+impl WalletStore {
+  fn path(db_path: &str, deposit_desc: &str) -> Result<PathBuf, S5Error> {
+    if db_path.is_empty() {
+      return Err(S5Error::new(ErrorKind::Input, "Storage-Path"));
+    }
+    let mut path = PathBuf::from(db_path);
+    path.push(format!("{}.json", wallet_id(deposit_desc)?));
+    Ok(path)
+  }
+
+  /// Loads the store for a wallet. Returns an empty store if none has been saved yet.
+  pub fn load(db_path: &str, deposit_desc: &str) -> Result<WalletStore, S5Error> {
+    if db_path.is_empty() {
+      return Ok(WalletStore::default());
+    }
+    let path = WalletStore::path(db_path, deposit_desc)?;
+    if !path.exists() {
+      return Ok(WalletStore::default());
+    }
+    let file = match File::open(path) {
+      Ok(file) => file,
+      Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Storage-Open")),
+    };
+    let mut store: WalletStore = match serde_json::from_reader(file) {
+      Ok(result) => result,
+      Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Storage-Parse")),
+    };
+    let utxo_labels = std::mem::take(&mut store.utxo_labels);
+    for (outpoint, label) in utxo_labels {
+      if store.get_label(LabelType::Output, &outpoint).is_none() {
+        store.set_label(Label {
+          label_type: LabelType::Output,
+          reference: outpoint,
+          label: Some(label),
+          origin: None,
+          spendable: None,
+        });
+      }
+    }
+    Ok(store)
+  }
+
+  pub fn save(&self, db_path: &str, deposit_desc: &str) -> Result<(), S5Error> {
+    let path = WalletStore::path(db_path, deposit_desc)?;
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Storage-Stringify")),
+    };
+    // write a temporary file and rename it over the store, so a crash cannot leave a partial store
+    let temp_path = path.with_extension("json.tmp");
+    if let Err(e) = fs::write(&temp_path, stringified) {
+      return Err(S5Error::new(ErrorKind::Internal, &e.to_string()));
+    }
+    match fs::rename(&temp_path, path) {
+      Ok(_) => Ok(()),
+      Err(e) => Err(S5Error::new(ErrorKind::Internal, &e.to_string())),
+    }
+  }
+
+  pub fn is_frozen(&self, outpoint: &OutPoint) -> bool {
+    self.frozen.contains(&outpoint.to_string())
+  }
+
+  pub fn frozen_outpoints(&self) -> Vec<OutPoint> {
+    self
+      .frozen
+      .iter()
+      .filter_map(|outpoint| OutPoint::from_str(outpoint).ok())
+      .collect()
+  }
+
+  pub fn get_label(&self, label_type: LabelType, reference: &str) -> Option<String> {
+    self
+      .labels
+      .iter()
+      .find(|label| label.label_type == label_type && label.reference == reference)
+      .and_then(|label| label.label.clone())
+  }
+
+  /// Replaces any label with the same type and ref. A label without text is removed.
+  /// spendable is applied to the frozen set rather than stored on the label.
+  pub fn set_label(&mut self, label: Label) {
+    if label.label_type == LabelType::Output {
+      match label.spendable {
+        Some(false) => {
+          self.frozen.insert(label.reference.clone());
+        }
+        Some(true) => {
+          self.frozen.remove(&label.reference);
+        }
+        None => (),
+      }
+    }
+    self
+      .labels
+      .retain(|existing| !(existing.label_type == label.label_type && existing.reference == label.reference));
+    if label.label.is_some() {
+      self.labels.push(Label {
+        spendable: None,
+        ..label
+      });
+    }
+  }
+
+  pub fn utxo_label(&self, outpoint: &OutPoint) -> Option<String> {
+    self.get_label(LabelType::Output, &outpoint.to_string())
+  }
+
+  /// All labels as BIP329 records, including frozen utxos which have no label.
+  pub fn bip329_labels(&self) -> Vec<Label> {
+    let mut labels: Vec<Label> = self
+      .labels
+      .iter()
+      .map(|label| Label {
+        spendable: if label.label_type == LabelType::Output && self.frozen.contains(&label.reference) {
+          Some(false)
+        } else {
+          None
+        },
+        ..label.clone()
+      })
+      .collect();
+
+    for outpoint in self.frozen.iter() {
+      if self.get_label(LabelType::Output, outpoint).is_none() {
+        labels.push(Label {
+          label_type: LabelType::Output,
+          reference: outpoint.to_string(),
+          label: None,
+          origin: None,
+          spendable: Some(false),
+        });
+      }
+    }
+    labels
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_wallet_id() {
+    let xprv_desc = "wpkh([db7d25b5/84'/1'/6']tprv8fWev2sCuSkVWYoNUUSEuqLkmmfiZaVtgxosS5jRE9fw5ejL2odsajv1QyiLrPri3ppgyta6dsFaoDVCF4ZdEAR6qqY4tnaosujsPzLxB49/0/*)";
+    let xpub_desc = "wpkh([db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)";
+    assert_eq!(wallet_id(xprv_desc).unwrap(), wallet_id(xpub_desc).unwrap());
+    assert_eq!(wallet_id(xpub_desc).unwrap().len(), 16);
+    let checksummed = descriptor::checksum(xpub_desc).unwrap().descriptor;
+    assert_eq!(wallet_id(&checksummed).unwrap(), wallet_id(xpub_desc).unwrap());
+    let bad = format!("{}#{}", xpub_desc, "qqqqqqqq");
+    assert_eq!(wallet_id(&bad).err().unwrap().message, "Invalid Descriptor Checksum.");
+  }
+
+  #[test]
+  fn test_store_persistence() {
+    let xpub_desc = "wpkh([db7d25b5/84'/1'/6']tpubDCCh4SuT3pSAQ1qAN86qKEzsLoBeiugoGGQeibmieRUKv8z6fCTTmEXsb9yeueBkUWjGVzJr91bCzeCNShorbBqjZV4WRGjz3CrJsCboXUe/0/*)";
+    let outpoint = "69ec8f72a3e601e807adb5d778ad0ad27cf5f14dcab59f5fbadf3754442cdcfd:0";
+    let db_path = std::env::temp_dir().join("stackmate-test-store");
+    fs::create_dir_all(&db_path).unwrap();
+    let db_path = db_path.to_str().unwrap();
+
+    let mut store = WalletStore::load(db_path, xpub_desc).unwrap();
+    store.frozen.clear();
+    store.frozen.insert(outpoint.to_string());
+    store.set_label(Label {
+      label_type: LabelType::Output,
+      reference: outpoint.to_string(),
+      label: Some("exchange".to_string()),
+      origin: None,
+      spendable: None,
+    });
+    store.save(db_path, xpub_desc).unwrap();
+
+    let loaded = WalletStore::load(db_path, xpub_desc).unwrap();
+    let outpoint = OutPoint::from_str(outpoint).unwrap();
+    assert!(loaded.is_frozen(&outpoint));
+    assert_eq!(loaded.frozen_outpoints(), vec![outpoint]);
+    assert_eq!(loaded.utxo_label(&outpoint), Some("exchange".to_string()));
+
+    fs::write(
+      WalletStore::path(db_path, xpub_desc).unwrap(),
+      format!(
+        r#"{{"frozen":["{}"],"utxo_labels":{{"{}":"cold storage"}}}}"#,
+        outpoint, outpoint
+      ),
+    )
+    .unwrap();
+    let migrated = WalletStore::load(db_path, xpub_desc).unwrap();
+    assert!(migrated.is_frozen(&outpoint));
+    assert_eq!(migrated.utxo_label(&outpoint), Some("cold storage".to_string()));
+    migrated.save(db_path, xpub_desc).unwrap();
+    let saved = fs::read_to_string(WalletStore::path(db_path, xpub_desc).unwrap()).unwrap();
+    assert!(!saved.contains("utxo_labels"));
+    let reloaded = WalletStore::load(db_path, xpub_desc).unwrap();
+    assert_eq!(reloaded.utxo_label(&outpoint), Some("cold storage".to_string()));
+
+    let store_path = WalletStore::path(db_path, xpub_desc).unwrap();
+    assert!(!store_path.with_extension("json.tmp").exists());
+    assert_eq!(store_path.extension().unwrap(), "json");
+
+    let empty = WalletStore::load("", xpub_desc).unwrap();
+    assert!(empty.frozen.is_empty());
+    assert_eq!(empty.save("", xpub_desc).err().unwrap().message, "Storage-Path");
+  }
+}
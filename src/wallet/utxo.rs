@@ -0,0 +1,205 @@
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "online")]
+use bdk::blockchain::noop_progress;
+#[cfg(feature = "online")]
+use bdk::database::MemoryDatabase;
+#[cfg(feature = "online")]
+use bdk::Wallet;
+use bdk::{KeychainKind, LocalUtxo};
+
+use bitcoin::blockdata::transaction::OutPoint;
+use bitcoin::network::constants::Network;
+use bitcoin::util::address::Address;
+
+#[cfg(feature = "online")]
+use crate::config::WalletConfig;
+use crate::e::{ErrorKind, S5Error};
+use crate::wallet::labels::{Label, LabelType};
+use crate::wallet::store::WalletStore;
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletUtxo {
+  pub outpoint: String,
+  pub value: u64,
+  pub address: String,
+  pub keychain: String,
+  pub frozen: bool,
+  pub label: Option<String>,
+}
+
+impl WalletUtxo {
+  pub fn from_local_utxo(utxo: &LocalUtxo, network: Network, store: &WalletStore) -> Self {
+    WalletUtxo {
+      outpoint: utxo.outpoint.to_string(),
+      value: utxo.txout.value,
+      address: match Address::from_script(&utxo.txout.script_pubkey, network) {
+        Some(address) => address.to_string(),
+        None => "None".to_string(),
+      },
+      keychain: match utxo.keychain {
+        KeychainKind::External => "external".to_string(),
+        KeychainKind::Internal => "internal".to_string(),
+      },
+      frozen: store.is_frozen(&utxo.outpoint),
+      label: store.utxo_label(&utxo.outpoint),
+    }
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WalletUtxos {
+  pub utxos: Vec<WalletUtxo>,
+}
+
+impl WalletUtxos {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+#[cfg(feature = "online")]
+pub async fn list_unspent(config: WalletConfig, store: &WalletStore) -> Result<WalletUtxos, S5Error> {
+  let network = config.network;
+  let wallet = match Wallet::new(
+    &config.deposit_desc,
+    Some(&config.change_desc),
+    config.network,
+    MemoryDatabase::default(),
+    config.client,
+  ).await {
+    Ok(result) => result,
+    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Initialization")),
+  };
+
+  match wallet.sync(noop_progress(), None).await {
+    Ok(_) => (),
+    Err(_) => return Err(S5Error::new(ErrorKind::Internal, "Wallet-Sync")),
+  };
+
+  match wallet.list_unspent() {
+    Ok(utxos) => Ok(WalletUtxos {
+      utxos: utxos
+        .iter()
+        .map(|utxo| WalletUtxo::from_local_utxo(utxo, network, store))
+        .collect(),
+    }),
+    Err(e) => Err(S5Error::new(ErrorKind::Internal, &e.to_string())),
+  }
+}
+
+/// FFI Output
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UtxoStatus {
+  pub outpoint: String,
+  pub frozen: bool,
+  pub label: Option<String>,
+}
+
+impl UtxoStatus {
+  pub fn c_stringify(&self) -> *mut c_char {
+    let stringified = match serde_json::to_string(self) {
+      Ok(result) => result,
+      Err(_) => {
+        return CString::new("Error:JSON Stringify Failed. BAD NEWS! Contact Support.")
+          .unwrap()
+          .into_raw()
+      }
+    };
+
+    CString::new(stringified).unwrap().into_raw()
+  }
+}
+
+fn parse_outpoint(outpoint: &str) -> Result<OutPoint, S5Error> {
+  match OutPoint::from_str(outpoint) {
+    Ok(result) => Ok(result),
+    Err(_) => Err(S5Error::new(ErrorKind::Input, "Invalid Outpoint (txid:vout).")),
+  }
+}
+
+/// Marks a utxo as do not spend. Frozen utxos are excluded from coin selection and sweeps.
+pub fn freeze(db_path: &str, deposit_desc: &str, outpoint: &str, frozen: bool) -> Result<UtxoStatus, S5Error> {
+  let outpoint = parse_outpoint(outpoint)?;
+  let mut store = WalletStore::load(db_path, deposit_desc)?;
+  if frozen {
+    store.frozen.insert(outpoint.to_string());
+  } else {
+    store.frozen.remove(&outpoint.to_string());
+  }
+  store.save(db_path, deposit_desc)?;
+
+  Ok(UtxoStatus {
+    outpoint: outpoint.to_string(),
+    frozen: store.is_frozen(&outpoint),
+    label: store.utxo_label(&outpoint),
+  })
+}
+
+/// Attaches a note to a utxo. An empty label removes the existing note.
+pub fn label(db_path: &str, deposit_desc: &str, outpoint: &str, label: &str) -> Result<UtxoStatus, S5Error> {
+  let outpoint = parse_outpoint(outpoint)?;
+  let mut store = WalletStore::load(db_path, deposit_desc)?;
+  store.set_label(Label {
+    label_type: LabelType::Output,
+    reference: outpoint.to_string(),
+    label: if label.is_empty() {
+      None
+    } else {
+      Some(label.to_string())
+    },
+    origin: None,
+    spendable: None,
+  });
+  store.save(db_path, deposit_desc)?;
+
+  Ok(UtxoStatus {
+    outpoint: outpoint.to_string(),
+    frozen: store.is_frozen(&outpoint),
+    label: store.utxo_label(&outpoint),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+
+  #[test]
+  fn test_coin_control() {
+    let deposit_desc = "wpkh([66a0c105/84h/1h/5h]tpubDCKvnVh6U56wTSUEJGamQzdb3ByAc6gTPbjxXQqts5Bf1dBMopknipUUSmAV3UuihKPTddruSZCiqhyiYyhFWhz62SAGuC3PYmtAafUuG6R/0/*)";
+    let outpoint = "69ec8f72a3e601e807adb5d778ad0ad27cf5f14dcab59f5fbadf3754442cdcfd:1";
+    let db_path = std::env::temp_dir().join("stackmate-test-utxo");
+    fs::create_dir_all(&db_path).unwrap();
+    let db_path = db_path.to_str().unwrap();
+
+    let status = freeze(db_path, deposit_desc, outpoint, true).unwrap();
+    assert!(status.frozen);
+    let status = label(db_path, deposit_desc, outpoint, "kyc exchange").unwrap();
+    assert!(status.frozen);
+    assert_eq!(status.label, Some("kyc exchange".to_string()));
+
+    let status = freeze(db_path, deposit_desc, outpoint, false).unwrap();
+    assert!(!status.frozen);
+    let status = label(db_path, deposit_desc, outpoint, "").unwrap();
+    assert_eq!(status.label, None);
+
+    let error = freeze(db_path, deposit_desc, "notanoutpoint", true).err().unwrap();
+    assert_eq!(error.message, "Invalid Outpoint (txid:vout).");
+  }
+}